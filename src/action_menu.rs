@@ -0,0 +1,140 @@
+use crate::keymap::Command;
+use crate::metadata::Metadata;
+use crate::scanner::CruftDirectory;
+use base64::Engine;
+use std::io::Write;
+use std::path::Path;
+
+/// Something that can be done to the selected entry. Each has its own command too;
+/// the menu (`m`) is there so that they can be found without knowing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Inspect,
+    Delete,
+    ToggleIgnore,
+    IgnoreSimilar,
+    ToggleKeep,
+    EditNote,
+    ToggleBreakdown,
+    CopyPath,
+    Open,
+    Rescan,
+}
+
+impl Action {
+    /// The command that does the same outside the menu
+    pub fn command(&self) -> Command {
+        match self {
+            Action::Inspect => Command::Inspect,
+            Action::Delete => Command::Delete,
+            Action::ToggleIgnore => Command::Ignore,
+            Action::IgnoreSimilar => Command::IgnoreSimilar,
+            Action::ToggleKeep => Command::Keep,
+            Action::EditNote => Command::Note,
+            Action::ToggleBreakdown => Command::Breakdown,
+            Action::CopyPath => Command::CopyPath,
+            Action::Open => Command::Open,
+            Action::Rescan => Command::RescanEntry,
+        }
+    }
+
+    pub fn label(&self, dir: &CruftDirectory, metadata: &Metadata) -> &'static str {
+        match self {
+            Action::Inspect => "Why is this flagged?",
+            Action::Delete if dir.via_symlink.is_some() => "Delete (or remove the link)",
+            Action::Delete => "Delete",
+            Action::ToggleIgnore if metadata.is_ignored(dir) => "Stop ignoring",
+            Action::ToggleIgnore => "Ignore",
+            Action::IgnoreSimilar => "Ignore all like this...",
+            Action::ToggleKeep if metadata.is_kept(&dir.path) => "Unmark keep",
+            Action::ToggleKeep => "Mark keep",
+            Action::EditNote if metadata.note(&dir.path).is_some() => "Edit note",
+            Action::EditNote => "Add note",
+            Action::ToggleBreakdown => "Size breakdown",
+            Action::CopyPath => "Copy path",
+            Action::Open => "Open in file manager",
+            Action::Rescan => "Rescan it",
+        }
+    }
+}
+
+pub struct ActionMenu {
+    pub actions: Vec<Action>,
+    pub selected: usize,
+}
+
+impl ActionMenu {
+    /// The actions that make sense for `dir`
+    pub fn for_entry(dir: &CruftDirectory, metadata: &Metadata) -> Self {
+        let mut actions = vec![Action::Inspect];
+        // Kept entries can't be deleted until unmarked
+        if !metadata.is_kept(&dir.path) {
+            actions.push(Action::Delete);
+        }
+        actions.push(Action::ToggleIgnore);
+        if !metadata.is_ignored(dir) {
+            actions.push(Action::IgnoreSimilar);
+        }
+        actions.extend([Action::ToggleKeep, Action::EditNote]);
+        if !dir.pending && dir.members.is_empty() {
+            actions.push(Action::ToggleBreakdown);
+        }
+        actions.extend([Action::CopyPath, Action::Open]);
+        // Aggregates are only found by a full scan
+        if dir.members.is_empty() {
+            actions.push(Action::Rescan);
+        }
+        Self {
+            actions,
+            selected: 0,
+        }
+    }
+
+    pub fn select_next_or_previous(&mut self, forward: bool) {
+        let len = self.actions.len();
+        self.selected = if forward {
+            (self.selected + 1) % len
+        } else {
+            (self.selected + len - 1) % len
+        };
+    }
+
+    pub fn selected(&self) -> Action {
+        self.actions[self.selected]
+    }
+
+    /// The action that does `command`, if it's on the menu
+    pub fn find(&self, command: Command) -> Option<Action> {
+        self.actions
+            .iter()
+            .copied()
+            .find(|action| action.command() == command)
+    }
+}
+
+/// Puts `text` on the clipboard with an OSC 52 escape sequence, which most terminal
+/// emulators support, and which works over SSH too
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
+
+/// Opens `path` in the desktop's file manager, without waiting for it
+pub fn open_in_file_manager(path: &Path) -> std::io::Result<()> {
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(program)
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(drop)
+}
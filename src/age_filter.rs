@@ -6,6 +6,8 @@ pub enum AgeFilter {
     Days90,
     Days180,
     Days365,
+    /// A threshold from `--older-than`, the config, or typed in (`>`)
+    OlderThan(u64),
 }
 
 impl AgeFilter {
@@ -15,14 +17,13 @@ impl AgeFilter {
             AgeFilter::Days90 => Some(90),
             AgeFilter::Days180 => Some(180),
             AgeFilter::Days365 => Some(365),
+            AgeFilter::OlderThan(days) => Some(*days),
         }
     }
-    pub fn as_str(&self) -> &'static str {
+    pub fn label(&self) -> String {
         match self {
-            AgeFilter::None => "all",
-            AgeFilter::Days90 => "90 days",
-            AgeFilter::Days180 => "180 days",
-            AgeFilter::Days365 => "365 days",
+            AgeFilter::None => "all".to_string(),
+            days => format!("{} days", days.as_days().unwrap_or(0)),
         }
     }
 }
@@ -0,0 +1,126 @@
+use crate::output_format::OutputFormat;
+use crate::ownership;
+use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use crate::units::format_size;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where the users' home directories are
+#[cfg(target_os = "macos")]
+const HOMES_DIR: &str = "/Users";
+#[cfg(not(target_os = "macos"))]
+const HOMES_DIR: &str = "/home";
+
+/// How many kinds of cruft the text report names for each user
+const TOP_REASONS: usize = 3;
+
+/// The home directories under `/home` (`/Users` on macOS), each with the uid that owns it.
+/// Ones owned by root (`lost+found`, `/Users/Shared`) aren't anyone's home.
+pub fn homes() -> Vec<(u32, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(HOMES_DIR) else {
+        return Vec::new();
+    };
+    let mut homes: Vec<(u32, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|entry| {
+            let path = entry.path();
+            let uid = ownership::owner_uid(&path).filter(|&uid| uid != 0)?;
+            Some((uid, path))
+        })
+        .collect();
+    homes.sort_by(|a, b| a.1.cmp(&b.1));
+    homes
+}
+
+/// What was found in one user's home, all of which is attributed to them
+pub struct UserReport {
+    pub uid: u32,
+    pub home: PathBuf,
+    pub found: Vec<CruftDirectory>,
+}
+
+impl UserReport {
+    fn reclaimable(&self, size_mode: SizeMode) -> u64 {
+        self.found.iter().map(|dir| size_mode.size_of(dir)).sum()
+    }
+
+    /// Size per kind of cruft, biggest first
+    fn by_reason(&self, size_mode: SizeMode) -> Vec<(String, u64)> {
+        let mut sizes: HashMap<String, u64> = HashMap::new();
+        for dir in &self.found {
+            *sizes.entry(dir.crufty_reason.to_string()).or_default() += size_mode.size_of(dir);
+        }
+        let mut sizes: Vec<(String, u64)> = sizes.into_iter().collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sizes
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    user: String,
+    uid: u32,
+    home: &'a Path,
+    directories: usize,
+    reclaimable: u64,
+    by_reason: Vec<(String, u64)>,
+}
+
+/// Prints the per-user report, users with the most to reclaim first
+pub fn print(
+    mut reports: Vec<UserReport>,
+    format: OutputFormat,
+    size_mode: SizeMode,
+) -> Result<()> {
+    reports.sort_by_key(|report| std::cmp::Reverse(report.reclaimable(size_mode)));
+    let mut out = std::io::stdout().lock();
+    match format {
+        OutputFormat::Text => {
+            for report in &reports {
+                writeln!(
+                    out,
+                    "{} ({}): {} in {} directories",
+                    ownership::user_name(report.uid),
+                    report.home.display(),
+                    format_size(report.reclaimable(size_mode)),
+                    report.found.len()
+                )?;
+                for (reason, size) in report.by_reason(size_mode).into_iter().take(TOP_REASONS) {
+                    writeln!(out, "    {}: {}", reason, format_size(size))?;
+                }
+            }
+            let total: u64 = reports
+                .iter()
+                .map(|report| report.reclaimable(size_mode))
+                .sum();
+            writeln!(
+                out,
+                "Total: {} ({}) across {} users",
+                format_size(total),
+                size_mode.as_str(),
+                reports.len()
+            )?;
+        }
+        OutputFormat::Json => {
+            let reports: Vec<_> = reports
+                .iter()
+                .map(|report| JsonReport {
+                    user: ownership::user_name(report.uid),
+                    uid: report.uid,
+                    home: &report.home,
+                    directories: report.found.len(),
+                    reclaimable: report.reclaimable(size_mode),
+                    by_reason: report.by_reason(size_mode),
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut out, &reports)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
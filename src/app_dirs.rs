@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+/// The current user's home directory, if it can be determined.
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Where decruft looks for its configuration file.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(xdg).join("decruft"));
+    }
+    #[cfg(windows)]
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        return Some(PathBuf::from(appdata).join("decruft"));
+    }
+    home_dir().map(|home| home.join(".config").join("decruft"))
+}
+
+/// Directories decruft itself writes to, which scans leave alone: what's in them
+/// is never cruft, and must not be deleted behind decruft's back.
+pub fn own_dirs() -> Vec<PathBuf> {
+    let dirs = [
+        data_dir(),
+        config_dir(),
+        crate::archive::dir().map(Path::to_path_buf),
+    ];
+    let mut own: Vec<PathBuf> = dirs.into_iter().flatten().collect();
+    // Scanned paths may be reached through symlinks, or the other way around
+    own.extend(
+        own.iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect::<Vec<_>>(),
+    );
+    own
+}
+
+/// Where decruft keeps its own state (audit log, etc.),
+/// following the XDG base directory convention.
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(xdg) = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(xdg).join("decruft"));
+    }
+    #[cfg(windows)]
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        return Some(PathBuf::from(appdata).join("decruft"));
+    }
+    home_dir().map(|home| home.join(".local").join("share").join("decruft"))
+}
@@ -0,0 +1,169 @@
+use crate::audit::AuditLog;
+use crate::confirm_policy::{ConfirmPolicy, Risk};
+use crate::scanner::CruftDirectory;
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `[archive]`: what to pack up before deleting it, and where to
+static ARCHIVER: OnceLock<Archiver> = OnceLock::new();
+
+struct Archiver {
+    dir: PathBuf,
+    risks: Vec<Risk>,
+    policy: ConfirmPolicy,
+}
+
+/// Packs every entry at one of `risks` (as `policy` rates it) into a `.tar.zst` in `dir`
+/// before it is deleted, from here on
+pub fn enable(dir: PathBuf, risks: Vec<Risk>, policy: ConfirmPolicy) {
+    let _ = ARCHIVER.set(Archiver { dir, risks, policy });
+}
+
+/// Where archives go, if they are made at all
+pub fn dir() -> Option<&'static Path> {
+    ARCHIVER.get().map(|archiver| archiver.dir.as_path())
+}
+
+/// Archives `dir` if it is of a kind that is archived before deleting, recording where to
+/// in the audit log (if any), and returns the archive. Archiving is done by `tar --zstd`,
+/// so needs a `tar` that can do that and `zstd` on the `PATH`.
+pub fn archive_if_wanted(
+    dir: &CruftDirectory,
+    audit_log: Option<&AuditLog>,
+) -> Result<Option<PathBuf>> {
+    let Some(archiver) = ARCHIVER.get() else {
+        return Ok(None);
+    };
+    if !archiver.risks.contains(&archiver.policy.risk(dir)) {
+        return Ok(None);
+    }
+    // tar would read its own output, and the deletion would take the archive with it
+    if archiver.is_inside(dir) {
+        bail!(
+            "{} is where archives go, and is inside {}; not deleted",
+            archiver.dir.display(),
+            dir.path.display()
+        );
+    }
+    let archive = archiver
+        .create(dir)
+        .with_context(|| format!("Failed to archive {}; not deleted", dir.path.display()))?;
+    if let Some(audit_log) = audit_log {
+        audit_log.record_path("archived", dir, &archive, dir.size)?;
+    }
+    Ok(Some(archive))
+}
+
+impl Archiver {
+    /// Whether the archive directory is in what deleting `dir` deletes
+    fn is_inside(&self, dir: &CruftDirectory) -> bool {
+        let canonical = self.dir.canonicalize().ok();
+        let deleted = if dir.members.is_empty() {
+            std::slice::from_ref(&dir.path)
+        } else {
+            &dir.members
+        };
+        deleted.iter().any(|path| {
+            self.dir.starts_with(path)
+                || canonical
+                    .as_ref()
+                    .zip(path.canonicalize().ok())
+                    .is_some_and(|(canonical, path)| canonical.starts_with(path))
+        })
+    }
+
+    fn create(&self, dir: &CruftDirectory) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let archive = self.new_archive(&dir.path)?;
+        let result = pack(dir, &archive);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&archive);
+        }
+        result.map(|()| archive)
+    }
+
+    /// Claims an archive name of the form `<parent>-<name>-<seconds since the epoch>.tar.zst`
+    fn new_archive(&self, path: &Path) -> Result<PathBuf> {
+        let name_of = |path: Option<&Path>| {
+            path.and_then(Path::file_name)
+                .map_or("root".to_string(), |name| {
+                    name.to_string_lossy().into_owned()
+                })
+        };
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let stem = format!(
+            "{}-{}-{}",
+            name_of(path.parent()),
+            name_of(Some(path)),
+            secs
+        );
+        for n in 1.. {
+            let archive = match n {
+                1 => self.dir.join(format!("{}.tar.zst", stem)),
+                n => self.dir.join(format!("{}-{}.tar.zst", stem, n)),
+            };
+            match std::fs::File::create_new(&archive) {
+                Ok(_) => return Ok(archive),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to create {}", archive.display()));
+                }
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Runs `tar` on `dir` (or the files it is made of), relative to its parent (or itself)
+/// so that the archive unpacks to what was deleted. Names go in on stdin, so that there
+/// can be any number of them.
+fn pack(dir: &CruftDirectory, archive: &Path) -> Result<()> {
+    let (base, names): (&Path, Vec<&Path>) = if dir.members.is_empty() {
+        let name = dir.path.file_name().map_or(dir.path.as_path(), Path::new);
+        (dir.path.parent().unwrap_or(&dir.path), vec![name])
+    } else {
+        let names = dir
+            .members
+            .iter()
+            .map(|member| member.strip_prefix(&dir.path).unwrap_or(member))
+            .collect();
+        (&dir.path, names)
+    };
+    let mut tar = Command::new("tar")
+        .arg("--zstd")
+        .arg("-cf")
+        .arg(archive)
+        .arg("-C")
+        .arg(base)
+        .args(["--null", "-T", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Cannot run tar")?;
+    let mut stdin = tar.stdin.take().unwrap();
+    let written = names.iter().try_for_each(|name| {
+        stdin.write_all(name.as_os_str().as_encoded_bytes())?;
+        stdin.write_all(b"\0")
+    });
+    drop(stdin);
+    // tar may have given up before reading all the names (say, without --zstd support), in
+    // which case what it said is more use than the broken pipe
+    let output = tar.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    written.context("Failed to pass the names to tar")?;
+    Ok(())
+}
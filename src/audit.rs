@@ -0,0 +1,179 @@
+use crate::scanner::CruftDirectory;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Append-only, tab-separated log of destructive actions.
+///
+/// Deletions are logged when they start ("delete") and when they end ("deleted" or
+/// "delete failed"), so that one cut short by a crash or a kill can be finished later.
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+/// A deletion that was started but never finished, according to the audit log
+pub struct InterruptedDeletion {
+    pub started: String,
+    pub size: u64,
+    pub reason: String,
+    pub path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The default audit log location inside decruft's data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::data_dir().map(|dir| dir.join("audit.log"))
+    }
+
+    pub fn record(&self, event: &str, dir: &CruftDirectory) -> Result<()> {
+        self.write_line(event, &dir.crufty_reason.to_string(), &dir.path, dir.size)
+    }
+
+    /// Records an action on something other than `dir` itself, like the symlink through
+    /// which it was found or one of the files an aggregate entry is made of
+    pub fn record_path(
+        &self,
+        event: &str,
+        dir: &CruftDirectory,
+        path: &Path,
+        size: u64,
+    ) -> Result<()> {
+        self.write_line(event, &dir.crufty_reason.to_string(), path, size)
+    }
+
+    /// Records an action on something that isn't a scanned entry (any more), like a directory
+    /// restored from an earlier session's staging area
+    pub fn record_other(&self, event: &str, reason: &str, path: &Path, size: u64) -> Result<()> {
+        self.write_line(event, reason, path, size)
+    }
+
+    pub fn record_interrupted(&self, event: &str, deletion: &InterruptedDeletion) -> Result<()> {
+        self.write_line(event, &deletion.reason, &deletion.path, deletion.size)
+    }
+
+    /// Deletions by the current user that were started but neither finished nor given up
+    /// on, and whose target still exists, oldest first.
+    pub fn interrupted_deletions(&self) -> Result<Vec<InterruptedDeletion>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to open audit log {}", self.path.display()));
+            }
+        };
+        let user = current_user();
+        let mut started: HashMap<PathBuf, (usize, InterruptedDeletion)> = HashMap::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.splitn(6, '\t').collect();
+            let &[timestamp, line_user, event, size, reason, path] = fields.as_slice() else {
+                continue;
+            };
+            if line_user != user {
+                continue;
+            }
+            let path = PathBuf::from(path);
+            match event {
+                "delete" | "resume" => {
+                    let deletion = InterruptedDeletion {
+                        started: timestamp.to_string(),
+                        size: size.parse().unwrap_or(0),
+                        reason: reason.to_string(),
+                        path: path.clone(),
+                    };
+                    started.entry(path).or_insert((n, deletion));
+                }
+                "deleted" | "delete failed" | "abandon" => {
+                    started.remove(&path);
+                }
+                _ => {}
+            }
+        }
+        let mut interrupted: Vec<_> = started
+            .into_values()
+            .filter(|(_, deletion)| std::fs::symlink_metadata(&deletion.path).is_ok())
+            .collect();
+        interrupted.sort_by_key(|(n, _)| *n);
+        Ok(interrupted
+            .into_iter()
+            .map(|(_, deletion)| deletion)
+            .collect())
+    }
+
+    fn write_line(&self, event: &str, reason: &str, path: &Path, size: u64) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit log {}", self.path.display()))?;
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            format_timestamp(SystemTime::now()),
+            current_user(),
+            event,
+            size,
+            reason,
+            path.display()
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn current_user() -> String {
+    crate::ownership::user_name(rustix::process::geteuid().as_raw())
+}
+
+#[cfg(not(unix))]
+fn current_user() -> String {
+    std::env::var("USERNAME").unwrap_or_else(|_| "?".to_string())
+}
+
+/// The UTC year, month and day of a time in seconds since the epoch
+pub fn civil_date(secs: u64) -> (i64, i64, i64) {
+    let days = secs / 86400;
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp (second precision).
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let rem = secs % 86400;
+    let (year, month, day) = civil_date(secs);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,134 @@
+use crate::io_scheduler;
+use crate::scanner::allocated_size;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// Something inside a directory being browsed, with everything under it added up
+pub struct Child {
+    pub name: OsString,
+    pub is_dir: bool,
+    pub size: u64,
+    pub disk_usage: u64,
+}
+
+/// A du-style browser of what's inside an entry (Enter), to see what's taking up the space
+/// before deciding. The whole entry is sized in one walk in the background, which gives the
+/// contents of every directory in it, so that going deeper needs no further walks; the
+/// result is kept for the rest of the session.
+pub struct Browser {
+    pub root: PathBuf,
+    /// The directory being looked at, at or under `root`
+    pub current: PathBuf,
+    pub selected: usize,
+    receiver: Receiver<HashMap<PathBuf, Vec<Child>>>,
+    /// The contents of each directory under `root`, biggest first, once sized
+    listings: Option<HashMap<PathBuf, Vec<Child>>>,
+    /// Where the selection was in each directory above `current`, to return to it
+    trail: Vec<usize>,
+}
+
+impl Browser {
+    pub fn start(root: &Path) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn({
+            let root = root.to_path_buf();
+            move || {
+                let mut listings = HashMap::new();
+                io_scheduler::run(&root, || size_tree(&root, &mut listings));
+                let _ = sender.send(listings);
+            }
+        });
+        Self {
+            root: root.to_path_buf(),
+            current: root.to_path_buf(),
+            selected: 0,
+            receiver,
+            listings: None,
+            trail: Vec::new(),
+        }
+    }
+
+    /// Picks up the sizes if they have become ready
+    pub fn poll(&mut self) {
+        if self.listings.is_none() {
+            self.listings = self.receiver.try_recv().ok();
+        }
+    }
+
+    /// What's in the current directory, biggest first; `None` while it's still being sized
+    pub fn children(&self) -> Option<&[Child]> {
+        let listings = self.listings.as_ref()?;
+        Some(listings.get(&self.current).map_or(&[], Vec::as_slice))
+    }
+
+    pub fn select_next_or_previous(&mut self, next: bool) {
+        let count = self.children().map_or(0, <[Child]>::len);
+        if count == 0 {
+            return;
+        }
+        self.selected = if next {
+            (self.selected + 1) % count
+        } else {
+            (self.selected + count - 1) % count
+        };
+    }
+
+    /// Goes into the selected directory, if a directory is selected
+    pub fn enter(&mut self) {
+        let Some(child) = self
+            .children()
+            .and_then(|children| children.get(self.selected))
+        else {
+            return;
+        };
+        if !child.is_dir {
+            return;
+        }
+        self.current = self.current.join(&child.name);
+        self.trail.push(self.selected);
+        self.selected = 0;
+    }
+
+    /// Goes back up to the parent directory; false if already at the top
+    pub fn leave(&mut self) -> bool {
+        if self.current == self.root {
+            return false;
+        }
+        self.current.pop();
+        self.selected = self.trail.pop().unwrap_or(0);
+        true
+    }
+}
+
+/// Sizes everything under `dir`, recording the contents of each directory on the way,
+/// and returns its total size and disk usage. Symlinks are counted as themselves, not
+/// followed; what can't be read counts as empty.
+fn size_tree(dir: &Path, listings: &mut HashMap<PathBuf, Vec<Child>>) -> (u64, u64) {
+    let mut children = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let (size, disk_usage) = if metadata.is_dir() {
+                size_tree(&entry.path(), listings)
+            } else {
+                (metadata.len(), allocated_size(&metadata))
+            };
+            children.push(Child {
+                name: entry.file_name(),
+                is_dir: metadata.is_dir(),
+                size,
+                disk_usage,
+            });
+        }
+    }
+    children.sort_by_key(|child| std::cmp::Reverse(child.size));
+    let total = children.iter().fold((0, 0), |(size, disk_usage), child| {
+        (size + child.size, disk_usage + child.disk_usage)
+    });
+    listings.insert(dir.to_path_buf(), children);
+    total
+}
@@ -0,0 +1,153 @@
+use crate::audit::AuditLog;
+use crate::delete;
+use crate::metadata::Metadata;
+use crate::scanner::{CruftDirectory, CruftyReason};
+use crate::size_mode::SizeMode;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+pub struct CleanOptions<'a> {
+    /// Actually delete; otherwise just report what would be deleted
+    pub yes: bool,
+    pub allowed_roots: &'a [PathBuf],
+    pub always_confirm: &'a [CruftyReason],
+    pub audit_log: Option<&'a AuditLog>,
+    pub size_mode: SizeMode,
+    pub metadata: &'a Metadata,
+}
+
+/// Non-interactive deletion of everything that was found, for cron jobs and CI.
+pub fn run(found_dirs: &[CruftDirectory], options: &CleanOptions) -> Result<()> {
+    if options.allowed_roots.is_empty() {
+        anyhow::bail!(
+            "`decruft clean` only deletes under the roots listed in `[automation] allowed_roots` \
+             in the config file, and none are configured"
+        );
+    }
+    if options.yes && delete::is_shredding() && !delete::is_dry_run() {
+        eprintln!(
+            "Shredding: file contents are overwritten before deleting, which is slow, and only \
+             best-effort on SSDs, copy-on-write filesystems and snapshots"
+        );
+    }
+    let allowed_roots: Vec<PathBuf> = options
+        .allowed_roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect();
+
+    let open_files = crate::open_files::OpenFiles::snapshot();
+    let mut n_refused = 0;
+    let mut n_failed = 0;
+    let mut freed = 0;
+    // Deleted from overlayfs image layers or tmpfs, so not (or not certainly) freed on disk
+    let mut not_freed = 0;
+    for dir in found_dirs {
+        if !is_under_allowed_root(&dir.path, &allowed_roots) {
+            eprintln!(
+                "Refusing to delete {}: not under any allowed root",
+                dir.path.display()
+            );
+            n_refused += 1;
+            continue;
+        }
+        if options.metadata.is_kept(&dir.path) {
+            eprintln!("Skipping {}: marked keep", dir.path.display());
+            continue;
+        }
+        // Left for a later run, once whatever uses it has stopped
+        let users = open_files.users_of(&dir.path);
+        if !users.is_empty() {
+            eprintln!(
+                "Skipping {}: in use by {}",
+                dir.path.display(),
+                crate::open_files::describe(&users)
+            );
+            continue;
+        }
+        if let Some(tracked) = crate::git_status::TrackedFiles::in_dir(&dir.path) {
+            eprintln!(
+                "Skipping {}: it holds files tracked by git ({})",
+                dir.path.display(),
+                tracked.tracked
+            );
+            n_refused += 1;
+            continue;
+        }
+        if options.always_confirm.contains(&dir.crufty_reason) {
+            eprintln!(
+                "Skipping {}: {} always requires interactive confirmation",
+                dir.path.display(),
+                dir.crufty_reason
+            );
+            n_refused += 1;
+            continue;
+        }
+        if !options.yes {
+            println!(
+                "Would delete: {} ({}: {} bytes)",
+                dir.path.display(),
+                options.size_mode.as_str(),
+                options.size_mode.size_of(dir)
+            );
+            continue;
+        }
+        match delete::delete_entry(dir, options.audit_log) {
+            Ok(()) => {
+                let size = options.size_mode.size_of(dir);
+                println!(
+                    "{}: {} ({}: {} bytes)",
+                    if delete::is_dry_run() {
+                        "Would delete"
+                    } else {
+                        "Deleted"
+                    },
+                    dir.path.display(),
+                    options.size_mode.as_str(),
+                    size
+                );
+                if dir.space_caveat.is_some() {
+                    not_freed += size;
+                } else {
+                    freed += size;
+                }
+            }
+            Err(e) => {
+                eprintln!("{:#}", e);
+                n_failed += 1;
+            }
+        }
+    }
+
+    if options.yes && delete::is_dry_run() {
+        println!(
+            "Dry run: nothing was deleted, but {} bytes would have been freed",
+            freed
+        );
+    } else if options.yes {
+        println!("Freed {} bytes", freed);
+        if not_freed > 0 {
+            println!(
+                "Also deleted {} bytes from overlay or in-memory filesystems, which may not free disk space",
+                not_freed
+            );
+        }
+    } else {
+        println!("Nothing was deleted; pass --yes to delete");
+    }
+    if n_refused + n_failed > 0 {
+        anyhow::bail!(
+            "{} directories were refused and {} could not be deleted",
+            n_refused,
+            n_failed
+        );
+    }
+    Ok(())
+}
+
+fn is_under_allowed_root(path: &Path, allowed_roots: &[PathBuf]) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    allowed_roots.iter().any(|root| path.starts_with(root))
+}
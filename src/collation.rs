@@ -0,0 +1,94 @@
+use std::cmp::Ordering;
+
+/// How names are compared for the alphabetical sort order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Collation {
+    /// Case-insensitive, with runs of digits compared by value (`Project2` < `Project10`)
+    Natural,
+    /// Like `natural`, but text is compared using the current locale's collation rules
+    Locale,
+}
+
+impl Collation {
+    /// Prepares the process for this collation; must be called before sorting.
+    pub fn init(&self) {
+        #[cfg(unix)]
+        if *self == Collation::Locale {
+            // SAFETY: called once at startup, before any other threads exist.
+            unsafe {
+                libc::setlocale(libc::LC_COLLATE, c"".as_ptr());
+            }
+        }
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let mut a_chunks = Chunks(a);
+        let mut b_chunks = Chunks(b);
+        loop {
+            let ordering = match (a_chunks.next(), b_chunks.next()) {
+                (None, None) => break,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(a_chunk), Some(b_chunk)) => self.compare_chunks(a_chunk, b_chunk),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        // Equal modulo case and leading zeros: fall back to a stable raw comparison
+        a.cmp(b)
+    }
+
+    fn compare_chunks(&self, a: &str, b: &str) -> Ordering {
+        let a_digits = a.starts_with(|c: char| c.is_ascii_digit());
+        let b_digits = b.starts_with(|c: char| c.is_ascii_digit());
+        match (a_digits, b_digits) {
+            (true, true) => compare_numbers(a, b),
+            _ => match self {
+                Collation::Natural => a.to_lowercase().cmp(&b.to_lowercase()),
+                Collation::Locale => locale_compare(a, b),
+            },
+        }
+    }
+}
+
+/// Compares two runs of ASCII digits by value, with no risk of overflow.
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(unix)]
+fn locale_compare(a: &str, b: &str) -> Ordering {
+    use std::ffi::CString;
+    match (CString::new(a), CString::new(b)) {
+        // SAFETY: both pointers are valid NUL-terminated strings for the duration of the call.
+        (Ok(a), Ok(b)) => unsafe { libc::strcoll(a.as_ptr(), b.as_ptr()) }.cmp(&0),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(not(unix))]
+fn locale_compare(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+/// Splits a string into alternating runs of ASCII digits and everything else.
+struct Chunks<'a>(&'a str);
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let first = self.0.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = self
+            .0
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(self.0.len());
+        let (chunk, rest) = self.0.split_at(end);
+        self.0 = rest;
+        Some(chunk)
+    }
+}
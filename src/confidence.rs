@@ -0,0 +1,29 @@
+use crate::cycle::Cycle;
+
+/// How sure a detection is: a marker file (or a `node_modules` next to its `package.json`)
+/// is certain, an exact well-known name is likely, and a name merely containing "build"
+/// or "dist" is a guess. Guesses are kept apart in the "possible cruft" section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Guess,
+    Likely,
+    Certain,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Guess => "guess",
+            Confidence::Likely => "likely",
+            Confidence::Certain => "certain",
+        }
+    }
+}
+
+impl Cycle for Confidence {
+    fn all_values() -> &'static [Self] {
+        static ALL: [Confidence; 3] = [Confidence::Guess, Confidence::Likely, Confidence::Certain];
+        &ALL
+    }
+}
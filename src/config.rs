@@ -0,0 +1,143 @@
+use crate::confirm_policy::{ConfirmPolicy, Risk};
+use crate::keymap::Keys;
+use crate::rules::Expectation;
+use crate::scanner::CruftyReason;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Settings read from `config.toml` in decruft's config directory (or `--config`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub scan: ScanConfig,
+    pub ui: UiConfig,
+    pub confirm: ConfirmPolicy,
+    pub automation: AutomationConfig,
+    pub rules: RulesConfig,
+    pub hooks: HooksConfig,
+    pub archive: ArchiveConfig,
+    pub safety: SafetyConfig,
+    /// How to get back what each rule deletes, shown before deleting, instead of the built-in hints
+    pub rebuild: HashMap<CruftyReason, String>,
+    /// Keys for the TUI's commands, by name, instead of the default ones
+    pub keys: HashMap<String, Keys>,
+    /// The file this was read from, if any
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Never cross into other filesystems, as with `--one-file-system`
+    pub one_file_system: bool,
+    /// Follow symlinks, as with `--follow-symlinks`
+    pub follow_symlinks: bool,
+    /// Never look for cruft in these, besides `.git`, `.github`, `.idea` and `.vscode`:
+    /// names (protected wherever they are) or paths (`~/backups`)
+    pub protect: Vec<String>,
+    /// Leave out anything smaller than this (`"500M"`, `"1.5G"`), as with `--min-size`
+    #[serde(deserialize_with = "crate::parse::deserialize_size")]
+    pub min_size: Option<u64>,
+    /// Leave out anything changed more recently than this (`"90d"`, `"6mo"`, `"2y"`),
+    /// as with `--older-than`
+    #[serde(deserialize_with = "crate::parse::deserialize_age")]
+    pub older_than: Option<u64>,
+    /// Leave out anything with fewer files than this, as with `--min-files`
+    pub min_files: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    /// No spinner and a slower redraw rate, as with `--reduced-motion`
+    pub reduced_motion: bool,
+    /// Small screen updates for slow links, as with `--low-bandwidth`
+    pub low_bandwidth: bool,
+    /// How often to redraw, in milliseconds, as with `--poll-interval`
+    pub poll_interval_ms: Option<u64>,
+    /// Start with the preview pane open
+    pub preview: bool,
+    /// Show sizes in powers of 1000 rather than 1024
+    pub si_units: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutomationConfig {
+    /// `decruft clean` refuses to delete anything that isn't under one of these
+    pub allowed_roots: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RulesConfig {
+    /// What given paths should be detected as, for `decruft rules check`
+    pub expect: Vec<Expectation>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// A shell command to run after each successful deletion, with `DECRUFT_PATH`,
+    /// `DECRUFT_SIZE` and `DECRUFT_REASON` set to what was deleted
+    pub after_delete: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// Where to put a `.tar.zst` of each entry before deleting it; nothing is archived without this
+    pub dir: Option<String>,
+    /// Which entries to archive, by risk level (as `[confirm]` rates them)
+    pub risk: Vec<Risk>,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            risk: vec![Risk::High],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    /// Never delete anything less than this many directories deep (`/build` is 1)
+    pub min_depth: usize,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            min_depth: crate::safety::DEFAULT_MIN_DEPTH,
+        }
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Loads the configuration from an explicit path, or from the default location if it exists.
+    pub fn load(explicit_path: Option<&Path>) -> Result<Self> {
+        let path = match explicit_path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        config.source = Some(path);
+        Ok(config)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::confidence::Confidence;
+use crate::scanner::{CruftDirectory, CruftyReason};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// How much it takes to confirm a deletion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confirmation {
+    /// Pressing `d` deletes right away
+    None,
+    /// A y/n prompt
+    Simple,
+    /// The directory's name must be typed
+    Typed,
+}
+
+/// How bad it would be to delete something by mistake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Risk {
+    /// Caches that tools recreate in moments (`__pycache__`, `.pytest_cache`, coverage reports, ...)
+    Low,
+    /// Installed dependencies and build output, which take a while to get back
+    Normal,
+    /// Loose heuristic matches and directories that may hold hand-made things (`dist`, `tmp`)
+    High,
+}
+
+/// Which confirmation a deletion needs, by risk and size (`[confirm]` in the config file).
+///
+/// Each risk level maps the small, medium and large size buckets to a confirmation.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmPolicy {
+    /// Reasons that always require confirmation (and are never deleted by `decruft clean`)
+    pub always: Vec<CruftyReason>,
+    /// Entries at least this big (`"100M"`) are medium-sized
+    #[serde(deserialize_with = "crate::parse::deserialize_required_size")]
+    pub medium_size: u64,
+    /// Entries at least this big (`"5G"`) are large
+    #[serde(deserialize_with = "crate::parse::deserialize_required_size")]
+    pub large_size: u64,
+    pub low: [Confirmation; 3],
+    pub normal: [Confirmation; 3],
+    pub high: [Confirmation; 3],
+    /// Overrides of the built-in risk level of a reason
+    pub risk: HashMap<CruftyReason, Risk>,
+}
+
+impl Default for ConfirmPolicy {
+    fn default() -> Self {
+        use Confirmation::*;
+        Self {
+            always: Vec::new(),
+            medium_size: 100 * 1024 * 1024,
+            large_size: 5 * 1024 * 1024 * 1024,
+            low: [None, Simple, Typed],
+            normal: [Simple, Simple, Typed],
+            high: [Simple, Typed, Typed],
+            risk: HashMap::new(),
+        }
+    }
+}
+
+impl ConfirmPolicy {
+    pub fn risk(&self, dir: &CruftDirectory) -> Risk {
+        if dir.confidence == Confidence::Guess {
+            return Risk::High;
+        }
+        if let Some(&risk) = self.risk.get(&dir.crufty_reason) {
+            return risk;
+        }
+        match dir.crufty_reason {
+            CruftyReason::CacheDir
+            | CruftyReason::CacheTagFound
+            | CruftyReason::CoverageDir
+            | CruftyReason::ToxDir
+            | CruftyReason::ZigCacheDir
+            | CruftyReason::FinderMetadata => Risk::Low,
+            CruftyReason::DistDir | CruftyReason::TempDir => Risk::High,
+            _ => Risk::Normal,
+        }
+    }
+
+    /// The confirmation needed to delete `dir`, which takes up `size` bytes
+    /// (`None` while it is still being sized, which counts as large).
+    pub fn required(&self, dir: &CruftDirectory, size: Option<u64>) -> Confirmation {
+        let bucket = match size {
+            Some(size) if size < self.medium_size => 0,
+            Some(size) if size < self.large_size => 1,
+            _ => 2,
+        };
+        let row = match self.risk(dir) {
+            Risk::Low => &self.low,
+            Risk::Normal => &self.normal,
+            Risk::High => &self.high,
+        };
+        let mut confirmation = row[bucket];
+        // Entries found through a symlink need a choice of what to delete
+        if self.always.contains(&dir.crufty_reason) || dir.via_symlink.is_some() {
+            confirmation = confirmation.max(Confirmation::Simple);
+        }
+        confirmation
+    }
+}
@@ -0,0 +1,110 @@
+use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use crate::units::format_size;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Bar, BarChart, BarGroup, Block, Borders};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How old the newest file in an entry must be to fall in each age bucket, in days
+const AGE_BUCKETS: [(f64, &str); 5] = [
+    (365.0, "over a year"),
+    (180.0, "6-12 months"),
+    (30.0, "1-6 months"),
+    (7.0, "1-4 weeks"),
+    (0.0, "under a week"),
+];
+
+/// The second screen (Tab), where the bulk of the waste is: what's shown in the list,
+/// totalled per kind of cruft, per top-level directory under the scan root, and per age
+pub fn draw(f: &mut Frame, area: Rect, dirs: &[&CruftDirectory], size_mode: SizeMode, root: &Path) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(area);
+
+    let by_reason = totals(dirs, size_mode, |dir| dir.crufty_reason.to_string());
+    let by_project = totals(dirs, size_mode, |dir| top_level(&dir.path, root));
+    let mut by_age = totals(dirs, size_mode, |dir| age_bucket(dir).to_string());
+    // Oldest first, rather than biggest first
+    by_age.sort_by_key(|(label, _)| {
+        AGE_BUCKETS
+            .iter()
+            .position(|(_, bucket)| bucket == label)
+            .unwrap_or(AGE_BUCKETS.len())
+    });
+    draw_chart(f, columns[0], "By type", &by_reason, Color::Green);
+    draw_chart(f, columns[1], "By project", &by_project, Color::Cyan);
+    draw_chart(f, columns[2], "By age", &by_age, Color::Magenta);
+}
+
+/// The total size of `dirs` for each label, biggest first
+fn totals(
+    dirs: &[&CruftDirectory],
+    size_mode: SizeMode,
+    label: impl Fn(&CruftDirectory) -> String,
+) -> Vec<(String, u64)> {
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    for dir in dirs {
+        *sizes.entry(label(dir)).or_default() += size_mode.size_of(dir);
+    }
+    let mut sizes: Vec<(String, u64)> = sizes.into_iter().collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sizes
+}
+
+/// The directory right under the scan root that `path` is in, which is usually the project
+fn top_level(path: &Path, root: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut components = relative.components();
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => first.as_os_str().to_string_lossy().to_string(),
+        // Cruft right in the scan root belongs to no project under it
+        _ => ".".to_string(),
+    }
+}
+
+fn age_bucket(dir: &CruftDirectory) -> &'static str {
+    match dir.newest_file_age_days {
+        Some(days) if !dir.pending => AGE_BUCKETS
+            .iter()
+            .find(|(min_days, _)| days >= *min_days)
+            .map_or("under a week", |(_, label)| label),
+        _ => "not known yet",
+    }
+}
+
+/// A horizontal bar per label, as many as fit, with the size at the end of each bar
+fn draw_chart(f: &mut Frame, area: Rect, title: &str, totals: &[(String, u64)], color: Color) {
+    let shown = totals.len().min(area.height.saturating_sub(2) as usize);
+    let bars: Vec<Bar> = totals
+        .iter()
+        .take(shown)
+        .map(|(label, size)| {
+            Bar::default()
+                .label(label.clone())
+                .value(*size)
+                .text_value(format_size(*size))
+        })
+        .collect();
+    let title = if totals.len() > shown {
+        format!("{} (top {} of {})", title, shown, totals.len())
+    } else {
+        title.to_string()
+    };
+    let chart = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .bar_style(Style::default().fg(color))
+        .value_style(Style::default().fg(Color::Black).bg(color))
+        .data(BarGroup::default().bars(&bars));
+    f.render_widget(chart, area);
+}
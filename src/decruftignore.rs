@@ -0,0 +1,53 @@
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+use std::sync::Arc;
+
+const FILE_NAME: &str = ".decruftignore";
+
+/// The `.decruftignore` files (gitignore syntax) that apply to a directory being scanned:
+/// the one in the scan root and any in directories between it and this one.
+#[derive(Clone, Default)]
+pub struct IgnoreRules(Option<Arc<Node>>);
+
+struct Node {
+    matcher: Gitignore,
+    parent: Option<Arc<Node>>,
+}
+
+impl IgnoreRules {
+    /// The rules for the entries of `dir`, adding its own `.decruftignore` if it has one.
+    /// Lines that don't parse are skipped.
+    pub fn enter(&self, dir: &Path) -> IgnoreRules {
+        let file = dir.join(FILE_NAME);
+        if !file.is_file() {
+            return self.clone();
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        builder.add(&file);
+        match builder.build() {
+            Ok(matcher) if !matcher.is_empty() => IgnoreRules(Some(Arc::new(Node {
+                matcher,
+                parent: self.0.clone(),
+            }))),
+            _ => self.clone(),
+        }
+    }
+
+    /// As with `.gitignore`, the innermost file with a matching pattern decides,
+    /// so a nested `!pattern` can bring back something ignored further up.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut node = self.0.as_deref();
+        while let Some(current) = node {
+            if path.starts_with(current.matcher.path()) {
+                match current.matcher.matched(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            node = current.parent.as_deref();
+        }
+        false
+    }
+}
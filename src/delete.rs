@@ -0,0 +1,622 @@
+use crate::archive;
+use crate::audit::{AuditLog, InterruptedDeletion};
+use crate::history;
+use crate::io_scheduler;
+use crate::safety;
+use crate::scanner::{self, CruftDirectory, CruftyReason};
+use crate::size_mode::SizeMode;
+use crate::staging::Staging;
+use anyhow::{Context, Result};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// `--dry-run`: deleting only logs and tallies what would have been deleted
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+/// `--allow-git-repos`: directories with a git repository inside may be deleted after all
+static ALLOW_GIT_REPOS: AtomicBool = AtomicBool::new(false);
+/// `--shred`: file contents are overwritten before the files are unlinked
+static SHRED: AtomicBool = AtomicBool::new(false);
+/// What dry-run deletions would have freed so far: size, disk usage and unique disk usage
+static WOULD_FREE: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// `[hooks] after_delete`: the shell command to run after each successful deletion
+static AFTER_DELETE: OnceLock<String> = OnceLock::new();
+
+/// Makes every deletion from here on a dry run, whichever way it's asked for
+pub fn enable_dry_run() {
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Lets directories that hold a git repository be deleted, from here on
+pub fn allow_git_repos() {
+    ALLOW_GIT_REPOS.store(true, Ordering::Relaxed);
+}
+
+/// Makes every deletion from here on overwrite the contents of each file before unlinking
+/// it. That is slow, and only best-effort: SSDs, copy-on-write and journaling filesystems
+/// and snapshots can all keep the old data somewhere regardless. Files with more than one
+/// hard link are left as they are, as their other links still need the contents.
+pub fn enable_shred() {
+    SHRED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_shredding() -> bool {
+    SHRED.load(Ordering::Relaxed)
+}
+
+/// Runs `command` after each deletion from here on that succeeds (not in dry runs)
+pub fn set_after_delete_hook(command: String) {
+    let _ = AFTER_DELETE.set(command);
+}
+
+/// What the dry-run deletions so far would have freed, as `size_mode` counts it
+pub fn would_have_freed(size_mode: SizeMode) -> u64 {
+    let [size, disk_usage, unique] = WOULD_FREE.each_ref().map(|n| n.load(Ordering::Relaxed));
+    match size_mode {
+        SizeMode::Apparent => size,
+        SizeMode::DiskUsage => disk_usage,
+        SizeMode::Unique => unique,
+    }
+}
+
+/// Something that identifies a directory itself rather than its path:
+/// device and inode on Unix, creation time elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirIdentity {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(not(unix))]
+    created: std::time::SystemTime,
+}
+
+impl DirIdentity {
+    /// Identifies whatever currently lives at `path`, without following symlinks.
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Some(Self {
+                dev: metadata.dev(),
+                ino: metadata.ino(),
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            Some(Self {
+                created: metadata.created().ok()?,
+            })
+        }
+    }
+}
+
+/// Makes sure `dir` is still the directory that was scanned, and still looks like what it
+/// was found as: between scanning and deleting, it may have been replaced by something else
+/// (or a project may have moved in, making it not cruft any more).
+pub fn check_unchanged(dir: &CruftDirectory) -> Result<()> {
+    // Aggregates stand for their members, each checked as it's deleted, and what's in the
+    // staging area was checked on the way in
+    if dir.members.is_empty() && dir.staged_from.is_none() {
+        check_safe(&dir.path)?;
+    }
+    if dir.identity.is_some() && DirIdentity::of(&dir.path) != dir.identity {
+        anyhow::bail!(
+            "{} was replaced since it was scanned; rescan before deleting",
+            dir.path.display()
+        );
+    }
+    if !scanner::still_detected(dir) {
+        anyhow::bail!(
+            "{} is no longer detected as {}; rescan before deleting",
+            dir.path.display(),
+            dir.crufty_reason
+        );
+    }
+    Ok(())
+}
+
+/// Refuses to delete `path` if it's somewhere never to be deleted, or (unless
+/// `--allow-git-repos`) holds a git repository
+fn check_safe(path: &Path) -> Result<()> {
+    safety::check(path)?;
+    if !ALLOW_GIT_REPOS.load(Ordering::Relaxed)
+        && let Some(repo) = git_repository_in(path)
+    {
+        anyhow::bail!(
+            "{} holds a git repository ({}), so is likely not cruft; \
+             pass --allow-git-repos to delete it anyway",
+            path.display(),
+            repo.display()
+        );
+    }
+    Ok(())
+}
+
+/// The first git repository (a directory with a `.git` directory or file) at or under
+/// `path`, which a rule is unlikely to have matched on purpose. Walks the whole tree if
+/// there is none, but only reads directories, like deleting it would anyway.
+fn git_repository_in(path: &Path) -> Option<PathBuf> {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .find(|entry| entry.depth() > 0 && entry.file_name() == ".git")
+        .and_then(|entry| entry.path().parent().map(Path::to_path_buf))
+}
+
+/// Deletes a scanned cruft directory, after making sure it is still the directory that was
+/// scanned, and records the deletion in the audit log (if any) as it starts and ends.
+pub fn delete_entry(dir: &CruftDirectory, audit_log: Option<&AuditLog>) -> Result<()> {
+    delete_entry_reporting(dir, audit_log, &mut |_| {})
+}
+
+/// Like `delete_entry`, calling `on_removed` with the size of each file as it goes
+pub fn delete_entry_reporting(
+    dir: &CruftDirectory,
+    audit_log: Option<&AuditLog>,
+    on_removed: &mut dyn FnMut(u64),
+) -> Result<()> {
+    check_unchanged(dir)?;
+    if is_dry_run() {
+        if let Some(audit_log) = audit_log {
+            audit_log.record("would delete", dir)?;
+        }
+        for (total, size) in
+            WOULD_FREE
+                .iter()
+                .zip([dir.size, dir.disk_usage, dir.unique_disk_usage])
+        {
+            total.fetch_add(size, Ordering::Relaxed);
+        }
+        return Ok(());
+    }
+    // What's purged from the staging area was archived (if at all) when it was staged
+    if dir.staged_from.is_none() {
+        archive::archive_if_wanted(dir, audit_log)?;
+    }
+    if !dir.members.is_empty() {
+        delete_members(dir, audit_log)?;
+        history::record_deletion(dir);
+        return run_after_delete_hook(dir, audit_log);
+    }
+    // Log before deleting, so a deletion can never happen without a trace
+    if let Some(audit_log) = audit_log {
+        audit_log.record("delete", dir)?;
+    }
+    let result = io_scheduler::run(&dir.path, || imp::remove_dir_all(&dir.path, on_removed));
+    if let Some(audit_log) = audit_log {
+        audit_log.record(outcome_event(&result), dir)?;
+    }
+    result.with_context(|| format!("Failed to delete {}", dir.path.display()))?;
+    history::record_deletion(dir);
+    run_after_delete_hook(dir, audit_log)
+}
+
+/// Runs the `after_delete` hook (if any) for `dir`, which has just been deleted, under the
+/// path it was found at even if it was purged from the staging area. The deletion has
+/// happened either way, so a failing hook is only recorded in the audit log; its output
+/// goes nowhere, as it would mess up the TUI.
+fn run_after_delete_hook(dir: &CruftDirectory, audit_log: Option<&AuditLog>) -> Result<()> {
+    let Some(command) = AFTER_DELETE.get() else {
+        return Ok(());
+    };
+    let path = dir.staged_from.as_ref().unwrap_or(&dir.path);
+    let status = shell(command)
+        .env("DECRUFT_PATH", path)
+        .env("DECRUFT_SIZE", dir.size.to_string())
+        .env("DECRUFT_REASON", dir.crufty_reason.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if status.is_ok_and(|status| status.success()) {
+        return Ok(());
+    }
+    if let Some(audit_log) = audit_log {
+        audit_log.record_other(
+            "hook failed",
+            &dir.crufty_reason.to_string(),
+            path,
+            dir.size,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Finishes a deletion that the audit log says was cut short, after the same checks as
+/// `check_unchanged`: the path may hold something else entirely by now.
+pub fn finish_interrupted(deletion: &InterruptedDeletion, audit_log: &AuditLog) -> Result<()> {
+    safety::check(&deletion.path)?;
+    // What's purged from the staging area was checked on the way in, and doesn't go by its
+    // own name any more
+    let staged = Staging::default_root().is_some_and(|root| deletion.path.starts_with(root));
+    if !staged {
+        check_safe(&deletion.path)?;
+    }
+    let still_cruft = staged
+        || CruftyReason::from_label(&deletion.reason)
+            .is_some_and(|reason| scanner::still_detected_at(&deletion.path, &reason));
+    if !still_cruft {
+        anyhow::bail!(
+            "{} is no longer detected as {}, so is left alone",
+            deletion.path.display(),
+            deletion.reason
+        );
+    }
+    audit_log.record_interrupted("resume", deletion)?;
+    let result = io_scheduler::run(&deletion.path, || remove_path(&deletion.path));
+    audit_log.record_interrupted(outcome_event(&result), deletion)?;
+    result.with_context(|| format!("Failed to delete {}", deletion.path.display()))
+}
+
+fn outcome_event(result: &io::Result<()>) -> &'static str {
+    if result.is_ok() {
+        "deleted"
+    } else {
+        "delete failed"
+    }
+}
+
+/// Removes whatever is at `path`; something that's already gone counts as removed.
+fn remove_path(path: &Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => remove_dir_all(path),
+        Ok(_) => imp::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Deletes the files and directories an aggregate entry is made of, carrying on past
+/// failures so that one locked file doesn't keep the rest around.
+fn delete_members(dir: &CruftDirectory, audit_log: Option<&AuditLog>) -> Result<()> {
+    let mut failures = Vec::new();
+    for member in &dir.members {
+        if let Err(e) = safety::check(member) {
+            failures.push(format!("{:#}", e));
+            continue;
+        }
+        let size = audit_log.map(|_| apparent_size(member)).unwrap_or(0);
+        if let Some(audit_log) = audit_log {
+            audit_log.record_path("delete", dir, member, size)?;
+        }
+        let result = remove_path(member);
+        if let Some(audit_log) = audit_log {
+            audit_log.record_path(outcome_event(&result), dir, member, size)?;
+        }
+        if let Err(e) = result {
+            failures.push(format!("{}: {}", member.display(), e));
+        }
+    }
+    match failures.first() {
+        None => Ok(()),
+        Some(first) => anyhow::bail!(
+            "{} of {} could not be deleted, e.g. {}",
+            failures.len(),
+            dir.members.len(),
+            first
+        ),
+    }
+}
+
+/// The summed length of the files at or under `path`, for the audit log
+fn apparent_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Removes the symlink through which a cruft directory was found, leaving its target alone.
+pub fn remove_link(dir: &CruftDirectory, audit_log: Option<&AuditLog>) -> Result<()> {
+    let Some(ref link) = dir.via_symlink else {
+        anyhow::bail!("{} was not found through a symlink", dir.path.display());
+    };
+    let is_symlink = std::fs::symlink_metadata(link).is_ok_and(|m| m.file_type().is_symlink());
+    if !is_symlink {
+        anyhow::bail!(
+            "{} is no longer a symlink; rescan before deleting",
+            link.display()
+        );
+    }
+    if is_dry_run() {
+        if let Some(audit_log) = audit_log {
+            audit_log.record_path("would unlink", dir, link, 0)?;
+        }
+        return Ok(());
+    }
+    if let Some(audit_log) = audit_log {
+        audit_log.record_path("unlink", dir, link, 0)?;
+    }
+    std::fs::remove_file(link).with_context(|| format!("Failed to remove {}", link.display()))
+}
+
+/// Removes a directory and everything in it.
+///
+/// On Unix this works relative to directory file descriptors and never follows symlinks,
+/// so a directory that gets swapped for a symlink (to, say, `$HOME`) between scanning and
+/// deletion is refused instead of followed, and nothing outside the tree can be reached
+/// by replacing one of its subdirectories mid-deletion.
+pub fn remove_dir_all(path: &Path) -> io::Result<()> {
+    imp::remove_dir_all(path, &mut |_| {})
+}
+
+#[cfg(unix)]
+mod imp {
+    use rustix::fs::{
+        AtFlags, CWD, Dir, FileType, Mode, OFlags, Stat, chmodat, fchmod, fstat, openat, statat,
+        unlinkat,
+    };
+    use std::ffi::CStr;
+    use std::io::{self, Write};
+    use std::os::fd::{AsFd, OwnedFd};
+    use std::path::Path;
+
+    pub fn remove_dir_all(path: &Path, on_removed: &mut dyn FnMut(u64)) -> io::Result<()> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("refusing to delete {}", path.display()),
+            ));
+        };
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        let parent_fd = open_dir(CWD, parent)?;
+        remove_at(parent_fd.as_fd(), name, on_removed)
+    }
+
+    pub fn remove_file(path: &Path) -> io::Result<()> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return std::fs::remove_file(path);
+        };
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        let parent_fd = open_dir(CWD, parent)?;
+        let file_type =
+            FileType::from_raw_mode(statat(&parent_fd, name, AtFlags::SYMLINK_NOFOLLOW)?.st_mode);
+        remove_file_at(&parent_fd, name, file_type, &mut |_| {})
+    }
+
+    fn open_dir<Fd: AsFd, P: rustix::path::Arg>(dirfd: Fd, name: P) -> io::Result<OwnedFd> {
+        Ok(openat(
+            dirfd,
+            name,
+            OFlags::RDONLY | OFlags::DIRECTORY | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+            Mode::empty(),
+        )?)
+    }
+
+    /// Removes the directory `name` inside `parent_fd`, recursively.
+    fn remove_at<Fd: AsFd, P: rustix::path::Arg + Copy>(
+        parent_fd: Fd,
+        name: P,
+        on_removed: &mut dyn FnMut(u64),
+    ) -> io::Result<()> {
+        // O_NOFOLLOW makes this fail if `name` has become a symlink
+        let fd = match open_dir(&parent_fd, name) {
+            // Its owner may delete a directory they can't read, once they allow themselves to
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                let stat = statat(&parent_fd, name, AtFlags::SYMLINK_NOFOLLOW)?;
+                if FileType::from_raw_mode(stat.st_mode) != FileType::Directory {
+                    return Err(e);
+                }
+                chmod_unfollowed(&parent_fd, name, &stat, Mode::RWXU)?;
+                open_dir(&parent_fd, name)?
+            }
+            fd => fd?,
+        };
+        make_writable(&fd);
+        let mut dir = Dir::read_from(&fd)?;
+        while let Some(entry) = dir.read() {
+            let entry = entry?;
+            let child = entry.file_name();
+            if is_dot(child) {
+                continue;
+            }
+            let file_type = match entry.file_type() {
+                FileType::Unknown => {
+                    FileType::from_raw_mode(statat(&fd, child, AtFlags::SYMLINK_NOFOLLOW)?.st_mode)
+                }
+                file_type => file_type,
+            };
+            if file_type == FileType::Directory {
+                remove_at(&fd, child, on_removed)?;
+            } else {
+                remove_file_at(&fd, child, file_type, on_removed)?;
+            }
+        }
+        unlinkat(&parent_fd, name, AtFlags::REMOVEDIR)?;
+        Ok(())
+    }
+
+    /// Changes the mode of `name` inside `parent_fd`, as long as it's still the file `stat`
+    /// was taken of: never through a symlink it's been swapped for in the meantime, which
+    /// would change the mode of whatever that points to (say, `/etc/shadow`, as root).
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn chmod_unfollowed<Fd: AsFd, P: rustix::path::Arg>(
+        parent_fd: Fd,
+        name: P,
+        stat: &Stat,
+        mode: Mode,
+    ) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+        // Linux's fchmodat can't be told not to follow symlinks, and fchmod doesn't take
+        // O_PATH descriptors, but the descriptor's /proc link leads to just what it opened
+        let fd = openat(
+            parent_fd,
+            name,
+            OFlags::PATH | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+            Mode::empty(),
+        )?;
+        let opened = fstat(&fd)?;
+        if (opened.st_dev, opened.st_ino) != (stat.st_dev, stat.st_ino) {
+            return Err(io::Error::other("it was replaced while being deleted"));
+        }
+        Ok(chmodat(
+            CWD,
+            format!("/proc/self/fd/{}", fd.as_raw_fd()).as_str(),
+            mode,
+            AtFlags::empty(),
+        )?)
+    }
+
+    /// The same, where fchmodat can be told not to follow symlinks
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn chmod_unfollowed<Fd: AsFd, P: rustix::path::Arg + Copy>(
+        parent_fd: Fd,
+        name: P,
+        stat: &Stat,
+        mode: Mode,
+    ) -> io::Result<()> {
+        let now = statat(&parent_fd, name, AtFlags::SYMLINK_NOFOLLOW)?;
+        if (now.st_dev, now.st_ino) != (stat.st_dev, stat.st_ino) {
+            return Err(io::Error::other("it was replaced while being deleted"));
+        }
+        // Were it swapped for a symlink after all, this changes the link's own mode
+        Ok(chmodat(parent_fd, name, mode, AtFlags::SYMLINK_NOFOLLOW)?)
+    }
+
+    /// Gives the owner of a directory that's being deleted write permission on it (which
+    /// tools like Go's module cache take away), so that what's in it can be unlinked.
+    /// Without it, unlinking fails in its place.
+    fn make_writable(fd: &OwnedFd) {
+        if let Ok(stat) = fstat(fd)
+            && stat.st_mode & 0o700 != 0o700
+        {
+            let _ = fchmod(fd, Mode::from_raw_mode((stat.st_mode & 0o7777) | 0o700));
+        }
+    }
+
+    /// Unlinks the non-directory `name` inside `parent_fd`, shredding it first if asked to
+    fn remove_file_at<Fd: AsFd, P: rustix::path::Arg + Copy>(
+        parent_fd: Fd,
+        name: P,
+        file_type: FileType,
+        on_removed: &mut dyn FnMut(u64),
+    ) -> io::Result<()> {
+        let stat = statat(&parent_fd, name, AtFlags::SYMLINK_NOFOLLOW).ok();
+        let size = stat.map_or(0, |stat| stat.st_size as u64);
+        if super::is_shredding()
+            && file_type == FileType::RegularFile
+            && let Some(stat) = stat.filter(|stat| stat.st_nlink == 1)
+        {
+            overwrite_at(&parent_fd, name, &stat)?;
+        }
+        unlinkat(&parent_fd, name, AtFlags::empty())?;
+        on_removed(size);
+        Ok(())
+    }
+
+    /// Overwrites the file `name` (of which `stat` was taken) with zeros and flushes them to
+    /// disk, making it writable first if it's read-only
+    fn overwrite_at<Fd: AsFd, P: rustix::path::Arg + Copy>(
+        parent_fd: Fd,
+        name: P,
+        stat: &Stat,
+    ) -> io::Result<()> {
+        let flags = OFlags::WRONLY | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+        let fd = match openat(&parent_fd, name, flags, Mode::empty()) {
+            Err(rustix::io::Errno::ACCESS) => {
+                chmod_unfollowed(&parent_fd, name, stat, Mode::RUSR | Mode::WUSR)?;
+                openat(&parent_fd, name, flags, Mode::empty())?
+            }
+            fd => fd?,
+        };
+        let mut file = std::fs::File::from(fd);
+        let zeros = [0u8; 65536];
+        let mut left = stat.st_size as u64;
+        while left > 0 {
+            let n = left.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..n])?;
+            left -= n as u64;
+        }
+        file.sync_all()
+    }
+
+    fn is_dot(name: &CStr) -> bool {
+        matches!(name.to_bytes(), b"." | b"..")
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::io;
+    use std::path::Path;
+
+    /// Without progress: std's implementation doesn't report any
+    pub fn remove_dir_all(path: &Path, _on_removed: &mut dyn FnMut(u64)) -> io::Result<()> {
+        // A directory that has been swapped for a symlink or junction is refused, like on Unix;
+        // below that, std's implementation doesn't follow directory symlinks or junctions
+        if std::fs::symlink_metadata(path)?.file_type().is_symlink() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is a symlink or junction", path.display()),
+            ));
+        }
+        match std::fs::remove_dir_all(path) {
+            // Read-only files can't be deleted until they aren't
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                let entries = walkdir::WalkDir::new(path)
+                    .into_iter()
+                    .filter_map(Result::ok);
+                for entry in entries {
+                    clear_read_only(entry.path());
+                }
+                std::fs::remove_dir_all(path)
+            }
+            result => result,
+        }
+    }
+
+    pub fn remove_file(path: &Path) -> io::Result<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                clear_read_only(path);
+                std::fs::remove_file(path)
+            }
+            result => result,
+        }
+    }
+
+    fn clear_read_only(path: &Path) {
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            let mut permissions = metadata.permissions();
+            if permissions.readonly() {
+                #[allow(clippy::permissions_set_readonly_false)]
+                permissions.set_readonly(false);
+                let _ = std::fs::set_permissions(path, permissions);
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::audit::AuditLog;
+use crate::delete;
+use crate::scanner::CruftDirectory;
+use crate::staging::{self, Staged};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// How often a deletion under way reports how far it's got
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How far a deletion has got
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    pub files: u64,
+    pub bytes: u64,
+    /// How long it has been going; zero while it waits its turn
+    pub elapsed: Duration,
+}
+
+/// What became of an entry handed to the worker
+pub enum Done {
+    Deleted,
+    /// Moved into the staging area, for `Staging::add`
+    Staged(Box<Staged>),
+}
+
+enum DeleteEvent {
+    Progress(PathBuf, Progress),
+    Finished(PathBuf, Result<Done>),
+}
+
+/// Deletes entries (or moves them into the staging area) on a thread of its own, one after
+/// the other, so that the UI stays responsive while a huge tree is checked, archived or
+/// deleted, and reports progress back as it goes.
+pub struct DeleteWorker {
+    /// Each entry, with where to stage it if it's to be staged
+    jobs: Sender<(CruftDirectory, Option<PathBuf>)>,
+    events: Receiver<DeleteEvent>,
+    /// Deletions queued or under way, with how far each has got
+    in_progress: HashMap<PathBuf, (CruftDirectory, Progress)>,
+    /// The one under way, if any
+    current: Option<PathBuf>,
+}
+
+impl DeleteWorker {
+    pub fn start(audit_log: Option<AuditLog>) -> Self {
+        let (jobs, queued) = mpsc::channel::<(CruftDirectory, Option<PathBuf>)>();
+        let (sender, events) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (dir, stage_at) in queued {
+                let started = Instant::now();
+                let mut progress = Progress::default();
+                let mut last_report = started;
+                let _ = sender.send(DeleteEvent::Progress(dir.path.clone(), progress));
+                let result = match stage_at {
+                    Some(at) => staging::stage(&dir, &at, audit_log.as_ref())
+                        .map(|staged| Done::Staged(Box::new(staged))),
+                    None => delete::delete_entry_reporting(&dir, audit_log.as_ref(), &mut |size| {
+                        progress.files += 1;
+                        progress.bytes += size;
+                        if last_report.elapsed() >= PROGRESS_INTERVAL {
+                            last_report = Instant::now();
+                            progress.elapsed = started.elapsed();
+                            let _ = sender.send(DeleteEvent::Progress(dir.path.clone(), progress));
+                        }
+                    })
+                    .map(|()| Done::Deleted),
+                };
+                let _ = sender.send(DeleteEvent::Finished(dir.path, result));
+            }
+        });
+        Self {
+            jobs,
+            events,
+            in_progress: HashMap::new(),
+            current: None,
+        }
+    }
+
+    /// Queues `dir` for deletion, unless it already is
+    pub fn delete(&mut self, dir: &CruftDirectory) {
+        self.queue(dir, None);
+    }
+
+    /// Queues `dir` to be moved into the staging area at `at`, unless it already is
+    pub fn stage(&mut self, dir: &CruftDirectory, at: PathBuf) {
+        self.queue(dir, Some(at));
+    }
+
+    fn queue(&mut self, dir: &CruftDirectory, stage_at: Option<PathBuf>) {
+        if self.in_progress.contains_key(&dir.path) {
+            return;
+        }
+        self.in_progress
+            .insert(dir.path.clone(), (dir.clone(), Progress::default()));
+        // The worker only goes away with the UI
+        let _ = self.jobs.send((dir.clone(), stage_at));
+    }
+
+    pub fn progress(&self, path: &Path) -> Option<Progress> {
+        self.in_progress.get(path).map(|(_, progress)| *progress)
+    }
+
+    /// The deletion under way, and how far it has got
+    pub fn current(&self) -> Option<(&CruftDirectory, Progress)> {
+        let (dir, progress) = self.in_progress.get(self.current.as_deref()?)?;
+        Some((dir, *progress))
+    }
+
+    /// How many deletions are waiting for the current one to finish
+    pub fn queued(&self) -> usize {
+        self.in_progress.len().saturating_sub(1)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.in_progress.is_empty()
+    }
+
+    /// Takes in progress reports, returning the deletions that have finished since last time
+    pub fn poll(&mut self) -> Vec<(CruftDirectory, Result<Done>)> {
+        let mut finished = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                DeleteEvent::Progress(path, progress) => {
+                    if let Some((_, entry)) = self.in_progress.get_mut(&path) {
+                        *entry = progress;
+                    }
+                    self.current = Some(path);
+                }
+                DeleteEvent::Finished(path, result) => {
+                    self.current = None;
+                    if let Some((dir, _)) = self.in_progress.remove(&path) {
+                        finished.push((dir, result));
+                    }
+                }
+            }
+        }
+        finished
+    }
+}
@@ -0,0 +1,129 @@
+use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use crate::units::format_size;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One directory of a saved scan: an entry of what `--scan-only --format json` prints
+#[derive(Deserialize)]
+pub struct SnapshotEntry {
+    path: PathBuf,
+    reason: String,
+    size: u64,
+    #[serde(default)]
+    disk_usage: u64,
+    #[serde(default)]
+    unique_disk_usage: u64,
+}
+
+impl SnapshotEntry {
+    fn size(&self, size_mode: SizeMode) -> u64 {
+        match size_mode {
+            SizeMode::Apparent => self.size,
+            SizeMode::DiskUsage => self.disk_usage,
+            SizeMode::Unique => self.unique_disk_usage,
+        }
+    }
+}
+
+/// A scan saved with `decruft --scan-only --format json > FILE`
+pub fn load(path: &Path) -> Result<Vec<SnapshotEntry>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| {
+        format!(
+            "{} is not a saved scan (from `--scan-only --format json`)",
+            path.display()
+        )
+    })
+}
+
+/// What was just scanned, as if it had been saved and loaded again
+pub fn from_scan(dirs: &[CruftDirectory]) -> Vec<SnapshotEntry> {
+    dirs.iter()
+        .map(|dir| SnapshotEntry {
+            path: dir.path.clone(),
+            reason: dir.crufty_reason.to_string(),
+            size: dir.size,
+            disk_usage: dir.disk_usage,
+            unique_disk_usage: dir.unique_disk_usage,
+        })
+        .collect()
+}
+
+/// Prints which directories appeared in `new`, disappeared from `old` and grew in between,
+/// biggest first in each, to spot what suddenly started filling the disk
+pub fn print(old: &[SnapshotEntry], new: &[SnapshotEntry], size_mode: SizeMode) -> Result<()> {
+    let old_sizes: HashMap<&Path, u64> = old
+        .iter()
+        .map(|entry| (entry.path.as_path(), entry.size(size_mode)))
+        .collect();
+    let new_paths: HashSet<&Path> = new.iter().map(|entry| entry.path.as_path()).collect();
+    let mut appeared: Vec<(&SnapshotEntry, u64)> = Vec::new();
+    let mut grew: Vec<(&SnapshotEntry, u64)> = Vec::new();
+    for entry in new {
+        match old_sizes.get(entry.path.as_path()) {
+            None => appeared.push((entry, entry.size(size_mode))),
+            Some(&was) if entry.size(size_mode) > was => {
+                grew.push((entry, entry.size(size_mode) - was))
+            }
+            Some(_) => {}
+        }
+    }
+    let mut disappeared: Vec<(&SnapshotEntry, u64)> = old
+        .iter()
+        .filter(|entry| !new_paths.contains(entry.path.as_path()))
+        .map(|entry| (entry, entry.size(size_mode)))
+        .collect();
+
+    let mut out = std::io::stdout().lock();
+    for (heading, sign, entries) in [
+        ("Appeared", '+', &mut appeared),
+        ("Grew", '+', &mut grew),
+        ("Disappeared", '-', &mut disappeared),
+    ] {
+        if entries.is_empty() {
+            continue;
+        }
+        entries.sort_by_key(|(_, change)| std::cmp::Reverse(*change));
+        let total: u64 = entries.iter().map(|(_, change)| change).sum();
+        writeln!(
+            out,
+            "{} ({}, {}{}):",
+            heading,
+            entries.len(),
+            sign,
+            format_size(total)
+        )?;
+        for (entry, change) in entries.iter() {
+            writeln!(
+                out,
+                "  {}{:>13}  {} ({})",
+                sign,
+                format_size(*change),
+                entry.path.display(),
+                entry.reason
+            )?;
+        }
+    }
+    let old_total: u64 = old.iter().map(|entry| entry.size(size_mode)).sum();
+    let new_total: u64 = new.iter().map(|entry| entry.size(size_mode)).sum();
+    let (sign, change) = if new_total >= old_total {
+        ('+', new_total - old_total)
+    } else {
+        ('-', old_total - new_total)
+    };
+    writeln!(
+        out,
+        "Total {}: {}, was {} ({}{})",
+        size_mode.as_str(),
+        format_size(new_total),
+        format_size(old_total),
+        sign,
+        format_size(change)
+    )?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Passes output through to the terminal, counting the bytes, to see what each frame
+/// costs over a slow link.
+pub struct CountingWriter<W> {
+    inner: W,
+    written: Arc<AtomicU64>,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W, written: Arc<AtomicU64>) -> Self {
+        Self { inner, written }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// How the TUI's frames are doing, for the debug overlay
+#[derive(Default)]
+pub struct FrameStats {
+    pub frames: u64,
+    pub last_frame_bytes: u64,
+    pub total_bytes: u64,
+    /// Time spent filtering and sorting the list for the last frame
+    pub last_filter_time: Duration,
+    /// Scanner events taken in for the last frame
+    pub last_frame_events: usize,
+    // When the frames of the last second were drawn
+    recent: VecDeque<Instant>,
+}
+
+impl FrameStats {
+    /// Accounts for the frame just drawn, taking its bytes off `written`
+    pub fn record_frame(&mut self, written: &AtomicU64) {
+        let bytes = written.swap(0, Ordering::Relaxed);
+        let now = Instant::now();
+        self.recent.push_back(now);
+        while self
+            .recent
+            .front()
+            .is_some_and(|&drawn| now - drawn > Duration::from_secs(1))
+        {
+            self.recent.pop_front();
+        }
+        self.frames += 1;
+        self.last_frame_bytes = bytes;
+        self.total_bytes += bytes;
+    }
+
+    pub fn frames_per_second(&self) -> usize {
+        self.recent.len()
+    }
+
+    pub fn average_frame_bytes(&self) -> u64 {
+        self.total_bytes.checked_div(self.frames).unwrap_or(0)
+    }
+}
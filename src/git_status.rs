@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Files in a directory that git tracks, for warning before deleting a directory that
+/// someone committed files into (a checked-in `dist/`, say)
+pub struct TrackedFiles {
+    pub tracked: usize,
+    /// Tracked, and changed since they were last committed
+    pub modified: usize,
+}
+
+impl TrackedFiles {
+    /// What git tracks at or under `dir`, if it lives in a git work tree with anything
+    /// tracked in it. Nothing if git isn't installed or won't look (at a repository owned
+    /// by someone else, say).
+    pub fn in_dir(dir: &Path) -> Option<Self> {
+        let tracked = count_files(dir, "--cached")?;
+        if tracked == 0 {
+            return None;
+        }
+        Some(Self {
+            tracked,
+            modified: count_files(dir, "--modified").unwrap_or(0),
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let files = if self.tracked == 1 { "file" } else { "files" };
+        match self.modified {
+            0 => format!("Holds {} {} tracked by git!", self.tracked, files),
+            modified => format!(
+                "Holds {} {} tracked by git, {} with uncommitted changes!",
+                self.tracked, files, modified
+            ),
+        }
+    }
+}
+
+/// How many of `dirs`, all in the git work tree at `repo`, have anything tracked in them:
+/// asked of git a batch at a time rather than one directory at a time, as a bulk deletion
+/// may take in hundreds. Directories git won't list count as holding nothing.
+pub fn count_with_tracked_files(repo: &Path, dirs: &[&Path]) -> usize {
+    dirs.chunks(100)
+        .map(|batch| {
+            let relative: Vec<&Path> = batch
+                .iter()
+                .filter_map(|dir| dir.strip_prefix(repo).ok())
+                .collect();
+            let Some(tracked) = tracked_files(repo, &relative) else {
+                return 0;
+            };
+            relative
+                .iter()
+                .filter(|dir| tracked.iter().any(|file| file.starts_with(dir)))
+                .count()
+        })
+        .sum()
+}
+
+/// What `git ls-files` lists under `pathspecs` (relative to `repo`), relative to `repo`
+fn tracked_files(repo: &Path, pathspecs: &[&Path]) -> Option<Vec<PathBuf>> {
+    if pathspecs.is_empty() {
+        return Some(Vec::new());
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["--literal-pathspecs", "ls-files", "-z", "--cached", "--"])
+        .args(pathspecs)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        output
+            .stdout
+            .split(|&byte| byte == 0)
+            .filter(|file| !file.is_empty())
+            .map(path_from_bytes)
+            .collect(),
+    )
+}
+
+/// Which paths git ignores, asked of one `git check-ignore --stdin` per work tree that's
+/// kept running for as long as this is, rather than of a new git for every path
+#[derive(Default)]
+pub struct IgnoreChecker {
+    // Keyed by work tree; `None` once git can't be asked there (isn't installed, say)
+    work_trees: Mutex<HashMap<PathBuf, Arc<Mutex<Option<CheckIgnore>>>>>,
+}
+
+impl IgnoreChecker {
+    /// Whether `path` is in a git work tree that ignores it. Asks git itself, so that every
+    /// source of ignore rules counts (nested `.gitignore`s, `.git/info/exclude`, the global
+    /// excludes file); outside a work tree, or without git, nothing is ignored.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Some(work_tree) = path
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| ancestor.join(".git").exists())
+        else {
+            return false;
+        };
+        let Ok(relative) = path.strip_prefix(work_tree) else {
+            return false;
+        };
+        let process = self
+            .work_trees
+            .lock()
+            .unwrap()
+            .entry(work_tree.to_path_buf())
+            .or_insert_with(|| Arc::new(Mutex::new(CheckIgnore::start(work_tree))))
+            .clone();
+        let mut process = process.lock().unwrap();
+        let Some(check) = process.as_mut() else {
+            return false;
+        };
+        match check.ask(relative) {
+            Some(ignored) => ignored,
+            None => {
+                // Git went away or said something unexpected; don't keep asking it
+                *process = None;
+                false
+            }
+        }
+    }
+}
+
+/// `git check-ignore --stdin -z --verbose --non-matching`, which answers each path it's
+/// given with four fields: the ignore file, line and pattern that decided it (all empty
+/// if none did), and the path
+struct CheckIgnore {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl CheckIgnore {
+    fn start(work_tree: &Path) -> Option<Self> {
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(work_tree)
+            .args([
+                "check-ignore",
+                "--stdin",
+                "-z",
+                "--verbose",
+                "--non-matching",
+            ])
+            // Otherwise git holds on to its answers until its output buffer fills
+            .env("GIT_FLUSH", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take()?);
+        Some(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    fn ask(&mut self, relative: &Path) -> Option<bool> {
+        let stdin = self.stdin.as_mut()?;
+        let mut query = path_to_bytes(relative);
+        query.push(0);
+        stdin.write_all(&query).ok()?;
+        stdin.flush().ok()?;
+        let mut fields = [const { Vec::new() }; 4];
+        for field in &mut fields {
+            if self.stdout.read_until(0, field).ok()? == 0 || field.pop() != Some(0) {
+                return None;
+            }
+        }
+        // A negated pattern (`!dist`) matching means the path is un-ignored
+        let pattern = &fields[2];
+        Some(!pattern.is_empty() && !pattern.starts_with(b"!"))
+    }
+}
+
+impl Drop for CheckIgnore {
+    fn drop(&mut self) {
+        // Closing its input is what tells git it's done
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+/// Git writes paths as UTF-8 where they aren't just bytes
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// How many files `git ls-files <which>` lists under `dir`
+fn count_files(dir: &Path, which: &str) -> Option<usize> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "-z", which, "--", "."])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(output.stdout.iter().filter(|&&byte| byte == 0).count())
+}
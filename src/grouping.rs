@@ -0,0 +1,109 @@
+use crate::cycle::Cycle;
+use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use std::collections::{HashMap, HashSet};
+
+/// Whether the list is one flat list, or grouped under a header per kind of cruft or per
+/// project (`L`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    None,
+    Type,
+    Project,
+}
+
+impl Cycle for Grouping {
+    fn all_values() -> &'static [Self] {
+        static ALL: [Grouping; 3] = [Grouping::None, Grouping::Type, Grouping::Project];
+        &ALL
+    }
+}
+
+impl Grouping {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Grouping::None => "none",
+            Grouping::Type => "type",
+            Grouping::Project => "project",
+        }
+    }
+
+    /// The header `dir` goes under
+    pub fn label(&self, dir: &CruftDirectory) -> Option<String> {
+        match self {
+            Grouping::None => None,
+            Grouping::Type => Some(dir.crufty_reason.to_string()),
+            // With the path, as projects in different places may well share a name
+            Grouping::Project => Some(match dir.project {
+                Some(ref project) => format!(
+                    "{} ({})",
+                    dir.project_name().unwrap_or_default(),
+                    project.display()
+                ),
+                None => "no project".to_string(),
+            }),
+        }
+    }
+}
+
+/// A row of the list: a group's header, or an entry
+pub enum Row<'a> {
+    Group(Group),
+    Entry(&'a CruftDirectory),
+}
+
+pub struct Group {
+    pub label: String,
+    pub count: usize,
+    pub size: u64,
+    pub collapsed: bool,
+}
+
+/// The rows of the list for `dirs` (in the order they're shown in): the entries themselves
+/// when not grouping, and otherwise a header per group, biggest group first, each followed
+/// by its entries unless it's collapsed
+pub fn rows<'a>(
+    dirs: &[&'a CruftDirectory],
+    grouping: Grouping,
+    collapsed: &HashSet<String>,
+    size_mode: SizeMode,
+) -> Vec<Row<'a>> {
+    if grouping == Grouping::None {
+        return dirs.iter().map(|&dir| Row::Entry(dir)).collect();
+    }
+    let mut groups: Vec<(Group, Vec<&'a CruftDirectory>)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for &dir in dirs {
+        let label = grouping.label(dir).unwrap_or_default();
+        let position = *positions.entry(label.clone()).or_insert_with(|| {
+            groups.push((
+                Group {
+                    collapsed: collapsed.contains(&label),
+                    label,
+                    count: 0,
+                    size: 0,
+                },
+                Vec::new(),
+            ));
+            groups.len() - 1
+        });
+        let (group, entries) = &mut groups[position];
+        group.count += 1;
+        group.size += size_mode.size_of(dir);
+        entries.push(dir);
+    }
+    groups.sort_by(|a, b| {
+        b.0.size
+            .cmp(&a.0.size)
+            .then_with(|| a.0.label.cmp(&b.0.label))
+    });
+    let mut rows = Vec::with_capacity(dirs.len() + groups.len());
+    for (group, entries) in groups {
+        let collapsed = group.collapsed;
+        rows.push(Row::Group(group));
+        if !collapsed {
+            rows.extend(entries.into_iter().map(Row::Entry));
+        }
+    }
+    rows
+}
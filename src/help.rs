@@ -0,0 +1,148 @@
+use crate::keymap::{Command, keymap};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// The commands of the list, by what they're for
+const SECTIONS: &[(&str, &[Command])] = &[
+    (
+        "Moving around",
+        &[
+            Command::Next,
+            Command::Previous,
+            Command::PageDown,
+            Command::PageUp,
+            Command::HalfPageDown,
+            Command::HalfPageUp,
+            Command::First,
+            Command::Last,
+            Command::NextMatch,
+            Command::PreviousMatch,
+            Command::NextScreen,
+            Command::Back,
+        ],
+    ),
+    (
+        "Filtering and sorting",
+        &[
+            Command::Search,
+            Command::RegexFilter,
+            Command::CycleMinSize,
+            Command::RaiseMinSize,
+            Command::LowerMinSize,
+            Command::MinSize,
+            Command::MinFiles,
+            Command::CycleAge,
+            Command::Age,
+            Command::CommonTypes,
+            Command::PickTypes,
+            Command::CycleConfidence,
+            Command::DeletableOnly,
+            Command::PossibleCruft,
+            Command::ShowIgnored,
+            Command::Sort,
+            Command::ReverseSort,
+            Command::Grouping,
+            Command::SizeMode,
+            Command::OwnerColumns,
+        ],
+    ),
+    (
+        "The selected entry",
+        &[
+            Command::Browse,
+            Command::Actions,
+            Command::Preview,
+            Command::Inspect,
+            Command::Breakdown,
+            Command::Delete,
+            Command::Ignore,
+            Command::IgnoreSimilar,
+            Command::Keep,
+            Command::Note,
+            Command::CopyPath,
+            Command::Open,
+            Command::RescanEntry,
+        ],
+    ),
+    (
+        "Everything shown",
+        &[
+            Command::DeleteAll,
+            Command::Undo,
+            Command::DeletedItems,
+            Command::Purge,
+        ],
+    ),
+    (
+        "The scan",
+        &[
+            Command::CancelScan,
+            Command::Rescan,
+            Command::Errors,
+            Command::ExcludeSuggestion,
+            Command::Scope,
+            Command::History,
+            Command::Debug,
+            Command::Help,
+            Command::Quit,
+        ],
+    ),
+];
+
+/// How many lines the help has, for scrolling it
+pub fn line_count(settings: &[(&str, String)]) -> usize {
+    // The settings have a heading, and every other section a blank line and a heading
+    1 + settings.len()
+        + SECTIONS
+            .iter()
+            .map(|(_, commands)| commands.len() + 2)
+            .sum::<usize>()
+}
+
+/// The help overlay (`?`): how the list is filtered and sorted right now, followed by every
+/// command, the keys for it (as remapped in `[keys]`) and its name there, scrolled down by `scroll` lines
+pub fn draw(f: &mut Frame, settings: &[(&str, String)], scroll: usize) {
+    let heading = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::styled("Current settings", heading)];
+    for (label, value) in settings {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<16}", label), bold),
+            Span::raw(value.clone()),
+        ]));
+    }
+    for (title, commands) in SECTIONS {
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(*title, heading));
+        for command in commands.iter() {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<16}", keymap().keys_for(*command)), bold),
+                Span::raw(format!("{:<52}", command.description())),
+                Span::styled(command.name(), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+    }
+
+    let area = f.area();
+    let popup_area = Rect::new(
+        area.width / 10,
+        area.height / 10,
+        area.width - area.width / 5,
+        area.height - area.height / 5,
+    );
+    let panel = Paragraph::new(lines)
+        .scroll((scroll.min(u16::MAX as usize) as u16, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title("Help (j/k to scroll, any other key to close)"),
+        );
+    f.render_widget(Clear, popup_area);
+    f.render_widget(panel, popup_area);
+}
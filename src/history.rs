@@ -0,0 +1,232 @@
+use crate::audit::civil_date;
+use crate::scanner::{CruftDirectory, CruftyReason};
+use crate::units::format_size;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where deletions are recorded from here on, and the scan root they're counted under
+static HISTORY: OnceLock<(History, PathBuf)> = OnceLock::new();
+
+/// What decruft has scanned and deleted over time, one JSON record per line in decruft's
+/// data directory, for `decruft stats` and the TUI's history screen (`H`). Unlike the
+/// audit log, this is always kept, and only has totals: no paths but the scan roots.
+pub struct History {
+    path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Record {
+    Scan {
+        /// Seconds since the epoch
+        at: u64,
+        root: PathBuf,
+        found: usize,
+        size: u64,
+    },
+    Delete {
+        at: u64,
+        root: PathBuf,
+        reason: CruftyReason,
+        size: u64,
+    },
+}
+
+/// Records deletions (and scans) under `root` in `history` from here on
+pub fn enable(history: History, root: &Path) {
+    let _ = HISTORY.set((history, root.to_path_buf()));
+}
+
+/// Records that `dir` was deleted for good. Failing to is no reason to fail the deletion,
+/// so errors go unreported.
+pub fn record_deletion(dir: &CruftDirectory) {
+    if let Some((history, root)) = HISTORY.get() {
+        let _ = history.append(&Record::Delete {
+            at: now(),
+            root: root.clone(),
+            reason: dir.crufty_reason.clone(),
+            size: dir.size,
+        });
+    }
+}
+
+/// Records a finished scan of `root` and what it found
+pub fn record_scan(root: &Path, found: &[CruftDirectory]) {
+    if let Some((history, _)) = HISTORY.get() {
+        let _ = history.append(&Record::Scan {
+            at: now(),
+            root: root.to_path_buf(),
+            found: found.len(),
+            size: found.iter().map(|dir| dir.size).sum(),
+        });
+    }
+}
+
+/// The totals of the history being recorded to, as lines for printing
+pub fn stats() -> Result<Vec<String>> {
+    match HISTORY.get() {
+        Some((history, _)) => history.stats(),
+        None => Ok(vec!["No history is kept (no data directory)".to_string()]),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[derive(Default)]
+struct Total {
+    size: u64,
+    count: usize,
+}
+
+impl Total {
+    fn add(&mut self, size: u64) {
+        self.size += size;
+        self.count += 1;
+    }
+
+    fn line(&self, label: &str) -> String {
+        format!(
+            "  {:<24} {:>15}  ({} deleted)",
+            label,
+            format_size(self.size),
+            self.count
+        )
+    }
+}
+
+impl History {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::data_dir().map(|dir| dir.join("history.jsonl"))
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn append(&self, record: &Record) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// Every record, skipping lines that can't be read (say, from a newer version)
+    fn records(&self) -> Result<Vec<Record>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to open {}", self.path.display()));
+            }
+        };
+        Ok(BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect())
+    }
+
+    /// What was freed in total, and by year, by month of this year, by rule and by scan root
+    fn stats(&self) -> Result<Vec<String>> {
+        let records = self.records()?;
+        let mut total = Total::default();
+        let mut scans = 0;
+        let mut first = None;
+        let mut by_year: BTreeMap<i64, Total> = BTreeMap::new();
+        let mut by_month: BTreeMap<i64, Total> = BTreeMap::new();
+        let mut by_reason: BTreeMap<String, Total> = BTreeMap::new();
+        let mut by_root: BTreeMap<PathBuf, Total> = BTreeMap::new();
+        let (this_year, _, _) = civil_date(now());
+        for record in &records {
+            match record {
+                Record::Scan { at, .. } => {
+                    scans += 1;
+                    first = first.or(Some(*at));
+                }
+                Record::Delete {
+                    at,
+                    root,
+                    reason,
+                    size,
+                } => {
+                    first = first.or(Some(*at));
+                    let (year, month, _) = civil_date(*at);
+                    total.add(*size);
+                    by_year.entry(year).or_default().add(*size);
+                    if year == this_year {
+                        by_month.entry(month).or_default().add(*size);
+                    }
+                    by_reason.entry(reason.to_string()).or_default().add(*size);
+                    by_root.entry(root.clone()).or_default().add(*size);
+                }
+            }
+        }
+        let Some(first) = first else {
+            return Ok(vec!["Nothing scanned or deleted yet".to_string()]);
+        };
+        let (year, month, day) = civil_date(first);
+        let mut lines = vec![format!(
+            "Freed {} in {} deletions and {} scans since {:04}-{:02}-{:02}",
+            format_size(total.size),
+            total.count,
+            scans,
+            year,
+            month,
+            day
+        )];
+        lines.push(String::new());
+        lines.push("By year:".to_string());
+        lines.extend(
+            by_year
+                .iter()
+                .map(|(year, total)| total.line(&year.to_string())),
+        );
+        if !by_month.is_empty() {
+            lines.push(format!("By month of {}:", this_year));
+            lines.extend(
+                by_month
+                    .iter()
+                    .map(|(month, total)| total.line(&format!("{:04}-{:02}", this_year, month))),
+            );
+        }
+        let biggest_first = |totals: BTreeMap<String, Total>| {
+            let mut totals: Vec<_> = totals.into_iter().collect();
+            totals.sort_by_key(|(_, total)| std::cmp::Reverse(total.size));
+            totals
+        };
+        lines.push("By rule:".to_string());
+        lines.extend(
+            biggest_first(by_reason)
+                .iter()
+                .map(|(reason, total)| total.line(reason)),
+        );
+        lines.push("By scan root:".to_string());
+        let by_root = by_root
+            .into_iter()
+            .map(|(root, total)| (root.display().to_string(), total))
+            .collect();
+        lines.extend(
+            biggest_first(by_root)
+                .iter()
+                .map(|(root, total)| total.line(root)),
+        );
+        Ok(lines)
+    }
+}
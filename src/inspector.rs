@@ -0,0 +1,82 @@
+use crate::io_scheduler;
+use crate::scanner::{self, CruftDirectory};
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// How many of the most recently modified files the inspector lists
+const NEWEST_FILES: usize = 5;
+
+/// What the inspector found out about an entry, which takes a walk of it
+pub struct Inspection {
+    /// The facts detection went by (marker files, the name, ...)
+    pub evidence: Vec<String>,
+    /// The most recently modified files, newest first, relative to the entry
+    pub newest: Vec<(PathBuf, SystemTime)>,
+}
+
+/// The "why is this flagged" view of an entry (`i`), worked out in the background
+/// so that inspecting a huge directory doesn't freeze the list
+pub struct Inspector {
+    pub path: PathBuf,
+    receiver: Receiver<Inspection>,
+    inspection: Option<Inspection>,
+}
+
+impl Inspector {
+    pub fn start(dir: &CruftDirectory) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let path = dir.path.clone();
+        let git_ignored = dir.git_ignored;
+        std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let _ = sender.send(Inspection {
+                    evidence: scanner::evidence(&path, git_ignored),
+                    newest: io_scheduler::run(&path, || newest_files(&path)),
+                });
+            }
+        });
+        Self {
+            path,
+            receiver,
+            inspection: None,
+        }
+    }
+
+    /// Picks up the inspection if it has become ready
+    pub fn poll(&mut self) {
+        if self.inspection.is_none() {
+            self.inspection = self.receiver.try_recv().ok();
+        }
+    }
+
+    pub fn inspection(&self) -> Option<&Inspection> {
+        self.inspection.as_ref()
+    }
+}
+
+fn newest_files(path: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut newest: Vec<(PathBuf, SystemTime)> = Vec::with_capacity(NEWEST_FILES + 1);
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok()) else {
+            continue;
+        };
+        if newest.len() == NEWEST_FILES && newest.last().is_some_and(|(_, t)| *t >= modified) {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+        newest.push((relative.to_path_buf(), modified));
+        newest.sort_by_key(|(_, modified)| Reverse(*modified));
+        newest.truncate(NEWEST_FILES);
+    }
+    newest
+}
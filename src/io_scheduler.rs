@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+
+/// How many heavy jobs may read a spinning disk at once; more only make its head seek
+const ROTATIONAL_JOBS: usize = 2;
+
+/// Limits how many heavy IO jobs (walking a tree to size it or find its newest files,
+/// deleting one) run on each device at once, across everything that does them: the
+/// scan's sizing pool, the inspector and deletions all queue here for their device.
+/// Spinning disks get a couple of slots, anything else one per CPU.
+pub fn run<T>(path: &Path, job: impl FnOnce() -> T) -> T {
+    let slots = scheduler().slots_for(path);
+    slots.acquire();
+    let _release = Release(&slots);
+    job()
+}
+
+#[derive(Default)]
+struct Scheduler {
+    devices: Mutex<HashMap<Option<u64>, Arc<Slots>>>,
+}
+
+fn scheduler() -> &'static Scheduler {
+    static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+    SCHEDULER.get_or_init(Scheduler::default)
+}
+
+impl Scheduler {
+    fn slots_for(&self, path: &Path) -> Arc<Slots> {
+        let device = device_id(path);
+        let mut devices = self.devices.lock().unwrap();
+        Arc::clone(devices.entry(device).or_insert_with(|| {
+            let limit = if device.is_some_and(is_rotational) {
+                ROTATIONAL_JOBS
+            } else {
+                std::thread::available_parallelism().map_or(4, |n| n.get())
+            };
+            Arc::new(Slots {
+                in_use: Mutex::new(0),
+                freed: Condvar::new(),
+                limit,
+            })
+        }))
+    }
+}
+
+struct Slots {
+    in_use: Mutex<usize>,
+    freed: Condvar,
+    limit: usize,
+}
+
+impl Slots {
+    fn acquire(&self) {
+        let mut in_use = self.in_use.lock().unwrap();
+        while *in_use >= self.limit {
+            in_use = self.freed.wait(in_use).unwrap();
+        }
+        *in_use += 1;
+    }
+}
+
+/// Gives the slot back when the job is done, even if it panics
+struct Release<'a>(&'a Slots);
+
+impl Drop for Release<'_> {
+    fn drop(&mut self) {
+        *self.0.in_use.lock().unwrap() -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Whether the block device behind `dev` is a spinning disk, as sysfs says; partitions
+/// have it in their parent's queue. Anything else (and anything not on Linux) is assumed
+/// to be solid-state, or not a disk at all.
+fn is_rotational(dev: u64) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let block = format!(
+            "/sys/dev/block/{}:{}",
+            rustix::fs::major(dev),
+            rustix::fs::minor(dev)
+        );
+        for queue in ["queue/rotational", "../queue/rotational"] {
+            if let Ok(rotational) = std::fs::read_to_string(Path::new(&block).join(queue)) {
+                return rotational.trim() == "1";
+            }
+        }
+    }
+    let _ = dev;
+    false
+}
@@ -0,0 +1,536 @@
+use anyhow::{Context, Result, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// `[keys]`: which key does what in the list, once remapped
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+/// Something a key does in the list (and on the totals and treemap screens). Popups and
+/// prompts keep their own keys: j/k or the arrows to move, Enter to pick, Esc to close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    Next,
+    Previous,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    First,
+    Last,
+    NextMatch,
+    PreviousMatch,
+    NextScreen,
+    Back,
+    Search,
+    RegexFilter,
+    CycleMinSize,
+    RaiseMinSize,
+    LowerMinSize,
+    MinSize,
+    MinFiles,
+    CycleAge,
+    Age,
+    CommonTypes,
+    PickTypes,
+    CycleConfidence,
+    DeletableOnly,
+    PossibleCruft,
+    ShowIgnored,
+    Sort,
+    ReverseSort,
+    Grouping,
+    SizeMode,
+    OwnerColumns,
+    Browse,
+    Actions,
+    Preview,
+    Inspect,
+    Breakdown,
+    Delete,
+    Ignore,
+    IgnoreSimilar,
+    Keep,
+    Note,
+    CopyPath,
+    Open,
+    RescanEntry,
+    DeleteAll,
+    Undo,
+    DeletedItems,
+    Purge,
+    CancelScan,
+    Rescan,
+    Errors,
+    ExcludeSuggestion,
+    Scope,
+    History,
+    Debug,
+    Help,
+    Quit,
+}
+
+/// Every command: its name in `[keys]`, and what it does
+const COMMANDS: &[(Command, &str, &str)] = &[
+    (Command::Next, "next", "Next entry"),
+    (Command::Previous, "previous", "Previous entry"),
+    (Command::PageDown, "page_down", "A screenful down"),
+    (Command::PageUp, "page_up", "A screenful up"),
+    (
+        Command::HalfPageDown,
+        "half_page_down",
+        "Half a screenful down",
+    ),
+    (Command::HalfPageUp, "half_page_up", "Half a screenful up"),
+    (Command::First, "first", "First entry"),
+    (Command::Last, "last", "Last entry"),
+    (Command::NextMatch, "next_match", "Next search match"),
+    (
+        Command::PreviousMatch,
+        "previous_match",
+        "Previous search match",
+    ),
+    (
+        Command::NextScreen,
+        "next_screen",
+        "Totals, then the treemap, then back to the list",
+    ),
+    (Command::Back, "back", "Back to the list"),
+    (
+        Command::Search,
+        "search",
+        "Search (text, path:, reason:, project:, owner:)",
+    ),
+    (
+        Command::RegexFilter,
+        "regex_filter",
+        "Only paths matching a regex",
+    ),
+    (
+        Command::CycleMinSize,
+        "cycle_min_size",
+        "Cycle the minimum size",
+    ),
+    (
+        Command::RaiseMinSize,
+        "raise_min_size",
+        "Raise the minimum size a step",
+    ),
+    (
+        Command::LowerMinSize,
+        "lower_min_size",
+        "Lower the minimum size a step",
+    ),
+    (Command::MinSize, "min_size", "Type a minimum size"),
+    (
+        Command::MinFiles,
+        "min_files",
+        "Type a minimum number of files",
+    ),
+    (Command::CycleAge, "cycle_age", "Cycle the age filter"),
+    (Command::Age, "age", "Type an age"),
+    (
+        Command::CommonTypes,
+        "common_types",
+        "All or only common types of cruft",
+    ),
+    (
+        Command::PickTypes,
+        "pick_types",
+        "Pick types of cruft to hide",
+    ),
+    (
+        Command::CycleConfidence,
+        "cycle_confidence",
+        "Cycle the minimum confidence",
+    ),
+    (
+        Command::DeletableOnly,
+        "deletable_only",
+        "Only what you can delete",
+    ),
+    (
+        Command::PossibleCruft,
+        "possible_cruft",
+        "Show/hide possible cruft",
+    ),
+    (
+        Command::ShowIgnored,
+        "show_ignored",
+        "Show/hide ignored entries",
+    ),
+    (Command::Sort, "sort", "Cycle the sort"),
+    (Command::ReverseSort, "reverse_sort", "Reverse the sort"),
+    (
+        Command::Grouping,
+        "grouping",
+        "Group by type, then by project",
+    ),
+    (
+        Command::SizeMode,
+        "size_mode",
+        "Size, disk usage or unique disk usage",
+    ),
+    (Command::OwnerColumns, "owner_columns", "Owner columns"),
+    (
+        Command::Browse,
+        "browse",
+        "Browse what's inside (on a group header, fold it)",
+    ),
+    (Command::Actions, "actions", "Actions menu"),
+    (Command::Preview, "preview", "Preview pane"),
+    (Command::Inspect, "inspect", "Why it's flagged"),
+    (Command::Breakdown, "breakdown", "Size breakdown"),
+    (
+        Command::Delete,
+        "delete",
+        "Delete it (on a group header, the whole group)",
+    ),
+    (Command::Ignore, "ignore", "Ignore it"),
+    (
+        Command::IgnoreSimilar,
+        "ignore_similar",
+        "Ignore all like it",
+    ),
+    (Command::Keep, "keep", "Keep it"),
+    (Command::Note, "note", "Note"),
+    (Command::CopyPath, "copy_path", "Copy its path"),
+    (Command::Open, "open", "Open it in the file manager"),
+    (
+        Command::RescanEntry,
+        "rescan_entry",
+        "Check and size it again",
+    ),
+    (Command::DeleteAll, "delete_all", "Delete everything shown"),
+    (Command::Undo, "undo", "Undo the last deletion"),
+    (
+        Command::DeletedItems,
+        "deleted_items",
+        "Deleted items, to restore or purge",
+    ),
+    (Command::Purge, "purge", "Purge deleted items now"),
+    (Command::CancelScan, "cancel_scan", "Stop the scan"),
+    (Command::Rescan, "rescan", "Rescan"),
+    (Command::Errors, "errors", "Unreadable paths"),
+    (
+        Command::ExcludeSuggestion,
+        "exclude_suggestion",
+        "Leave the suggested slow subtree out of future scans",
+    ),
+    (Command::Scope, "scope", "Scan scope"),
+    (Command::History, "history", "History"),
+    (Command::Debug, "debug", "Debug overlay"),
+    (Command::Help, "help", "This help"),
+    (Command::Quit, "quit", "Quit"),
+];
+
+impl Command {
+    /// Its name in `[keys]`, like `half_page_down`
+    pub fn name(&self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(command, _, _)| command == self)
+            .map_or("", |(_, name, _)| name)
+    }
+
+    pub fn description(&self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(command, _, _)| command == self)
+            .map_or("", |(_, _, description)| description)
+    }
+
+    fn named(name: &str) -> Option<Command> {
+        COMMANDS
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|(command, _, _)| *command)
+    }
+
+    fn default_keys(&self) -> Vec<Key> {
+        let keys: &[Key] = match self {
+            Command::Next => &[Key::char('j'), Key::code(KeyCode::Down)],
+            Command::Previous => &[Key::char('k'), Key::code(KeyCode::Up)],
+            Command::PageDown => &[Key::code(KeyCode::PageDown)],
+            Command::PageUp => &[Key::code(KeyCode::PageUp)],
+            Command::HalfPageDown => &[Key::ctrl('d')],
+            Command::HalfPageUp => &[Key::ctrl('u')],
+            Command::First => &[Key::code(KeyCode::Home), Key::char('g')],
+            Command::Last => &[Key::code(KeyCode::End), Key::char('G')],
+            Command::NextMatch => &[Key::char('n')],
+            Command::PreviousMatch => &[Key::char('N')],
+            Command::NextScreen => &[Key::code(KeyCode::Tab)],
+            Command::Back => &[Key::code(KeyCode::Esc)],
+            Command::Search => &[Key::char('/')],
+            Command::RegexFilter => &[Key::char('F')],
+            Command::CycleMinSize => &[Key::char('s')],
+            Command::RaiseMinSize => &[Key::char('+')],
+            Command::LowerMinSize => &[Key::char('-')],
+            Command::MinSize => &[Key::char('=')],
+            Command::MinFiles => &[Key::char('#')],
+            Command::CycleAge => &[Key::char('o')],
+            Command::Age => &[Key::char('>')],
+            Command::CommonTypes => &[Key::char('a')],
+            Command::PickTypes => &[Key::char('A')],
+            Command::CycleConfidence => &[Key::char('f')],
+            Command::DeletableOnly => &[Key::char('w')],
+            Command::PossibleCruft => &[Key::char('p')],
+            Command::ShowIgnored => &[Key::char('X')],
+            Command::Sort => &[Key::char('r')],
+            Command::ReverseSort => &[Key::char('~')],
+            Command::Grouping => &[Key::char('L')],
+            Command::SizeMode => &[Key::char('z')],
+            Command::OwnerColumns => &[Key::char('u')],
+            Command::Browse => &[Key::code(KeyCode::Enter)],
+            Command::Actions => &[Key::char('m')],
+            Command::Preview => &[Key::char('v')],
+            Command::Inspect => &[Key::char('i')],
+            Command::Breakdown => &[Key::char('b')],
+            Command::Delete => &[Key::char('d')],
+            Command::Ignore => &[Key::char('x')],
+            Command::IgnoreSimilar => &[Key::char('I')],
+            Command::Keep => &[Key::char('K')],
+            Command::Note => &[Key::char('t')],
+            Command::CopyPath => &[Key::char('y')],
+            Command::Open => &[Key::char('O')],
+            Command::RescanEntry => &[Key::ctrl('r')],
+            Command::DeleteAll => &[Key::char('D')],
+            Command::Undo => &[Key::char('U')],
+            Command::DeletedItems => &[Key::char('T')],
+            Command::Purge => &[Key::char('P')],
+            Command::CancelScan => &[Key::char('c')],
+            Command::Rescan => &[Key::char('R'), Key::code(KeyCode::F(5))],
+            Command::Errors => &[Key::char('e')],
+            Command::ExcludeSuggestion => &[Key::char('E')],
+            Command::Scope => &[Key::char('S')],
+            Command::History => &[Key::char('H')],
+            Command::Debug => &[Key::code(KeyCode::F(12))],
+            Command::Help => &[Key::char('?')],
+            Command::Quit => &[Key::char('q')],
+        };
+        keys.to_vec()
+    }
+}
+
+/// A key, with or without Ctrl; Shift is part of the character (`G`, `?`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    ctrl: bool,
+}
+
+impl Key {
+    const fn char(c: char) -> Self {
+        Self::code(KeyCode::Char(c))
+    }
+
+    const fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            ctrl: true,
+        }
+    }
+
+    const fn code(code: KeyCode) -> Self {
+        Self { code, ctrl: false }
+    }
+
+    fn of(event: &KeyEvent) -> Self {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let code = match event.code {
+            // Terminals don't tell Ctrl-D from Ctrl-d
+            KeyCode::Char(c) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Self { code, ctrl }
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    /// A character (`x`, `?`), a key's name (`Down`, `PageUp`, `F5`, `Space`), either of them
+    /// after `Ctrl-`
+    fn from_str(s: &str) -> Result<Self> {
+        let (ctrl, name) = match s.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("ctrl-") && s.len() > 5 => (true, &s[5..]),
+            _ => (false, s),
+        };
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "esc" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "insert" | "ins" => KeyCode::Insert,
+                "delete" | "del" => KeyCode::Delete,
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => bail!("{:?} isn't a key", s),
+                },
+            },
+        };
+        Ok(Self { code, ctrl })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Delete => write!(f, "Del"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// The keys for a command in `[keys]`: one (`delete = "x"`) or several (`next = ["j", "Down"]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Several(Vec<String>),
+}
+
+impl Keys {
+    fn parse(&self) -> Result<Vec<Key>> {
+        match self {
+            Keys::One(key) => Ok(vec![key.parse()?]),
+            Keys::Several(keys) => keys.iter().map(|key| key.parse()).collect(),
+        }
+    }
+}
+
+/// Which key does what
+pub struct Keymap {
+    commands: HashMap<Key, Command>,
+    keys: HashMap<Command, Vec<Key>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_keys(
+            COMMANDS
+                .iter()
+                .map(|(command, _, _)| (*command, command.default_keys()))
+                .collect(),
+        )
+    }
+}
+
+impl Keymap {
+    /// The default keys, with the commands in `overrides` given theirs instead. A key given to
+    /// one command is taken away from whatever had it by default, so `delete = "x"` is enough
+    /// to delete with `x`, at the cost of ignoring with it; an empty list unbinds a command.
+    pub fn new(overrides: &HashMap<String, Keys>) -> Result<Self> {
+        let mut keys: HashMap<Command, Vec<Key>> = Keymap::default().keys;
+        let mut claimed: HashMap<Key, Command> = HashMap::new();
+        for (name, list) in overrides {
+            let command = Command::named(name)
+                .with_context(|| format!("[keys]: there's no command called {:?}", name))?;
+            let list = list.parse().with_context(|| format!("[keys] {}", name))?;
+            for key in &list {
+                if let Some(other) = claimed.insert(*key, command)
+                    && other != command
+                {
+                    bail!(
+                        "[keys]: {} is given to both {} and {}",
+                        key,
+                        other.name(),
+                        command.name()
+                    );
+                }
+            }
+            keys.insert(command, list);
+        }
+        for (command, list) in keys.iter_mut() {
+            if !claimed.values().any(|c| c == command) {
+                list.retain(|key| !claimed.contains_key(key));
+            }
+        }
+        Ok(Self::from_keys(keys))
+    }
+
+    fn from_keys(keys: HashMap<Command, Vec<Key>>) -> Self {
+        let commands = keys
+            .iter()
+            .flat_map(|(command, list)| list.iter().map(|key| (*key, *command)))
+            .collect();
+        Self { commands, keys }
+    }
+
+    /// What `event` does, if anything
+    pub fn command(&self, event: &KeyEvent) -> Option<Command> {
+        self.commands.get(&Key::of(event)).copied()
+    }
+
+    /// All the keys for `command`, as in "j, Down"
+    pub fn keys_for(&self, command: Command) -> String {
+        match self.keys.get(&command) {
+            Some(keys) if !keys.is_empty() => keys
+                .iter()
+                .map(Key::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => "(none)".to_string(),
+        }
+    }
+
+    /// The first key for `command`, for mentioning it in passing
+    pub fn key_for(&self, command: Command) -> String {
+        self.keys
+            .get(&command)
+            .and_then(|keys| keys.first())
+            .map_or_else(|| "(none)".to_string(), Key::to_string)
+    }
+
+    /// A help line like "j/k: Navigate | q: Quit", with the first key of each of the commands
+    /// before each label; labels none of whose commands have a key are left out
+    pub fn hints(&self, items: &[(&[Command], &str)]) -> String {
+        items
+            .iter()
+            .filter_map(|(commands, label)| {
+                let keys: Vec<String> = commands
+                    .iter()
+                    .filter_map(|command| self.keys.get(command)?.first())
+                    .map(Key::to_string)
+                    .collect();
+                (!keys.is_empty()).then(|| format!("{}: {}", keys.join("/"), label))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// Uses `keymap` from here on
+pub fn use_keymap(keymap: Keymap) {
+    let _ = KEYMAP.set(keymap);
+}
+
+/// The keys in use: the defaults, unless `[keys]` says otherwise
+pub fn keymap() -> &'static Keymap {
+    KEYMAP.get_or_init(Keymap::default)
+}
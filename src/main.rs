@@ -1,107 +1,894 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use collation::Collation;
 use crossterm::ExecutableCommand;
 use crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::mpsc;
+use std::time::Duration;
 
+mod action_menu;
 mod age_filter;
+mod all_users;
+mod app_dirs;
+mod archive;
+mod audit;
+mod browser;
+mod clean;
+mod collation;
+mod confidence;
+mod config;
+mod confirm_policy;
 mod cycle;
+mod dashboard;
+mod decruftignore;
+mod delete;
+mod delete_worker;
+mod diff;
+mod frame_stats;
+mod git_status;
+mod grouping;
+mod help;
+mod history;
+mod inspector;
+mod io_scheduler;
+mod keymap;
+mod metadata;
+mod metrics;
+mod mounts;
+mod open_files;
+mod output_format;
+mod ownership;
+mod parse;
+mod preview;
+mod protect;
+mod rebuild;
+mod rules;
+mod safety;
 mod scanner;
+mod scope;
+mod screen;
+mod search;
+mod self_test;
+mod size_cache;
+mod size_estimator;
 mod size_filter;
+mod size_mode;
 mod sort_order;
+mod staging;
+mod treemap;
+mod type_filter;
 mod ui;
+mod units;
+mod watch;
+mod work_queue;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Maximum depth to search
-    #[arg(short, long, default_value_t = 3)]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Maximum depth to search; 0 for no limit
+    #[arg(short, long, default_value_t = 3, global = true)]
     max_depth: usize,
 
+    /// Search at any depth, as with `--max-depth 0`
+    #[arg(long, global = true, conflicts_with = "max_depth")]
+    unlimited: bool,
+
     /// Starting directory
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     dir: Option<PathBuf>,
 
     /// Just scan directories, do not show TUI
     #[arg(long)]
     scan_only: bool,
+
+    /// Scan every user's home directory instead of `--dir` and print how much each could
+    /// reclaim (root only)
+    #[arg(long, conflicts_with = "dir")]
+    all_users: bool,
+
+    /// How `--scan-only` prints its results (also used when stdout isn't a terminal)
+    #[arg(long, value_enum, default_value_t = output_format::OutputFormat::Text)]
+    format: output_format::OutputFormat,
+
+    /// Stop scanning after this long (e.g. 60s, 5m) and show what was found so far
+    #[arg(long, value_parser = parse::parse_duration, global = true)]
+    max_scan_time: Option<Duration>,
+
+    /// Don't cross filesystem boundaries (NFS mounts, bind-mounted volumes, ...), like `du -x`
+    #[arg(short = 'x', long, global = true)]
+    one_file_system: bool,
+
+    /// Descend into symlinked directories and count what symlinks point to in sizes
+    #[arg(long, global = true, overrides_with = "no_follow_symlinks")]
+    follow_symlinks: bool,
+
+    /// Don't follow symlinks, even if the config file says to (the default)
+    #[arg(long, global = true, overrides_with = "follow_symlinks")]
+    no_follow_symlinks: bool,
+
+    /// Leave paths matching this glob out of the scan entirely (e.g. `'**/photos'`); repeatable
+    #[arg(long, value_name = "GLOB", global = true)]
+    exclude: Vec<String>,
+
+    /// Also collect macOS Finder leftovers (`.DS_Store`, `._*`, `__MACOSX`) into one entry
+    #[arg(long, global = true)]
+    finder_metadata: bool,
+
+    /// Also report well-known per-user caches (~/.cache, ~/.npm, ~/.cargo/registry, ...)
+    #[arg(long, global = true)]
+    user_caches: bool,
+
+    /// Report space allocated on disk instead of apparent file sizes
+    #[arg(long, global = true)]
+    disk_usage: bool,
+
+    /// Always walk found directories for their sizes, instead of asking cheaper sources
+    /// (like `git count-objects`) where there are any
+    #[arg(long, global = true)]
+    exact_sizes: bool,
+
+    /// Size every found directory from scratch, instead of reusing sizes from earlier
+    /// scans for those that look unchanged
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Report only the disk space that deleting would free, leaving out files
+    /// hard-linked from elsewhere (as in pnpm stores)
+    #[arg(long, global = true, conflicts_with = "disk_usage")]
+    unique_size: bool,
+
+    /// Leave out directories smaller than this (e.g. 500M, 1.5G)
+    #[arg(long, value_name = "SIZE", value_parser = parse::parse_size, global = true)]
+    min_size: Option<u64>,
+
+    /// Leave out directories with anything changed more recently than this (e.g. 90d, 6mo, 2y)
+    #[arg(long, value_name = "AGE", value_parser = parse::parse_age, global = true)]
+    older_than: Option<u64>,
+
+    /// Leave out directories with fewer files than this; `#` changes it in the TUI
+    #[arg(long, value_name = "COUNT", global = true)]
+    min_files: Option<u64>,
+
+    /// Only show directories whose path matches this regular expression
+    /// (e.g. 'clients/.*/legacy'); `F` changes it in the TUI
+    #[arg(long, value_name = "REGEX", global = true)]
+    filter_regex: Option<regex::Regex>,
+
+    /// Only show directories owned by this user (name or uid)
+    #[arg(long, global = true)]
+    owner: Option<String>,
+
+    /// How to compare names when sorting alphabetically
+    #[arg(long, value_enum, default_value_t = Collation::Natural)]
+    collation: Collation,
+
+    /// No spinner, and redraw once a second unless `--poll-interval` says otherwise
+    #[arg(long)]
+    reduced_motion: bool,
+
+    /// Keep screen updates small for high-latency SSH: no spinner or separator lines,
+    /// and a slower redraw rate unless `--poll-interval` says otherwise
+    #[arg(long)]
+    low_bandwidth: bool,
+
+    /// How often to redraw the TUI while nothing is pressed (e.g. 100ms, 2s); raise it on slow links
+    #[arg(long, value_parser = parse::parse_duration)]
+    poll_interval: Option<Duration>,
+
+    /// Keep watching for changes after the scan, updating sizes and adding new cruft
+    /// (say, from a build that just ran) while the TUI is open
+    #[arg(long)]
+    watch: bool,
+
+    /// Check that this build works here, on a temporary tree of known cruft
+    #[arg(long, hide = true)]
+    self_test: bool,
+
+    /// Configuration file to use instead of the default one
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Go through the motions of deleting, in the TUI or with `clean`, but only log and
+    /// tally what would have been removed
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Overwrite the contents of each file before deleting it, for cruft that may hold
+    /// secrets. Slow, and only best-effort: SSDs, copy-on-write filesystems and snapshots
+    /// may keep copies regardless (Unix only)
+    #[arg(long, global = true)]
+    shred: bool,
+
+    /// Delete flagged directories even if there's a git repository somewhere inside them,
+    /// which usually means a rule matched real source code
+    #[arg(long, global = true)]
+    allow_git_repos: bool,
+
+    /// Log every deletion to this file (always on when running as root)
+    #[arg(long, global = true)]
+    audit_log: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Delete everything found without asking, for cron jobs and CI.
+    /// Only directories under the config's `[automation] allowed_roots` are deleted.
+    Clean {
+        /// Actually delete; without this, only list what would be deleted
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Show which directories appeared, disappeared or grew since a saved scan
+    /// (from `--scan-only --format json > FILE`)
+    Diff {
+        /// The earlier scan
+        old: PathBuf,
+        /// The later scan to compare it with
+        #[arg(required_unless_present = "scan")]
+        new: Option<PathBuf>,
+        /// Compare with a fresh scan of `--dir` instead
+        #[arg(long, conflicts_with = "new")]
+        scan: bool,
+    },
+    /// Share ignore lists, keep-marks and notes with other machines
+    Metadata {
+        #[command(subcommand)]
+        action: MetadataAction,
+    },
+    /// Show how much decruft has freed over time, by year, month, rule and scan root
+    Stats,
+    /// Try out the detection rules, excludes and ignore files
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RulesAction {
+    /// List what each rule matches under a directory, without working out sizes,
+    /// in a stable order that can be diffed between runs
+    Test {
+        /// The directory to evaluate the rules over
+        #[arg(long, value_name = "ROOT")]
+        against: PathBuf,
+    },
+    /// Check the `[[rules.expect]]` entries of the config against the rules, to notice
+    /// when an upgrade changes what is detected
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+enum MetadataAction {
+    /// Write the metadata for everything under `--dir` to a file, with paths relative to it
+    Export { file: PathBuf },
+    /// Merge an exported file into the local metadata, resolving its paths against `--dir`
+    Import { file: PathBuf },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    if args.self_test {
+        return self_test::run();
+    }
+    args.collation.init();
+    if args.dry_run {
+        delete::enable_dry_run();
+    }
+    if args.shred {
+        if !cfg!(unix) {
+            anyhow::bail!("--shred is only supported on Unix");
+        }
+        delete::enable_shred();
+    }
+    if args.allow_git_repos {
+        delete::allow_git_repos();
+    }
+    let config = config::Config::load(args.config.as_deref())?;
+    if let Some(command) = &config.hooks.after_delete {
+        delete::set_after_delete_hook(command.clone());
+    }
+    if config.ui.si_units {
+        units::use_si_units();
+    }
+    if !config.keys.is_empty() {
+        keymap::use_keymap(keymap::Keymap::new(&config.keys)?);
+    }
+    if let Some(dir) = &config.archive.dir {
+        archive::enable(
+            protect::expand_home(dir),
+            config.archive.risk.clone(),
+            config.confirm.clone(),
+        );
+    }
 
     let start_dir = args.dir.unwrap_or_else(|| std::env::current_dir().unwrap());
-    let max_depth = args.max_depth;
+    // Metadata is keyed by absolute path, so found paths must not depend on the working directory
+    let start_dir = std::path::absolute(&start_dir)
+        .with_context(|| format!("Invalid directory {}", start_dir.display()))?;
+    safety::enable(&start_dir, config.safety.min_depth);
+    if let Some(path) = history::History::default_path() {
+        history::enable(history::History::new(path), &start_dir);
+    }
+    let root_profile = ownership::is_root();
+    let mut scan_options = scanner::ScanOptions {
+        max_depth: (!args.unlimited && args.max_depth != 0).then_some(args.max_depth),
+        strict: root_profile,
+        user_caches: args.user_caches,
+        home: app_dirs::home_dir(),
+        max_scan_time: args.max_scan_time,
+        one_file_system: args.one_file_system || config.scan.one_file_system,
+        follow_symlinks: args.follow_symlinks
+            || (config.scan.follow_symlinks && !args.no_follow_symlinks),
+        finder_metadata: args.finder_metadata,
+        exclude: build_exclude_set(&args.exclude)?,
+        estimators: if args.exact_sizes {
+            Vec::new()
+        } else {
+            size_estimator::builtin()
+        },
+        sizes: true,
+        protected: protect::Protected::new(&config.scan.protect),
+        cache: if args.no_cache {
+            None
+        } else {
+            size_cache::SizeCache::default_path().map(size_cache::SizeCache::load)
+        },
+    };
+    #[cfg(not(unix))]
+    if scan_options.one_file_system {
+        anyhow::bail!("--one-file-system is not supported on this platform");
+    }
+    let owner_filter = match &args.owner {
+        Some(user) => {
+            Some(ownership::resolve_user(user).with_context(|| format!("Unknown user: {}", user))?)
+        }
+        None => None,
+    };
 
-    if args.scan_only {
-        // If scan_only is true, just run the scanner and exit
-        let scanned_ents = Arc::new(AtomicU64::new(0));
-        let found_dirs = Arc::new(Mutex::new(Vec::new()));
-        scanner::scan_directories(
-            &start_dir,
-            max_depth,
-            found_dirs.clone(),
-            scanned_ents,
-            Some(Box::new(|progress| {
-                eprintln!("Scanned: {}, Found: {}", progress.scanned, progress.found);
-            })),
-        )?;
-        for dir in found_dirs.lock().unwrap().iter() {
-            println!(
-                "Found directory: {} (size: {} bytes)",
-                dir.path.display(),
-                dir.size
-            );
+    let audit_log_path = match args.audit_log {
+        Some(path) => Some(path),
+        None if root_profile => Some(
+            audit::AuditLog::default_path()
+                .context("Cannot determine the audit log location; pass --audit-log")?,
+        ),
+        None => None,
+    };
+    let audit_log = audit_log_path.map(audit::AuditLog::new);
+    let size_mode = if args.disk_usage {
+        size_mode::SizeMode::DiskUsage
+    } else if args.unique_size {
+        size_mode::SizeMode::Unique
+    } else {
+        size_mode::SizeMode::Apparent
+    };
+    let filter = HeadlessFilter {
+        owner: owner_filter,
+        min_size: args.min_size.or(config.scan.min_size),
+        older_than_days: args.older_than.or(config.scan.older_than),
+        min_files: args.min_files.or(config.scan.min_files),
+        path_regex: args.filter_regex.clone(),
+        size_mode,
+    };
+
+    let metadata_path = metadata::Metadata::default_path();
+    let metadata = match metadata_path {
+        Some(ref path) => metadata::Metadata::load(path)?,
+        None => metadata::Metadata::default(),
+    };
+    for path in &metadata.exclude {
+        scan_options.protected.add_path(path);
+    }
+
+    if let Some(Command::Metadata { action }) = args.command {
+        return run_metadata_command(action, &start_dir, metadata, metadata_path);
+    }
+
+    if let Some(Command::Diff { old, new, .. }) = args.command {
+        let old = diff::load(&old)?;
+        let new = match new {
+            Some(new) => diff::load(&new)?,
+            None => diff::from_scan(&scan_headless(
+                &start_dir,
+                &scan_options,
+                &filter,
+                &metadata,
+            )?),
+        };
+        return diff::print(&old, &new, size_mode);
+    }
+
+    if let Some(Command::Stats) = args.command {
+        for line in history::stats()? {
+            println!("{}", line);
         }
         return Ok(());
     }
-    run_with_tui(start_dir, max_depth)
-}
 
-fn run_with_tui(start_dir: PathBuf, max_depth: usize) -> Result<()> {
-    setup_terminal()?;
+    if let Some(Command::Rules { action }) = args.command {
+        match action {
+            RulesAction::Test { against } => {
+                let against = std::path::absolute(&against)
+                    .with_context(|| format!("Invalid directory {}", against.display()))?;
+                scan_options.sizes = false;
+                let found = scan_headless(&against, &scan_options, &filter, &metadata)?;
+                return rules::print_matches(&found);
+            }
+            RulesAction::Check => {
+                return rules::check(&config.rules.expect, &scan_options.protected);
+            }
+        }
+    }
 
-    let backend = CrosstermBackend::new(std::io::stdout());
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
+    if let Some(Command::Clean { yes }) = args.command {
+        let found_dirs = scan_headless(&start_dir, &scan_options, &filter, &metadata)?;
+        return clean::run(
+            &found_dirs,
+            &clean::CleanOptions {
+                yes,
+                allowed_roots: &config.automation.allowed_roots,
+                always_confirm: &config.confirm.always,
+                audit_log: audit_log.as_ref(),
+                size_mode,
+                metadata: &metadata,
+            },
+        );
+    }
+
+    if args.all_users {
+        if !root_profile {
+            anyhow::bail!("--all-users needs root, to read everyone's home directory");
+        }
+        let mut reports = Vec::new();
+        for (uid, home) in all_users::homes() {
+            eprintln!("Scanning {}", home.display());
+            scan_options.home = Some(home.clone());
+            let found = scan_headless(&home, &scan_options, &filter, &metadata)?;
+            reports.push(all_users::UserReport { uid, home, found });
+        }
+        return all_users::print(reports, args.format, size_mode);
+    }
+
+    let piped = !args.scan_only && !std::io::stdout().is_terminal();
+    if piped {
+        // Raw mode escape sequences are no use to whatever reads the pipe
+        eprintln!(
+            "Output is not a terminal; printing the scan results instead of starting the TUI"
+        );
+    }
+    if args.scan_only || piped {
+        // If scan_only is true, just run the scanner and exit
+        let found_dirs = scan_headless(&start_dir, &scan_options, &filter, &metadata)?;
+        return args.format.print(&found_dirs, size_mode);
+    }
 
-    let scanned_ents = Arc::new(AtomicU64::new(0));
-    let scanned_ents_clone = Arc::clone(&scanned_ents);
+    let low_bandwidth = args.low_bandwidth || config.ui.low_bandwidth;
+    let reduced_motion = args.reduced_motion || config.ui.reduced_motion || low_bandwidth;
+    let poll_interval = args
+        .poll_interval
+        .or(config.ui.poll_interval_ms.map(Duration::from_millis))
+        .unwrap_or(if args.reduced_motion || config.ui.reduced_motion {
+            Duration::from_secs(1)
+        } else if low_bandwidth {
+            Duration::from_millis(500)
+        } else {
+            Duration::from_millis(100)
+        });
 
-    let found_dirs = Arc::new(Mutex::new(Vec::new()));
-    let found_dirs_clone = Arc::clone(&found_dirs);
+    if let Some(ref audit_log) = audit_log
+        && std::io::stdin().is_terminal()
+        && !args.dry_run
+    {
+        offer_to_finish_deletions(audit_log, root_profile)?;
+    }
 
-    let scan_complete = Arc::new(AtomicBool::new(false));
-    let scan_complete_clone = Arc::clone(&scan_complete);
+    let scope = describe_scope(
+        &start_dir,
+        &scan_options,
+        &args.exclude,
+        &metadata,
+        &config,
+        &filter,
+    );
+    let ui_options = ui::UiOptions {
+        owner_filter,
+        root_profile,
+        audit_log,
+        collation: args.collation,
+        confirm_policy: config.confirm,
+        rebuild_hints: rebuild::RebuildHints::new(config.rebuild),
+        reduced_motion,
+        low_bandwidth,
+        poll_interval,
+        preview: config.ui.preview,
+        bytes_written: Arc::new(AtomicU64::new(0)),
+        scan_metrics: Arc::new(metrics::ScanMetrics::default()),
+        size_mode,
+        size_filter: filter
+            .min_size
+            .map_or(size_filter::SizeFilter::DEFAULT, size_filter::SizeFilter),
+        age_filter: filter.older_than_days.map_or(
+            age_filter::AgeFilter::None,
+            age_filter::AgeFilter::OlderThan,
+        ),
+        min_files: filter.min_files,
+        path_regex: filter.path_regex.clone(),
+        metadata,
+        metadata_path,
+        scope,
+        staging: if args.dry_run {
+            None
+        } else {
+            staging::Staging::default_root().map(|root| staging::Staging::new(&root))
+        },
+    };
+    run_with_tui(start_dir, scan_options, ui_options, args.watch)
+}
 
-    std::thread::spawn(move || {
-        let result = scanner::scan_directories(
-            &start_dir,
-            max_depth,
-            found_dirs_clone,
-            scanned_ents_clone,
-            None,
+/// The settings this run goes by, for the scope panel the TUI starts with
+fn describe_scope(
+    start_dir: &Path,
+    scan_options: &scanner::ScanOptions,
+    excludes: &[String],
+    metadata: &metadata::Metadata,
+    config: &config::Config,
+    filter: &HeadlessFilter,
+) -> scope::Scope {
+    let on_off = |on: bool| if on { "yes" } else { "no" };
+    let mut scope = scope::Scope::default();
+    scope.add("Root", start_dir.display().to_string());
+    scope.add(
+        "Depth",
+        scan_options
+            .max_depth
+            .map_or("unlimited".to_string(), |depth| format!("{} levels", depth)),
+    );
+    if let Some(limit) = scan_options.max_scan_time {
+        scope.add("Time limit", format!("{}s", limit.as_secs()));
+    }
+    let mut detectors = vec!["built-in rules".to_string()];
+    if scan_options.user_caches {
+        detectors.push("user caches".to_string());
+    }
+    if scan_options.finder_metadata {
+        detectors.push("Finder metadata".to_string());
+    }
+    scope.add_list("Detectors", &detectors);
+    let estimators: Vec<String> = scan_options
+        .estimators
+        .iter()
+        .map(|estimator| estimator.name().to_string())
+        .collect();
+    scope.add_list("Size estimators", &estimators);
+    scope.add("One filesystem", on_off(scan_options.one_file_system));
+    scope.add("Follow symlinks", on_off(scan_options.follow_symlinks));
+    if scan_options.strict {
+        scope.add("Root profile", "system directories are skipped");
+    }
+    scope.add_list("Excludes", excludes);
+    if !metadata.exclude.is_empty() {
+        let learned: Vec<String> = metadata
+            .exclude
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        scope.add_list("Excluded as slow", &learned);
+    }
+    scope.add_list("Protected", &config.scan.protect);
+    let mut filters = Vec::new();
+    if let Some(uid) = filter.owner {
+        filters.push(format!("owner {}", ownership::user_name(uid)));
+    }
+    if let Some(min_size) = filter.min_size {
+        filters.push(format!(
+            "at least {}",
+            size_filter::format_threshold(min_size)
+        ));
+    }
+    if let Some(days) = filter.older_than_days {
+        filters.push(format!("older than {} days", days));
+    }
+    if let Some(files) = filter.min_files {
+        filters.push(format!("at least {} files", files));
+    }
+    if let Some(ref regex) = filter.path_regex {
+        filters.push(format!("paths matching /{}/", regex));
+    }
+    if filter.size_mode != size_mode::SizeMode::Apparent {
+        filters.push(filter.size_mode.as_str().to_string());
+    }
+    scope.add_list("Filters", &filters);
+    scope.add(
+        "Config",
+        config
+            .source
+            .as_ref()
+            .map_or("none (defaults)".to_string(), |path| {
+                path.display().to_string()
+            }),
+    );
+    if delete::is_dry_run() {
+        scope.add("Dry run", "nothing will be deleted");
+    }
+    if delete::is_shredding() {
+        scope.add(
+            "Shred",
+            "file contents are overwritten before deleting (slow; best-effort on SSDs)",
         );
-        if let Err(e) = result {
-            eprintln!("Error scanning directories: {}", e);
+    }
+    scope
+}
+
+fn build_exclude_set(patterns: &[String]) -> Result<globset::GlobSet> {
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = globset::Glob::new(pattern)
+            .with_context(|| format!("Invalid --exclude pattern '{}'", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Offers to finish deletions that were cut short (say, by a crash or a reboot halfway through
+/// a multi-million file tree), which the audit log knows about, before starting over.
+fn offer_to_finish_deletions(audit_log: &audit::AuditLog, root_profile: bool) -> Result<()> {
+    for deletion in audit_log.interrupted_deletions()? {
+        eprintln!(
+            "Deleting {} ({}, started {}) did not finish.",
+            deletion.path.display(),
+            deletion.reason,
+            deletion.started
+        );
+        let name = deletion
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if root_profile {
+            eprint!(
+                "Type '{}' to finish deleting it, 'n' to forget about it, or Enter to ask again next time: ",
+                name
+            );
+        } else {
+            eprint!(
+                "Finish deleting it? [y]es, [n]o and forget about it, or Enter to ask again next time: "
+            );
+        }
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        let confirmed = if root_profile {
+            !name.is_empty() && answer == name
+        } else {
+            answer.eq_ignore_ascii_case("y")
+        };
+        if confirmed {
+            match delete::finish_interrupted(&deletion, audit_log) {
+                Ok(()) => eprintln!("Deleted {}", deletion.path.display()),
+                Err(e) => eprintln!("{:#}", e),
+            }
+        } else if answer.eq_ignore_ascii_case("n") {
+            audit_log.record_interrupted("abandon", &deletion)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_metadata_command(
+    action: MetadataAction,
+    root: &Path,
+    mut metadata: metadata::Metadata,
+    metadata_path: Option<PathBuf>,
+) -> Result<()> {
+    match action {
+        MetadataAction::Export { file } => {
+            let exported = metadata.export(root);
+            exported.save(&file)?;
+            println!(
+                "Exported {} ignored, {} kept, {} notes and {} ignore patterns under {} to {}",
+                exported.ignore.len(),
+                exported.keep.len(),
+                exported.notes.len(),
+                exported.ignore_patterns.len(),
+                root.display(),
+                file.display()
+            );
+        }
+        MetadataAction::Import { file } => {
+            if !file.exists() {
+                anyhow::bail!("{} does not exist", file.display());
+            }
+            let summary = metadata.import(metadata::Metadata::load(&file)?, root);
+            let metadata_path =
+                metadata_path.context("Cannot determine where to store the metadata")?;
+            metadata.save(&metadata_path)?;
+            println!(
+                "Imported {} ignored, {} kept, {} notes, {} ignore patterns and {} excludes under {}",
+                summary.ignored,
+                summary.kept,
+                summary.notes,
+                summary.patterns,
+                summary.excluded,
+                root.display()
+            );
+            for path in summary.conflicting_notes {
+                eprintln!("Kept the local note for {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What headless runs leave out of their results: `--owner`, `--min-size`, `--older-than`,
+/// `--min-files` and `--filter-regex`
+struct HeadlessFilter {
+    owner: Option<u32>,
+    min_size: Option<u64>,
+    older_than_days: Option<u64>,
+    min_files: Option<u64>,
+    path_regex: Option<regex::Regex>,
+    size_mode: size_mode::SizeMode,
+}
+
+impl HeadlessFilter {
+    /// Unsized entries (as in `rules test`) have neither a size nor an age to go by, so they stay
+    fn admits(&self, dir: &scanner::CruftDirectory) -> bool {
+        if self.owner.is_some() && dir.owner != self.owner {
+            return false;
+        }
+        if let Some(ref regex) = self.path_regex
+            && !regex.is_match(&dir.path.to_string_lossy())
+        {
+            return false;
+        }
+        if dir.pending {
+            return true;
+        }
+        if self
+            .min_size
+            .is_some_and(|min_size| self.size_mode.size_of(dir) < min_size)
+        {
+            return false;
+        }
+        // Estimated sizes come without a count, which is no reason to hide them
+        if let (Some(min_files), Some(files)) = (self.min_files, dir.file_count)
+            && files < min_files
+        {
+            return false;
+        }
+        !self
+            .older_than_days
+            .is_some_and(|days| dir.newest_file_age_days.unwrap_or(0.0) < days as f64)
+    }
+}
+
+/// Runs a scan to completion without the TUI, reporting progress on stderr.
+/// Ignored directories are left out.
+fn scan_headless(
+    start_dir: &Path,
+    scan_options: &scanner::ScanOptions,
+    filter: &HeadlessFilter,
+    metadata: &metadata::Metadata,
+) -> Result<Vec<scanner::CruftDirectory>> {
+    let (events, received) = mpsc::channel();
+    let mut results = scanner::ScanResults::default();
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            scanner::scan_directories(
+                start_dir,
+                scan_options,
+                &events,
+                &metrics::ScanMetrics::default(),
+                &AtomicBool::new(false),
+            )
+        });
+        for event in received {
+            match event {
+                scanner::ScanEvent::Found(_) => {
+                    results.apply(event);
+                    eprintln!(
+                        "Scanned: {}, Found: {}",
+                        results.scanned,
+                        results.dirs.len()
+                    );
+                }
+                scanner::ScanEvent::Error(ref message) => {
+                    eprintln!("Cannot read {}", message);
+                    results.apply(event);
+                }
+                event => results.apply(event),
+            }
         }
-        scan_complete_clone.store(true, Ordering::Relaxed);
     });
+    if results.summary.is_some_and(|summary| summary.incomplete) {
+        eprintln!("Scan time limit reached; results are incomplete");
+    }
+    for subtree in &results.slow_subtrees {
+        eprintln!(
+            "Reading {} took {:.1}s ({} directories) and found nothing; excluding it would speed up scans",
+            subtree.path.display(),
+            subtree.elapsed.as_secs_f64(),
+            subtree.directories
+        );
+    }
+    let mut found_dirs = results.dirs;
+    found_dirs.retain(|dir| filter.admits(dir) && !metadata.is_ignored(dir));
+    // `rules test` only looks, without sizing anything
+    if scan_options.sizes {
+        history::record_scan(start_dir, &found_dirs);
+    }
+    Ok(found_dirs)
+}
+
+fn run_with_tui(
+    start_dir: PathBuf,
+    scan_options: scanner::ScanOptions,
+    ui_options: ui::UiOptions,
+    watch: bool,
+) -> Result<()> {
+    let scan_options = Arc::new(scan_options);
+    let watch = if watch {
+        Some(watch::Watch::start(
+            start_dir.clone(),
+            Arc::clone(&scan_options),
+            Arc::clone(&ui_options.scan_metrics),
+        )?)
+    } else {
+        None
+    };
+    setup_terminal()?;
 
-    ui::run_ui(&mut terminal, &found_dirs, &scan_complete, &scanned_ents)?;
+    let backend = CrosstermBackend::new(frame_stats::CountingWriter::new(
+        std::io::stdout(),
+        Arc::clone(&ui_options.bytes_written),
+    ));
+    let mut terminal = Terminal::new(backend)?;
+    terminal.clear()?;
 
+    let mut scan = scanner::BackgroundScan::start(
+        start_dir,
+        scan_options,
+        Arc::clone(&ui_options.scan_metrics),
+    );
+    let audit_log = ui_options.audit_log.clone();
+    let result = ui::run_ui(&mut terminal, &mut scan, watch.as_ref(), ui_options);
+    // Don't leave the scan grinding away at the disk after quitting
+    scan.cancel();
     restore_terminal()?;
+    if let Some(staging) = result?
+        && !staging.is_empty()
+    {
+        eprintln!(
+            "Deleting {} directories ({}) for good...",
+            staging.len(),
+            units::format_size(staging.size())
+        );
+        staging.purge(audit_log.as_ref())?;
+    }
+    scan.join();
 
     Ok(())
 }
 
 fn setup_terminal() -> Result<()> {
+    // A panic would otherwise leave the terminal in raw mode, with its message mangled
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
     enable_raw_mode().context("Failed to enable raw mode")?;
     std::io::stdout()
         .execute(EnterAlternateScreen)
@@ -0,0 +1,274 @@
+use crate::scanner::{CruftDirectory, CruftyReason};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// The user's curation of scan results: directories to leave out of the list entirely
+/// (one by one, or by pattern), directories marked as worth keeping (never deleted),
+/// free-form notes, and subtrees not to scan at all.
+///
+/// Stored in `metadata.toml` in decruft's data directory, keyed by absolute path.
+/// Exported files use the same format, with paths relative to the export root.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Metadata {
+    pub ignore: BTreeSet<PathBuf>,
+    pub keep: BTreeSet<PathBuf>,
+    pub notes: BTreeMap<PathBuf, String>,
+    pub ignore_patterns: Vec<IgnorePattern>,
+    /// Left out of scans, as accepted from the suggestions for slow subtrees without cruft
+    pub exclude: BTreeSet<PathBuf>,
+}
+
+/// Ignores every entry like one picked in the TUI: everything with a given name,
+/// or everything found for a given reason under some directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnorePattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<CruftyReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub under: Option<PathBuf>,
+}
+
+impl IgnorePattern {
+    /// The patterns `dir` could be generalized into, under the scan root `root`
+    pub fn suggestions(dir: &CruftDirectory, root: &Path) -> Vec<IgnorePattern> {
+        let mut suggestions = Vec::new();
+        if let Some(name) = dir.path.file_name() {
+            suggestions.push(IgnorePattern {
+                name: Some(name.to_string_lossy().into_owned()),
+                reason: None,
+                under: None,
+            });
+        }
+        suggestions.push(IgnorePattern {
+            name: None,
+            reason: Some(dir.crufty_reason.clone()),
+            under: Some(root.to_path_buf()),
+        });
+        suggestions
+    }
+
+    pub fn matches(&self, dir: &CruftDirectory) -> bool {
+        self.name.as_ref().is_none_or(|name| {
+            dir.path
+                .file_name()
+                .is_some_and(|file_name| file_name == name.as_str())
+        }) && self
+            .reason
+            .as_ref()
+            .is_none_or(|reason| dir.crufty_reason == *reason)
+            && self
+                .under
+                .as_ref()
+                .is_none_or(|under| dir.path.starts_with(under))
+    }
+
+    pub fn describe(&self) -> String {
+        let what = match (&self.name, &self.reason) {
+            (Some(name), Some(reason)) => format!("every {} named '{}'", reason, name),
+            (Some(name), None) => format!("everything named '{}'", name),
+            (None, Some(reason)) => format!("every {}", reason),
+            (None, None) => "everything".to_string(),
+        };
+        match self.under {
+            Some(ref under) => format!("{} under {}", what, under.display()),
+            None => format!("{} anywhere", what),
+        }
+    }
+}
+
+/// What an import changed
+pub struct ImportSummary {
+    pub ignored: usize,
+    pub kept: usize,
+    pub notes: usize,
+    pub patterns: usize,
+    pub excluded: usize,
+    /// Notes that differed from an existing local note, which was left alone
+    pub conflicting_notes: Vec<PathBuf>,
+}
+
+impl Metadata {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::data_dir().map(|dir| dir.join("metadata.toml"))
+    }
+
+    /// Loads metadata from `path`; a file that doesn't exist yet means no metadata.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid metadata file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let text = toml::to_string(self)?;
+        std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether `dir` is ignored, by itself or by a pattern
+    pub fn is_ignored(&self, dir: &CruftDirectory) -> bool {
+        self.ignore.contains(&dir.path) || self.ignore_patterns.iter().any(|p| p.matches(dir))
+    }
+
+    pub fn is_kept(&self, path: &Path) -> bool {
+        self.keep.contains(path)
+    }
+
+    pub fn note(&self, path: &Path) -> Option<&str> {
+        self.notes.get(path).map(String::as_str)
+    }
+
+    /// Ignores `dir`, or stops ignoring it; for an entry ignored by patterns, that means
+    /// removing the patterns, which are returned
+    pub fn toggle_ignore(&mut self, dir: &CruftDirectory) -> Vec<IgnorePattern> {
+        let (removed, kept) = std::mem::take(&mut self.ignore_patterns)
+            .into_iter()
+            .partition(|pattern| pattern.matches(dir));
+        self.ignore_patterns = kept;
+        if removed.is_empty() && !self.ignore.remove(&dir.path) {
+            self.ignore.insert(dir.path.clone());
+        }
+        removed
+    }
+
+    pub fn add_ignore_pattern(&mut self, pattern: IgnorePattern) {
+        if !self.ignore_patterns.contains(&pattern) {
+            self.ignore_patterns.push(pattern);
+        }
+    }
+
+    pub fn toggle_keep(&mut self, path: &Path) {
+        if !self.keep.remove(path) {
+            self.keep.insert(path.to_path_buf());
+        }
+    }
+
+    /// Sets the note for `path`; an empty note removes it.
+    pub fn set_note(&mut self, path: &Path, note: &str) {
+        let note = note.trim();
+        if note.is_empty() {
+            self.notes.remove(path);
+        } else {
+            self.notes.insert(path.to_path_buf(), note.to_string());
+        }
+    }
+
+    /// The metadata for everything under `root`, with paths made relative to it,
+    /// so that it can be imported on a machine where the same tree lives elsewhere.
+    pub fn export(&self, root: &Path) -> Metadata {
+        let relative = |path: &PathBuf| {
+            let relative = path.strip_prefix(root).ok()?;
+            (!relative.as_os_str().is_empty()).then(|| relative.to_path_buf())
+        };
+        Metadata {
+            ignore: self.ignore.iter().filter_map(relative).collect(),
+            keep: self.keep.iter().filter_map(relative).collect(),
+            exclude: self.exclude.iter().filter_map(relative).collect(),
+            notes: self
+                .notes
+                .iter()
+                .filter_map(|(path, note)| Some((relative(path)?, note.clone())))
+                .collect(),
+            // Patterns for anywhere go along as they are; an empty `under` is the root itself
+            ignore_patterns: self
+                .ignore_patterns
+                .iter()
+                .filter_map(|pattern| {
+                    let under = match pattern.under {
+                        Some(ref under) => Some(under.strip_prefix(root).ok()?.to_path_buf()),
+                        None => None,
+                    };
+                    Some(IgnorePattern {
+                        under,
+                        ..pattern.clone()
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Merges exported metadata, resolving its paths against `root`.
+    /// Local notes win over imported ones that say something different.
+    pub fn import(&mut self, exported: Metadata, root: &Path) -> ImportSummary {
+        let mut summary = ImportSummary {
+            ignored: 0,
+            kept: 0,
+            notes: 0,
+            patterns: 0,
+            excluded: 0,
+            conflicting_notes: Vec::new(),
+        };
+        for path in exported
+            .ignore
+            .iter()
+            .filter_map(|path| resolve(root, path))
+        {
+            if self.ignore.insert(path) {
+                summary.ignored += 1;
+            }
+        }
+        for path in exported.keep.iter().filter_map(|path| resolve(root, path)) {
+            if self.keep.insert(path) {
+                summary.kept += 1;
+            }
+        }
+        for path in exported
+            .exclude
+            .iter()
+            .filter_map(|path| resolve(root, path))
+        {
+            if self.exclude.insert(path) {
+                summary.excluded += 1;
+            }
+        }
+        for mut pattern in exported.ignore_patterns {
+            if let Some(ref under) = pattern.under {
+                let Some(under) = resolve(root, under) else {
+                    continue;
+                };
+                pattern.under = Some(under);
+            }
+            if !self.ignore_patterns.contains(&pattern) {
+                self.ignore_patterns.push(pattern);
+                summary.patterns += 1;
+            }
+        }
+        for (path, note) in exported.notes {
+            let Some(path) = resolve(root, &path) else {
+                continue;
+            };
+            match self.notes.get(&path) {
+                None => {
+                    self.notes.insert(path, note);
+                    summary.notes += 1;
+                }
+                Some(existing) if *existing != note => summary.conflicting_notes.push(path),
+                Some(_) => {}
+            }
+        }
+        summary
+    }
+}
+
+/// Resolves an exported path against `root`, refusing anything that would point outside it.
+fn resolve(root: &Path, relative: &Path) -> Option<PathBuf> {
+    if relative.as_os_str().is_empty() {
+        return Some(root.to_path_buf());
+    }
+    relative
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+        .then(|| root.join(relative))
+}
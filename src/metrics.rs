@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// Counters the scanner keeps up to date for the debug overlay
+#[derive(Default)]
+pub struct ScanMetrics {
+    /// Directories waiting to be read
+    pub dir_queue: AtomicUsize,
+    /// Found directories waiting to be sized
+    pub size_queue: AtomicUsize,
+    /// Times a thread had to wait for the set of already reported directories
+    pub reported_contention: AtomicU64,
+}
+
+/// Locks `mutex`, counting in `contention` if that meant waiting for another thread.
+pub fn lock_counting<'a, T>(mutex: &'a Mutex<T>, contention: &AtomicU64) -> MutexGuard<'a, T> {
+    match mutex.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => {
+            contention.fetch_add(1, Ordering::Relaxed);
+            mutex.lock().unwrap()
+        }
+        Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+    }
+}
+
+/// The resident set size of this process in bytes, where the platform makes that easy to find
+pub fn resident_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+        let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kilobytes * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+/// Why deleting a directory might not free the disk space its size suggests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpaceCaveat {
+    /// On overlayfs (as in a container), at least partly in a read-only lower layer:
+    /// deleting only hides those files behind whiteouts, and the image keeps them
+    ImageLayer,
+    /// On overlayfs, but the layers aren't visible from here (as from inside a container),
+    /// so it's anyone's guess how much of it is in the image
+    Overlay,
+    /// On tmpfs or ramfs: deleting frees memory, not disk space
+    Memory,
+}
+
+impl SpaceCaveat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpaceCaveat::ImageLayer => "image layer",
+            SpaceCaveat::Overlay => "overlay",
+            SpaceCaveat::Memory => "in memory",
+        }
+    }
+}
+
+struct Mount {
+    mount_point: PathBuf,
+    fs_type: String,
+    upper_dir: Option<PathBuf>,
+    lower_dirs: Vec<PathBuf>,
+}
+
+/// The mounts visible to this process, to tell where deleting frees space on the host.
+#[derive(Default)]
+pub struct MountTable(Vec<Mount>);
+
+impl MountTable {
+    /// Reads the mount table; anywhere but Linux, this knows about no mounts.
+    pub fn load() -> Self {
+        #[cfg(target_os = "linux")]
+        if let Ok(text) = std::fs::read_to_string("/proc/self/mountinfo") {
+            return MountTable(text.lines().filter_map(parse_mountinfo_line).collect());
+        }
+        MountTable::default()
+    }
+
+    pub fn caveat(&self, path: &Path) -> Option<SpaceCaveat> {
+        // The innermost mount wins; later lines are mounted on top of earlier ones
+        let mount = self
+            .0
+            .iter()
+            .filter(|mount| path.starts_with(&mount.mount_point))
+            .max_by_key(|mount| mount.mount_point.components().count())?;
+        match mount.fs_type.as_str() {
+            "tmpfs" | "ramfs" => Some(SpaceCaveat::Memory),
+            "overlay" => {
+                let relative = path.strip_prefix(&mount.mount_point).ok()?;
+                if mount.upper_dir.as_ref().is_none_or(|dir| !dir.is_dir()) {
+                    return Some(SpaceCaveat::Overlay);
+                }
+                let in_lower = mount
+                    .lower_dirs
+                    .iter()
+                    .any(|lower| lower.join(relative).exists());
+                in_lower.then_some(SpaceCaveat::ImageLayer)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses a line of `/proc/self/mountinfo`:
+/// `36 35 98:0 /root /mnt rw,noatime master:1 - overlay overlay rw,lowerdir=...,upperdir=...`
+#[cfg(target_os = "linux")]
+fn parse_mountinfo_line(line: &str) -> Option<Mount> {
+    let (mount, filesystem) = line.split_once(" - ")?;
+    let mount_point = mount.split(' ').nth(4)?;
+    let mut filesystem = filesystem.split(' ');
+    let fs_type = filesystem.next()?;
+    let super_options = filesystem.nth(1).unwrap_or("");
+    let mut upper_dir = None;
+    let mut lower_dirs = Vec::new();
+    for option in super_options.split(',') {
+        if let Some(dir) = option.strip_prefix("upperdir=") {
+            upper_dir = Some(PathBuf::from(unescape(dir)));
+        } else if let Some(dirs) = option.strip_prefix("lowerdir=") {
+            // Newer kernels separate data-only layers with "::"
+            lower_dirs.extend(
+                dirs.split(':')
+                    .filter(|dir| !dir.is_empty())
+                    .map(|dir| PathBuf::from(unescape(dir))),
+            );
+        }
+    }
+    Some(Mount {
+        mount_point: PathBuf::from(unescape(mount_point)),
+        fs_type: fs_type.to_string(),
+        upper_dir,
+        lower_dirs,
+    })
+}
+
+/// Undoes the octal escapes (`\040` for a space, ...) the kernel uses in mount paths.
+#[cfg(target_os = "linux")]
+fn unescape(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+        match escape
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok())
+        {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
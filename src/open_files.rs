@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+
+/// What running processes have open, run from or work in, to warn before deleting a
+/// directory that something still uses (a venv behind a running service, say).
+///
+/// Read from `/proc` on Linux, and from `lsof` (if it's installed) elsewhere. Either way,
+/// only processes that this user may look into are seen.
+pub struct OpenFiles {
+    open: Vec<(PathBuf, Process)>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+}
+
+impl OpenFiles {
+    /// What's open right now
+    pub fn snapshot() -> Self {
+        Self { open: imp::open() }
+    }
+
+    /// The processes using anything at or under `path`, each once
+    pub fn users_of(&self, path: &Path) -> Vec<&Process> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut users: Vec<&Process> = Vec::new();
+        for (open, process) in &self.open {
+            // Running from inside what's about to be deleted is fine for decruft itself
+            if process.pid == std::process::id() {
+                continue;
+            }
+            if open.starts_with(&path) && !users.contains(&process) {
+                users.push(process);
+            }
+        }
+        users
+    }
+}
+
+/// `users` for a warning: the first few by name and pid
+pub fn describe(users: &[&Process]) -> String {
+    const SHOWN: usize = 3;
+    let mut names: Vec<String> = users
+        .iter()
+        .take(SHOWN)
+        .map(|process| format!("{} ({})", process.name, process.pid))
+        .collect();
+    if users.len() > SHOWN {
+        names.push(format!("{} more", users.len() - SHOWN));
+    }
+    names.join(", ")
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::Process;
+    use std::path::{Path, PathBuf};
+
+    /// The executable, working directory, open files and mapped files (like a venv's
+    /// shared libraries, which stay mapped after the files that loaded them are closed)
+    /// of each process
+    pub fn open() -> Vec<(PathBuf, Process)> {
+        let mut open = Vec::new();
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return open;
+        };
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+            let proc_dir = entry.path();
+            let Ok(name) = std::fs::read_to_string(proc_dir.join("comm")) else {
+                continue;
+            };
+            let process = Process {
+                pid,
+                name: name.trim_end().to_string(),
+            };
+            let mut paths: Vec<PathBuf> = ["exe", "cwd"]
+                .iter()
+                .filter_map(|link| std::fs::read_link(proc_dir.join(link)).ok())
+                .collect();
+            let fds = std::fs::read_dir(proc_dir.join("fd")).into_iter().flatten();
+            paths.extend(
+                fds.flatten()
+                    .filter_map(|fd| std::fs::read_link(fd.path()).ok()),
+            );
+            if let Ok(maps) = std::fs::read_to_string(proc_dir.join("maps")) {
+                paths.extend(
+                    maps.lines()
+                        .filter_map(|line| line.split_whitespace().nth(5))
+                        .map(Path::new)
+                        .filter(|path| path.is_absolute())
+                        .map(Path::to_path_buf),
+                );
+            }
+            paths.sort();
+            paths.dedup();
+            open.extend(paths.into_iter().map(|path| (path, process.clone())));
+        }
+        open
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::Process;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+
+    /// Everything `lsof` lists, from its field output: a `p<pid>` line starts each
+    /// process, followed by `c<command>` and an `n<name>` line per open file
+    pub fn open() -> Vec<(PathBuf, Process)> {
+        let Ok(output) = Command::new("lsof")
+            .args(["-w", "-F", "pcn"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        else {
+            return Vec::new();
+        };
+        let mut open = Vec::new();
+        let mut process = Process {
+            pid: 0,
+            name: String::new(),
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut chars = line.chars();
+            let (field, value) = (chars.next(), chars.as_str());
+            match field {
+                Some('p') => {
+                    process = Process {
+                        pid: value.parse().unwrap_or(0),
+                        name: String::new(),
+                    }
+                }
+                Some('c') => process.name = value.to_string(),
+                Some('n') if value.starts_with('/') => {
+                    open.push((PathBuf::from(value), process.clone()))
+                }
+                _ => {}
+            }
+        }
+        open
+    }
+}
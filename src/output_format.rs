@@ -0,0 +1,80 @@
+use crate::confidence::Confidence;
+use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+/// How `--scan-only` (and a run whose output isn't a terminal) prints what it found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One line per directory
+    Text,
+    /// A JSON array of objects, for scripts
+    Json,
+}
+
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    path: &'a Path,
+    reason: String,
+    size: u64,
+    disk_usage: u64,
+    unique_disk_usage: u64,
+    newest_file_age_days: Option<f64>,
+    /// Not known if the size was estimated
+    file_count: Option<u64>,
+    owner: Option<u32>,
+    confidence: Confidence,
+    via_symlink: Option<&'a Path>,
+    /// Why deleting it might not free `disk_usage` ("image layer", "overlay", "in memory")
+    space_caveat: Option<&'static str>,
+    /// Where the size came from, if it was estimated rather than added up
+    size_estimated_by: Option<&'static str>,
+}
+
+impl OutputFormat {
+    pub fn print(&self, dirs: &[CruftDirectory], size_mode: SizeMode) -> Result<()> {
+        let mut out = std::io::stdout().lock();
+        match self {
+            OutputFormat::Text => {
+                for dir in dirs {
+                    write!(
+                        out,
+                        "Found directory: {} ({}: {} bytes)",
+                        dir.path.display(),
+                        size_mode.as_str(),
+                        size_mode.size_of(dir)
+                    )?;
+                    match dir.space_caveat {
+                        Some(caveat) => writeln!(out, " [{}]", caveat.label())?,
+                        None => writeln!(out)?,
+                    }
+                }
+            }
+            OutputFormat::Json => {
+                let entries: Vec<_> = dirs
+                    .iter()
+                    .map(|dir| JsonEntry {
+                        path: &dir.path,
+                        reason: dir.crufty_reason.to_string(),
+                        size: dir.size,
+                        disk_usage: dir.disk_usage,
+                        unique_disk_usage: dir.unique_disk_usage,
+                        newest_file_age_days: dir.newest_file_age_days,
+                        file_count: dir.file_count,
+                        owner: dir.owner,
+                        confidence: dir.confidence,
+                        via_symlink: dir.via_symlink.as_deref(),
+                        space_caveat: dir.space_caveat.map(|caveat| caveat.label()),
+                        size_estimated_by: dir.size_estimated_by,
+                    })
+                    .collect();
+                serde_json::to_writer_pretty(&mut out, &entries)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Returns the uid owning the given path, if the platform has such a thing.
+#[cfg(unix)]
+pub fn owner_uid(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path).ok().map(|m| m.uid())
+}
+
+#[cfg(not(unix))]
+pub fn owner_uid(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Whether decruft is running with root privileges.
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    rustix::process::geteuid().is_root()
+}
+
+#[cfg(not(unix))]
+pub fn is_root() -> bool {
+    false
+}
+
+/// Checks whether the current user could remove the given directory:
+/// the parent must be writable and searchable (and, if it's sticky, we must own the entry),
+/// and the directory itself must be writable and searchable so its contents can be removed.
+#[cfg(unix)]
+pub fn can_delete(path: &Path) -> bool {
+    use rustix::fs::{Access, access};
+    use std::os::unix::fs::MetadataExt;
+
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    let wx = Access::WRITE_OK | Access::EXEC_OK;
+    if access(parent, wx).is_err() || access(path, wx).is_err() {
+        return false;
+    }
+    let euid = rustix::process::geteuid();
+    if euid.is_root() {
+        return true;
+    }
+    // Sticky directories (like /tmp) only let owners remove their entries
+    match (std::fs::metadata(parent), std::fs::symlink_metadata(path)) {
+        (Ok(parent_meta), Ok(meta)) => {
+            parent_meta.mode() & 0o1000 == 0 || meta.uid() == euid.as_raw()
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether the current user could remove entries from the given directory
+/// (ignoring the sticky bit).
+#[cfg(unix)]
+pub fn can_remove_from(dir: &Path) -> bool {
+    use rustix::fs::{Access, access};
+    access(dir, Access::WRITE_OK | Access::EXEC_OK).is_ok()
+}
+
+#[cfg(not(unix))]
+pub fn can_remove_from(dir: &Path) -> bool {
+    can_delete(dir)
+}
+
+#[cfg(not(unix))]
+pub fn can_delete(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .map(|m| !m.permissions().readonly())
+        .unwrap_or(false)
+}
+
+/// uid -> user name table, read from /etc/passwd once.
+fn user_table() -> &'static HashMap<u32, String> {
+    static TABLE: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let Ok(passwd) = std::fs::read_to_string("/etc/passwd") else {
+            return HashMap::new();
+        };
+        passwd
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?;
+                let uid = fields.nth(1)?.parse().ok()?;
+                Some((uid, name.to_string()))
+            })
+            .collect()
+    })
+}
+
+/// Formats a uid as a user name, falling back to the numeric uid.
+pub fn user_name(uid: u32) -> String {
+    user_table()
+        .get(&uid)
+        .cloned()
+        .unwrap_or_else(|| uid.to_string())
+}
+
+/// Resolves a user given either as a name or as a numeric uid.
+pub fn resolve_user(user: &str) -> Option<u32> {
+    if let Ok(uid) = user.parse() {
+        return Some(uid);
+    }
+    user_table()
+        .iter()
+        .find(|(_, name)| *name == user)
+        .map(|(uid, _)| *uid)
+}
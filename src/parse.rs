@@ -0,0 +1,110 @@
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// Parses a duration like `90s`, `5m`, `2h` or `1d`; a bare number is in seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit) =
+        split_number(s).ok_or_else(|| format!("invalid duration '{}' (try 90s, 5m or 2h)", s))?;
+    let seconds_per_unit = match unit.as_str() {
+        "" | "s" | "sec" | "secs" => 1.0,
+        "ms" => 0.001,
+        "m" | "min" | "mins" => 60.0,
+        "h" | "hr" | "hrs" => 3600.0,
+        "d" | "day" | "days" => 86400.0,
+        unit => return Err(format!("unknown duration unit '{}' in '{}'", unit, s)),
+    };
+    Duration::try_from_secs_f64(number * seconds_per_unit)
+        .map_err(|_| format!("duration '{}' is out of range", s))
+}
+
+/// Parses a size like `500M`, `1.5G` or `2GiB` into bytes; a bare number is in bytes.
+/// Units are powers of 1024 either way, as sizes are shown.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, unit) =
+        split_number(s).ok_or_else(|| format!("invalid size '{}' (try 500M or 1.5G)", s))?;
+    let exponent = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => {
+            return Err(format!(
+                "unknown size unit '{}' in '{}' (use K, M, G or T)",
+                unit, s
+            ));
+        }
+    };
+    let bytes = number * 1024f64.powi(exponent);
+    if bytes >= u64::MAX as f64 {
+        return Err(format!("size '{}' is out of range", s));
+    }
+    Ok(bytes.round() as u64)
+}
+
+/// Parses an age like `90d`, `3w`, `6mo` or `2y` into days; a bare number is in days.
+/// A month is 30 days, a year 365.
+pub fn parse_age(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, unit) =
+        split_number(s).ok_or_else(|| format!("invalid age '{}' (try 90d, 6mo or 2y)", s))?;
+    let days_per_unit = match unit.as_str() {
+        "" | "d" | "day" | "days" => 1.0,
+        "w" | "wk" | "week" | "weeks" => 7.0,
+        "mo" | "month" | "months" => 30.0,
+        "y" | "yr" | "year" | "years" => 365.0,
+        "m" => {
+            return Err(format!(
+                "ambiguous age unit 'm' in '{}' (use mo for months)",
+                s
+            ));
+        }
+        unit => {
+            return Err(format!(
+                "unknown age unit '{}' in '{}' (use d, w, mo or y)",
+                unit, s
+            ));
+        }
+    };
+    let days = number * days_per_unit;
+    if days >= u64::MAX as f64 {
+        return Err(format!("age '{}' is out of range", s));
+    }
+    Ok(days.round() as u64)
+}
+
+/// Splits `1.5G` into the number and its unit (lowercased, without surrounding spaces)
+fn split_number(s: &str) -> Option<(f64, String)> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    Some((number.parse().ok()?, unit.trim().to_lowercase()))
+}
+
+/// For config values written like the `--min-size` flag (`min_size = "1.5G"`)
+pub fn deserialize_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_size(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Like `deserialize_size`, for values that can't be left out (`medium_size = "100M"`)
+pub fn deserialize_required_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    parse_size(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// For config values written like the `--older-than` flag (`older_than = "6mo"`)
+pub fn deserialize_age<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_age(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
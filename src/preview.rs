@@ -0,0 +1,95 @@
+use crate::io_scheduler;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// What the preview pane shows beyond what the scan already knows, which takes a walk
+pub struct Facts {
+    /// The most and least recently modified files, relative to the entry
+    pub newest: Option<(PathBuf, SystemTime)>,
+    pub oldest: Option<(PathBuf, SystemTime)>,
+}
+
+/// The walks behind the preview pane (`v`), one at a time in the background so that moving
+/// through the list stays quick, and kept for the session so that coming back is instant.
+/// While one walk runs, the selection may move on; only the last entry asked for is walked
+/// next.
+#[derive(Default)]
+pub struct Previews {
+    facts: HashMap<PathBuf, Facts>,
+    running: Option<Receiver<(PathBuf, Facts)>>,
+    wanted: Option<PathBuf>,
+}
+
+impl Previews {
+    /// Looks for the facts about `path`, unless they're known already
+    pub fn want(&mut self, path: &Path) {
+        self.poll();
+        if !self.facts.contains_key(path) {
+            self.wanted = Some(path.to_path_buf());
+            self.start_wanted();
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Facts> {
+        self.facts.get(path)
+    }
+
+    fn poll(&mut self) {
+        if let Some(ref receiver) = self.running
+            && let Ok((path, facts)) = receiver.try_recv()
+        {
+            self.facts.insert(path, facts);
+            self.running = None;
+        }
+    }
+
+    fn start_wanted(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        let Some(path) = self.wanted.take() else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let facts = io_scheduler::run(&path, || oldest_and_newest(&path));
+            let _ = sender.send((path, facts));
+        });
+        self.running = Some(receiver);
+    }
+}
+
+fn oldest_and_newest(path: &Path) -> Facts {
+    let mut facts = Facts {
+        newest: None,
+        oldest: None,
+    };
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok()) else {
+            continue;
+        };
+        let relative = || {
+            entry
+                .path()
+                .strip_prefix(path)
+                .unwrap_or(entry.path())
+                .to_path_buf()
+        };
+        if facts.newest.as_ref().is_none_or(|(_, t)| modified > *t) {
+            facts.newest = Some((relative(), modified));
+        }
+        if facts.oldest.as_ref().is_none_or(|(_, t)| modified < *t) {
+            facts.oldest = Some((relative(), modified));
+        }
+    }
+    facts
+}
@@ -0,0 +1,54 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Directories that are never cruft, nor is anything in them, on top of the built-in
+/// ones (`.git`, `.idea`, ...): the `[scan] protect` setting. Scans skip them entirely.
+#[derive(Debug, Default)]
+pub struct Protected {
+    /// Protected wherever they are, like the built-in ones
+    names: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+impl Protected {
+    /// Entries with a path separator in them, or a leading `~` for the home directory,
+    /// are paths; others are names.
+    pub fn new(entries: &[String]) -> Self {
+        let mut protected = Protected::default();
+        for entry in entries {
+            if entry.contains(['/', std::path::MAIN_SEPARATOR]) || entry == "~" {
+                let path = expand_home(entry);
+                // Scanned paths may be reached through symlinks, or the other way around
+                protected.paths.extend(path.canonicalize().ok());
+                protected.paths.push(path);
+            } else {
+                protected.names.push(entry.clone());
+            }
+        }
+        protected
+    }
+
+    /// Protects a path on top of the configured ones, as for excludes accepted in the TUI
+    pub fn add_path(&mut self, path: &Path) {
+        self.paths.push(path.to_path_buf());
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths
+            .iter()
+            .any(|protected| path.starts_with(protected))
+            || path.components().any(|component| {
+                matches!(component, Component::Normal(name)
+                    if self.names.iter().any(|protected| name == protected.as_str()))
+            })
+    }
+}
+
+pub fn expand_home(entry: &str) -> PathBuf {
+    let rest = entry
+        .strip_prefix("~/")
+        .or_else(|| (entry == "~").then_some(""));
+    match (rest, crate::app_dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(entry),
+    }
+}
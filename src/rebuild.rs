@@ -0,0 +1,47 @@
+use crate::scanner::CruftyReason;
+use std::collections::HashMap;
+
+/// How to get back what each rule deletes, shown in the inspector and when confirming a
+/// deletion, so that deleting feels less final. `[rebuild]` in the config file overrides
+/// these per rule; an empty command hides the hint.
+pub struct RebuildHints {
+    overrides: HashMap<CruftyReason, String>,
+}
+
+impl RebuildHints {
+    pub fn new(overrides: HashMap<CruftyReason, String>) -> Self {
+        Self { overrides }
+    }
+
+    pub fn for_reason(&self, reason: &CruftyReason) -> Option<&str> {
+        match self.overrides.get(reason) {
+            Some(hint) if hint.is_empty() => None,
+            Some(hint) => Some(hint),
+            None => default_hint(reason),
+        }
+    }
+}
+
+fn default_hint(reason: &CruftyReason) -> Option<&'static str> {
+    Some(match reason {
+        CruftyReason::NodeModules => "npm install (or yarn / pnpm install)",
+        CruftyReason::CacheDir | CruftyReason::UserCache => "nothing; the tool using it refills it",
+        CruftyReason::CacheTagFound => "nothing; the tool that tagged it refills it",
+        CruftyReason::BuildDir | CruftyReason::DistDir => "rerun the project's build",
+        CruftyReason::RustTargetDir => "cargo build",
+        CruftyReason::VenvDir => "uv venv && uv sync (or python -m venv, then pip install)",
+        CruftyReason::CoverageDir => "rerun the tests with coverage",
+        CruftyReason::ToxDir => "tox",
+        CruftyReason::MixBuildDir => "mix compile",
+        CruftyReason::MixDepsDir => "mix deps.get",
+        CruftyReason::ZigCacheDir | CruftyReason::ZigOutDir => "zig build",
+        CruftyReason::SwiftBuildDir => "swift build",
+        CruftyReason::CMakeBuildDir => "cmake -B <dir> && cmake --build <dir>",
+        CruftyReason::BazelOutputBase => "bazel build",
+        CruftyReason::TerraformDir => "terraform init",
+        CruftyReason::CdkOutDir => "cdk synth",
+        CruftyReason::PulumiDir => "pulumi install",
+        // Nothing needs these back
+        CruftyReason::TempDir | CruftyReason::FinderMetadata => return None,
+    })
+}
@@ -0,0 +1,93 @@
+use crate::protect::Protected;
+use crate::scanner::{self, CruftDirectory, CruftyReason};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// What a path should (or shouldn't) be detected as, from `[[rules.expect]]` in the config,
+/// checked by `decruft rules check`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    pub path: PathBuf,
+    /// The rule it should match; without this or `not`, it should match none
+    pub matches: Option<CruftyReason>,
+    /// A rule it must not match
+    pub not: Option<CruftyReason>,
+}
+
+/// Checks each expectation against the rules as they are now, printing the ones that
+/// no longer hold; an error if any don't. Paths needn't exist, but rules that go by
+/// marker files (`CACHEDIR.TAG`, a `package.json` next to it, ...) only match real ones.
+pub fn check(expectations: &[Expectation], protected: &Protected) -> Result<()> {
+    let mut failed = 0;
+    for expectation in expectations {
+        let path = crate::protect::expand_home(&expectation.path.to_string_lossy());
+        let detected = if protected.contains(&path) {
+            None
+        } else {
+            scanner::detect(&path)
+        };
+        let holds = match (&expectation.matches, &expectation.not) {
+            (Some(expected), _) if detected.as_ref() != Some(expected) => false,
+            (_, Some(unwanted)) if detected.as_ref() == Some(unwanted) => false,
+            (None, None) => detected.is_none(),
+            _ => true,
+        };
+        if holds {
+            continue;
+        }
+        failed += 1;
+        let expected = match (&expectation.matches, &expectation.not) {
+            (Some(expected), _) => format!("{:?}", expected),
+            (None, Some(unwanted)) => format!("anything but {:?}", unwanted),
+            (None, None) => "nothing".to_string(),
+        };
+        let got = detected.map_or("nothing".to_string(), |reason| format!("{:?}", reason));
+        println!(
+            "{}: expected {}, matches {}",
+            expectation.path.display(),
+            expected,
+            got
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} rule expectations failed",
+            failed,
+            expectations.len()
+        );
+    }
+    println!("All {} rule expectations hold", expectations.len());
+    Ok(())
+}
+
+/// Prints what each detector matched, for `decruft rules test`: one block per rule, paths
+/// sorted within it, and nothing that varies from run to run (like sizes or timings), so
+/// that the output of two runs can be diffed after changing excludes or ignore files.
+pub fn print_matches(found: &[CruftDirectory]) -> Result<()> {
+    let mut by_rule: BTreeMap<String, Vec<&CruftDirectory>> = BTreeMap::new();
+    for dir in found {
+        by_rule
+            .entry(dir.crufty_reason.to_string())
+            .or_default()
+            .push(dir);
+    }
+    let mut out = std::io::stdout().lock();
+    for (rule, mut dirs) in by_rule {
+        dirs.sort_by(|a, b| a.path.cmp(&b.path));
+        writeln!(out, "{} ({})", rule, dirs.len())?;
+        for dir in dirs {
+            // Symlinked cruft is listed as its target, not by whichever link reached it first
+            writeln!(
+                out,
+                "  {} [{}]",
+                dir.path.display(),
+                dir.confidence.as_str()
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// How many components deep a path must be to be deleted, unless the config says otherwise:
+/// `/app/node_modules` is fine, `/build` is not
+pub const DEFAULT_MIN_DEPTH: usize = 2;
+
+/// What's never deleted, whatever a rule matched: the last line of defence against a rule
+/// (or a bug) that takes something vital for cruft
+static RAILS: OnceLock<Rails> = OnceLock::new();
+
+struct Rails {
+    scan_root: Option<PathBuf>,
+    home: Option<PathBuf>,
+    min_depth: usize,
+}
+
+/// Sets what the rails protect besides `/` and mount points: the scan root and `$HOME`
+/// (and everything above them), and anything less than `min_depth` components deep
+pub fn enable(scan_root: &Path, min_depth: usize) {
+    let _ = RAILS.set(Rails {
+        scan_root: Some(canonical(scan_root)),
+        home: crate::app_dirs::home_dir().map(|home| canonical(&home)),
+        min_depth,
+    });
+}
+
+/// An error saying why `path` must not be deleted, if it mustn't
+pub fn check(path: &Path) -> Result<()> {
+    let rails = RAILS.get_or_init(|| Rails {
+        scan_root: None,
+        home: crate::app_dirs::home_dir().map(|home| canonical(&home)),
+        min_depth: DEFAULT_MIN_DEPTH,
+    });
+    let path = canonical(path);
+    let refuse = |why: &str| anyhow::bail!("Refusing to delete {}: {}", path.display(), why);
+    if path.parent().is_none() {
+        return refuse("it is the root of the filesystem");
+    }
+    if rails
+        .scan_root
+        .as_ref()
+        .is_some_and(|root| root.starts_with(&path))
+    {
+        return refuse("it is (or holds) the directory being scanned");
+    }
+    if rails
+        .home
+        .as_ref()
+        .is_some_and(|home| home.starts_with(&path))
+    {
+        return refuse("it is (or holds) your home directory");
+    }
+    let depth = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count();
+    if depth < rails.min_depth {
+        return refuse(&format!(
+            "it is less than {} levels deep (`[safety] min_depth`)",
+            rails.min_depth
+        ));
+    }
+    if is_mount_point(&path) {
+        return refuse("it is a mount point");
+    }
+    Ok(())
+}
+
+/// Symlinks along the way resolved, so that no spelling of a path gets past the rails
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(unix)]
+fn is_mount_point(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let dev = |path: &Path| std::fs::symlink_metadata(path).map(|m| m.dev()).ok();
+    match path.parent() {
+        Some(parent) => dev(path).is_some_and(|dev_of_path| dev(parent) != Some(dev_of_path)),
+        None => true,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_path: &Path) -> bool {
+    false
+}
@@ -1,17 +1,82 @@
+use crate::confidence::Confidence;
+use crate::decruftignore::IgnoreRules;
+use crate::delete::DirIdentity;
+use crate::git_status::IgnoreChecker;
+use crate::io_scheduler;
+use crate::metrics::{ScanMetrics, lock_counting};
+use crate::mounts::{MountTable, SpaceCaveat};
+use crate::ownership;
+use crate::protect::Protected;
+use crate::size_cache::{self, SizeCache};
+use crate::size_estimator::SizeEstimator;
+use crate::work_queue::WorkQueue;
 use anyhow::Result;
+use globset::GlobSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 use walkdir::WalkDir;
 
 #[derive(Clone)]
 pub struct CruftDirectory {
     pub path: PathBuf,
     pub size: u64,
+    /// Space allocated on disk
+    pub disk_usage: u64,
+    /// Space allocated on disk to files with no hard links outside this directory,
+    /// which is what deleting it actually frees
+    pub unique_disk_usage: u64,
     pub crufty_reason: CruftyReason,
     pub newest_file_age_days: Option<f64>,
+    pub owner: Option<u32>,
+    pub deletable: bool,
+    /// The largest immediate children and their sizes, largest first
+    pub breakdown: Vec<BreakdownEntry>,
+    /// The size, breakdown and age are still being computed in the background
+    pub pending: bool,
+    /// How sure the detection is; guesses (say, a name merely containing "build")
+    /// are more likely than other findings to be something worth keeping
+    pub confidence: Confidence,
+    /// Identity at scan time, to detect the directory being replaced before deletion
+    pub identity: Option<DirIdentity>,
+    /// The symlink through which this directory was found, if it lives elsewhere
+    pub via_symlink: Option<PathBuf>,
+    /// For an entry that stands for scattered files (like `.DS_Store`s) rather than a
+    /// directory, the files and directories it is made of; deleting it deletes those
+    /// instead of `path`
+    pub members: Vec<PathBuf>,
+    /// Set if deleting this won't free as much disk space as its size says (on overlayfs or tmpfs)
+    pub space_caveat: Option<SpaceCaveat>,
+    /// Files and directories inside that couldn't be looked at while sizing it,
+    /// so that the size is a lower bound
+    pub unreadable: usize,
+    /// Where the size came from, if it was estimated rather than walked for
+    pub size_estimated_by: Option<&'static str>,
+    /// Set if the size is from an earlier scan (the size cache), and so may be out of date
+    pub size_cached: bool,
+    /// How many files are in it (hard links to the same file counting once), unless it's
+    /// still being sized or its size was estimated
+    pub file_count: Option<u64>,
+    /// For an entry in the staging area, where it was deleted from
+    pub staged_from: Option<PathBuf>,
+    /// The project this belongs to: the git repository it's in, or failing that the nearest
+    /// directory above it with a project manifest in it
+    pub project: Option<PathBuf>,
+    /// Whether the git work tree it's in ignores it, as found when its confidence was assessed
+    pub git_ignored: bool,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct BreakdownEntry {
+    pub name: String,
+    pub size: u64,
+    pub disk_usage: u64,
 }
 
 impl CruftDirectory {
@@ -19,6 +84,15 @@ impl CruftDirectory {
         self.path.to_string_lossy().to_string()
     }
 
+    /// The name of the project this belongs to, or failing that of the directory containing it
+    pub fn project_name(&self) -> Option<String> {
+        self.project
+            .as_deref()
+            .or(self.path.parent())
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
     /// Trashiness score: product of size (in MB) and age (in days).
     /// Higher means more worth cleaning up (big and old = trashy).
     pub fn trashiness(&self) -> f64 {
@@ -28,7 +102,7 @@ impl CruftDirectory {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum CruftyReason {
     NodeModules,
     CacheDir,
@@ -38,8 +112,20 @@ pub enum CruftyReason {
     TempDir,
     VenvDir,
     DistDir,
-    TestReportDir,
+    CoverageDir,
     ToxDir,
+    MixBuildDir,
+    MixDepsDir,
+    ZigCacheDir,
+    ZigOutDir,
+    SwiftBuildDir,
+    CMakeBuildDir,
+    BazelOutputBase,
+    UserCache,
+    TerraformDir,
+    CdkOutDir,
+    PulumiDir,
+    FinderMetadata,
 }
 
 impl std::fmt::Display for CruftyReason {
@@ -48,74 +134,1132 @@ impl std::fmt::Display for CruftyReason {
             CruftyReason::BuildDir => write!(f, "build dir"),
             CruftyReason::CacheDir => write!(f, "cache dir"),
             CruftyReason::CacheTagFound => write!(f, "CACHEDIR.TAG"),
+            CruftyReason::CoverageDir => write!(f, "coverage"),
             CruftyReason::DistDir => write!(f, "dist dir"),
             CruftyReason::NodeModules => write!(f, "node_modules"),
             CruftyReason::RustTargetDir => write!(f, "rust target dir"),
             CruftyReason::TempDir => write!(f, "temp dir"),
-            CruftyReason::TestReportDir => write!(f, "test reports"),
             CruftyReason::ToxDir => write!(f, "tox dir"),
             CruftyReason::VenvDir => write!(f, "venv"),
+            CruftyReason::MixBuildDir => write!(f, "mix _build"),
+            CruftyReason::MixDepsDir => write!(f, "mix deps"),
+            CruftyReason::ZigCacheDir => write!(f, "zig cache"),
+            CruftyReason::ZigOutDir => write!(f, "zig out"),
+            CruftyReason::SwiftBuildDir => write!(f, "swiftpm build"),
+            CruftyReason::CMakeBuildDir => write!(f, "cmake build"),
+            CruftyReason::BazelOutputBase => write!(f, "bazel output"),
+            CruftyReason::UserCache => write!(f, "user cache"),
+            CruftyReason::TerraformDir => write!(f, "terraform"),
+            CruftyReason::CdkOutDir => write!(f, "cdk.out"),
+            CruftyReason::PulumiDir => write!(f, "pulumi plugins"),
+            CruftyReason::FinderMetadata => write!(f, "finder metadata"),
+        }
+    }
+}
+
+impl CruftyReason {
+    /// The reason shown as `label`, as in a staging manifest or the audit log
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            CruftyReason::NodeModules,
+            CruftyReason::CacheDir,
+            CruftyReason::CacheTagFound,
+            CruftyReason::BuildDir,
+            CruftyReason::RustTargetDir,
+            CruftyReason::TempDir,
+            CruftyReason::VenvDir,
+            CruftyReason::DistDir,
+            CruftyReason::CoverageDir,
+            CruftyReason::ToxDir,
+            CruftyReason::MixBuildDir,
+            CruftyReason::MixDepsDir,
+            CruftyReason::ZigCacheDir,
+            CruftyReason::ZigOutDir,
+            CruftyReason::SwiftBuildDir,
+            CruftyReason::CMakeBuildDir,
+            CruftyReason::BazelOutputBase,
+            CruftyReason::UserCache,
+            CruftyReason::TerraformDir,
+            CruftyReason::CdkOutDir,
+            CruftyReason::PulumiDir,
+            CruftyReason::FinderMetadata,
+        ]
+        .into_iter()
+        .find(|reason| reason.to_string() == label)
+    }
+
+    /// Whether this is the kind of cruft that's found by what a tool leaves in it, or by a
+    /// name only that tool uses; the rest go by generic names like `build`, `dist` and
+    /// `coverage`, which are more often something else
+    pub fn is_common_cruft(&self) -> bool {
+        match self {
+            CruftyReason::NodeModules
+            | CruftyReason::CacheDir
+            | CruftyReason::CacheTagFound
+            | CruftyReason::RustTargetDir
+            | CruftyReason::VenvDir
+            | CruftyReason::ToxDir
+            | CruftyReason::MixBuildDir
+            | CruftyReason::MixDepsDir
+            | CruftyReason::ZigCacheDir
+            | CruftyReason::SwiftBuildDir
+            | CruftyReason::BazelOutputBase
+            | CruftyReason::UserCache
+            | CruftyReason::TerraformDir
+            | CruftyReason::CdkOutDir
+            | CruftyReason::PulumiDir
+            | CruftyReason::ZigOutDir
+            | CruftyReason::CMakeBuildDir
+            | CruftyReason::FinderMetadata => true,
+            CruftyReason::BuildDir
+            | CruftyReason::TempDir
+            | CruftyReason::DistDir
+            | CruftyReason::CoverageDir => false,
         }
     }
 }
 
 pub struct ScanProgress {
+    /// Directories looked at so far
+    pub scanned: u64,
+}
+
+/// What the scanner reports, in order, as it goes
+pub enum ScanEvent {
+    /// A cruft directory; unless it is already complete, a `SizeUpdated` follows later.
+    /// One that was found before (when watching for changes) replaces the earlier one.
+    Found(CruftDirectory),
+    /// A directory found earlier has gone away
+    Removed(PathBuf),
+    /// The size and age of the directory found at `path` have been worked out
+    SizeUpdated {
+        path: PathBuf,
+        size: DirSize,
+        newest_file_age_days: Option<f64>,
+    },
+    Progress(ScanProgress),
+    /// Something that couldn't be looked at, like an unreadable directory
+    Error(String),
+    /// Subtrees that took long to read and held no cruft, sent just before `Done`
+    SlowSubtrees(Vec<SlowSubtree>),
+    /// The scan is over, and nothing else will be sent
+    Done(ScanSummary),
+}
+
+/// A subtree near the top of the scan that was slow to read and turned out to hold no cruft,
+/// like a photo library: a candidate for leaving out of future scans
+#[derive(Debug, Clone)]
+pub struct SlowSubtree {
+    pub path: PathBuf,
+    /// Time spent reading its directories, added up across the scan's threads
+    pub elapsed: Duration,
+    pub directories: u64,
+}
+
+/// Everything found so far, put together from the scanner's events
+#[derive(Default)]
+pub struct ScanResults {
+    pub dirs: Vec<CruftDirectory>,
+    pub scanned: u64,
+    pub errors: Vec<String>,
+    /// Set once the scan is done
+    pub summary: Option<ScanSummary>,
+    /// Worth excluding from future scans, slowest first
+    pub slow_subtrees: Vec<SlowSubtree>,
+    // Where each path is in `dirs`, for size updates
+    index: HashMap<PathBuf, usize>,
+}
+
+impl ScanResults {
+    pub fn apply(&mut self, event: ScanEvent) {
+        match event {
+            ScanEvent::Found(dir) => match self.index.get(&dir.path) {
+                Some(&i) => self.dirs[i] = dir,
+                None => {
+                    self.index.insert(dir.path.clone(), self.dirs.len());
+                    self.dirs.push(dir);
+                }
+            },
+            ScanEvent::Removed(path) => {
+                if self.index.contains_key(&path) {
+                    self.remove(&path);
+                }
+            }
+            ScanEvent::SizeUpdated {
+                path,
+                size,
+                newest_file_age_days,
+            } => {
+                // The entry may have been deleted from the list in the meantime
+                if let Some(&i) = self.index.get(&path) {
+                    let dir = &mut self.dirs[i];
+                    dir.size = size.total;
+                    dir.disk_usage = size.disk_usage;
+                    dir.unique_disk_usage = size.unique_disk_usage;
+                    dir.breakdown = size.breakdown;
+                    dir.newest_file_age_days = newest_file_age_days;
+                    dir.unreadable = size.errors.len();
+                    dir.size_estimated_by = size.estimated_by;
+                    dir.size_cached = size.cached;
+                    dir.file_count = size.files;
+                    dir.pending = false;
+                }
+            }
+            ScanEvent::Progress(progress) => self.scanned = progress.scanned,
+            ScanEvent::Error(message) => self.errors.push(message),
+            ScanEvent::SlowSubtrees(subtrees) => self.slow_subtrees = subtrees,
+            ScanEvent::Done(summary) => {
+                self.scanned = summary.scanned;
+                self.summary = Some(summary);
+            }
+        }
+    }
+
+    /// Takes the entry for `path` out of the results, as after deleting it
+    pub fn remove(&mut self, path: &Path) {
+        self.dirs.retain(|dir| dir.path != path);
+        self.index = self
+            .dirs
+            .iter()
+            .enumerate()
+            .map(|(i, dir)| (dir.path.clone(), i))
+            .collect();
+    }
+}
+
+pub struct ScanOptions {
+    /// How many levels below the starting directory to look at; `None` for no limit,
+    /// which stays manageable since found cruft is never descended into
+    pub max_depth: Option<usize>,
+    /// Root safety profile: never descend into system directories
+    pub strict: bool,
+    /// Also check well-known per-user cache locations outside the scanned tree
+    pub user_caches: bool,
+    /// Whose caches those are: the running user's home, or another user's for `--all-users`
+    pub home: Option<PathBuf>,
+    /// Stop descending into further directories once the scan has run this long
+    pub max_scan_time: Option<Duration>,
+    /// Stay on the starting directory's filesystem, like `du -x`
+    pub one_file_system: bool,
+    /// Descend into symlinked directories, and count what symlinks point to in sizes
+    pub follow_symlinks: bool,
+    /// Collect macOS Finder metadata files found anywhere into a single entry
+    pub finder_metadata: bool,
+    /// Paths to leave out of the walk entirely, matched both as absolute paths
+    /// and relative to the starting directory
+    pub exclude: GlobSet,
+    /// Asked for found directories' sizes before walking them
+    pub estimators: Vec<Box<dyn SizeEstimator>>,
+    /// Work out the sizes and ages of what's found; without, findings stay pending
+    pub sizes: bool,
+    /// Directories configured to be left alone, as if excluded
+    pub protected: Protected,
+    /// Sizes from earlier scans, for directories that haven't changed since
+    pub cache: Option<SizeCache>,
+}
+
+pub struct ScanSummary {
+    /// Directories looked at
     pub scanned: u64,
-    pub found: u64,
+    /// The scan was cut short by `max_scan_time`, so there may be more cruft than was found
+    pub incomplete: bool,
+    /// The scan was stopped on request, leaving the same uncertainty (and possibly unsized entries)
+    pub cancelled: bool,
+}
+
+/// How many directories to scan between `Progress` events
+const PROGRESS_INTERVAL: u64 = 256;
+
+/// How far below the starting directory subtrees are timed, to suggest excluding slow ones
+const TIMED_DEPTH: usize = 3;
+
+/// How long reading a subtree without cruft may take before it's suggested as an exclude
+const SLOW_SUBTREE: Duration = Duration::from_secs(5);
+
+/// A scan running on a thread of its own, which can be stopped and started over.
+pub struct BackgroundScan {
+    start_dir: PathBuf,
+    options: Arc<ScanOptions>,
+    metrics: Arc<ScanMetrics>,
+    cancel: Arc<AtomicBool>,
+    events: Receiver<ScanEvent>,
+    // For sending updates from elsewhere into the same stream of events
+    sender: Sender<ScanEvent>,
+    thread: JoinHandle<()>,
+}
+
+impl BackgroundScan {
+    pub fn start(start_dir: PathBuf, options: Arc<ScanOptions>, metrics: Arc<ScanMetrics>) -> Self {
+        let (cancel, events, sender, thread) = spawn_scan(&start_dir, &options, &metrics);
+        Self {
+            start_dir,
+            options,
+            metrics,
+            cancel,
+            events,
+            sender,
+            thread,
+        }
+    }
+
+    pub fn events(&self) -> &Receiver<ScanEvent> {
+        &self.events
+    }
+
+    pub fn start_dir(&self) -> &Path {
+        &self.start_dir
+    }
+
+    /// Asks the scan to stop; it sends `Done` once it has
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Stops the current scan and starts a new one with the same directory and options.
+    /// Events from the old scan are never seen, as its channel goes away with it.
+    pub fn restart(&mut self) {
+        self.cancel();
+        // Not waited for: it stops on its own, and sends into the void until it does
+        let (cancel, events, sender, thread) =
+            spawn_scan(&self.start_dir, &self.options, &self.metrics);
+        self.cancel = cancel;
+        self.events = events;
+        self.sender = sender;
+        self.thread = thread;
+    }
+
+    /// Takes another look at `path` in the background (say, after deleting it failed
+    /// halfway), sending its new size, or that it's gone, along with the scan's events
+    pub fn refresh(&self, path: &Path) {
+        let start_dir = self.start_dir.clone();
+        let options = Arc::clone(&self.options);
+        let metrics = Arc::clone(&self.metrics);
+        let sender = self.sender.clone();
+        let changed = vec![path.to_path_buf()];
+        std::thread::spawn(move || reevaluate(&start_dir, &options, &changed, &sender, &metrics));
+    }
+
+    /// Waits for the scan to finish (or notice it's been cancelled)
+    pub fn join(self) {
+        let _ = self.thread.join();
+    }
+}
+
+fn spawn_scan(
+    start_dir: &Path,
+    options: &Arc<ScanOptions>,
+    metrics: &Arc<ScanMetrics>,
+) -> (
+    Arc<AtomicBool>,
+    Receiver<ScanEvent>,
+    Sender<ScanEvent>,
+    JoinHandle<()>,
+) {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (sender, events) = mpsc::channel();
+    let thread = {
+        let sender = sender.clone();
+        let start_dir = start_dir.to_path_buf();
+        let options = Arc::clone(options);
+        let metrics = Arc::clone(metrics);
+        let cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            scan_directories(&start_dir, &options, &sender, &metrics, &cancel);
+        })
+    };
+    (cancel, events, sender, thread)
 }
 
+/// Scans `start_dir`, sending what is found to `events` and finishing with `Done`.
+/// Stops sending (but not scanning) if the receiver goes away; setting `cancel` stops
+/// the scan as soon as the threads notice, leaving whatever is still being sized unsized.
 pub fn scan_directories(
     start_dir: &Path,
-    max_depth: usize,
-    found_dirs: Arc<Mutex<Vec<CruftDirectory>>>,
-    scanned_ents: Arc<AtomicU64>,
-    progress_callback: Option<Box<dyn Fn(ScanProgress) + Send + Sync>>,
-) -> Result<()> {
-    let walker = WalkDir::new(start_dir)
-        .max_depth(max_depth)
-        .into_iter()
-        .filter_entry(|e| {
-            if !e.file_type().is_dir() {
-                return true; // Skip checking files for their cruftiness
-            }
-            scanned_ents.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    options: &ScanOptions,
+    events: &Sender<ScanEvent>,
+    metrics: &ScanMetrics,
+    cancel: &AtomicBool,
+) {
+    let scan = Scan::new(start_dir, options, events, metrics, cancel);
 
-            let path = e.path();
+    scan.scanned.fetch_add(1, Ordering::Relaxed); // The starting directory itself
+    if options.max_depth != Some(0) {
+        scan.queue
+            .push((start_dir.to_path_buf(), 0, IgnoreRules::default()));
+    }
+    let n_workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    std::thread::scope(|s| {
+        // Sizes and ages are computed by a separate pool, so that walking a huge node_modules
+        // doesn't hold up finding more cruft; the walk holds the queue open until it's done
+        scan.size_queue.hold();
+        let walk_done = scan.size_queue.done_on_drop();
+        for _ in 0..n_workers {
+            s.spawn(|| {
+                while let Some(path) = scan.size_queue.pop() {
+                    let _done = scan.size_queue.done_on_drop();
+                    metrics
+                        .size_queue
+                        .store(scan.size_queue.len(), Ordering::Relaxed);
+                    scan.fill_in_details(&path);
+                }
+            });
+        }
 
-            if path == start_dir {
-                return true; // Don't consider the starting directory itself
+        let walkers: Vec<_> = (0..n_workers)
+            .map(|_| {
+                s.spawn(|| {
+                    while let Some((dir, depth, rules)) = scan.queue.pop() {
+                        let _done = scan.queue.done_on_drop();
+                        metrics.dir_queue.store(scan.queue.len(), Ordering::Relaxed);
+                        if scan.is_cancelled() {
+                            // Drain the queue without reading anything more
+                        } else if scan.out_of_time() {
+                            scan.incomplete.store(true, Ordering::Relaxed);
+                        } else {
+                            let started = Instant::now();
+                            scan.read_dir(&dir, depth, &rules);
+                            scan.time_subtrees(&dir, started.elapsed());
+                        }
+                    }
+                })
+            })
+            .collect();
+        for walker in walkers {
+            walker.join().unwrap();
+        }
+        // A scan that was cut short may not have got to the cruft in them
+        if !scan.is_cancelled() && !scan.incomplete.load(Ordering::Relaxed) {
+            let slow_subtrees = scan.slow_subtrees();
+            if !slow_subtrees.is_empty() {
+                scan.send(ScanEvent::SlowSubtrees(slow_subtrees));
             }
+        }
 
-            if let Some(reason) = check_crufty(path) {
-                // We found cruft, so add it to our list before skipping recursion
-                let cruft_dir = CruftDirectory {
-                    path: path.to_path_buf(),
-                    size: calculate_dir_size(path).unwrap_or(0),
-                    crufty_reason: reason,
-                    newest_file_age_days: get_newest_file_age_days(path).unwrap_or(None),
-                };
-
-                // Add to the shared vector
-                if let Ok(mut dirs) = found_dirs.lock() {
-                    dirs.push(cruft_dir);
-                    if let Some(callback) = &progress_callback {
-                        let scanned = scanned_ents.load(std::sync::atomic::Ordering::Relaxed);
-                        let found = dirs.len() as u64;
-                        callback(ScanProgress { scanned, found });
-                    }
+        let finder_metadata = std::mem::take(&mut *scan.finder_metadata.lock().unwrap());
+        if !finder_metadata.is_empty() {
+            scan.add_found(make_finder_metadata_entry(
+                start_dir,
+                finder_metadata,
+                options,
+                cancel,
+            ));
+        }
+
+        if options.user_caches {
+            for path in known_user_caches(options.home.as_deref()) {
+                if path.is_dir() && scan.claim(&path) {
+                    scan.add_found(make_cruft_directory(&path, CruftyReason::UserCache));
                 }
+            }
+        }
+        drop(walk_done);
+    });
 
-                false // Don't recurse into this directory
+    if let Some(cache) = &options.cache {
+        // Only a cache: without it, the next scan just takes longer
+        let _ = cache.save();
+    }
+    let _ = events.send(ScanEvent::Done(ScanSummary {
+        scanned: scan.scanned.into_inner(),
+        incomplete: scan.incomplete.into_inner(),
+        cancelled: cancel.load(Ordering::Relaxed),
+    }));
+}
+
+/// Takes another look at paths that changed after they were scanned (as reported when
+/// watching for changes): the cruft directories they are in, or are, are sent as `Found`
+/// again and sized from scratch, and those that have gone away are sent as `Removed`.
+pub fn reevaluate(
+    start_dir: &Path,
+    options: &ScanOptions,
+    changed: &[PathBuf],
+    events: &Sender<ScanEvent>,
+    metrics: &ScanMetrics,
+) {
+    let cancel = AtomicBool::new(false);
+    let mut scan = Scan::new(start_dir, options, events, metrics, &cancel);
+    scan.use_cache = false;
+    // A build touches thousands of files in a handful of directories
+    let mut dirs = HashSet::new();
+    for path in changed {
+        if path.is_dir() {
+            dirs.insert(path.as_path());
+        } else {
+            if !path.exists() {
+                // Sent for everything that goes, only the found directories themselves matter
+                scan.send(ScanEvent::Removed(path.clone()));
+            }
+            dirs.extend(path.parent());
+        }
+    }
+    let mut found: Vec<(PathBuf, CruftyReason)> = Vec::new();
+    for dir in dirs {
+        if found.iter().any(|(path, _)| dir.starts_with(path)) {
+            continue;
+        }
+        if let Some(cruft) = scan.cruft_dir_containing(dir) {
+            found.push(cruft);
+        }
+    }
+    for (path, reason) in found {
+        let mut cruft_dir = make_cruft_directory(&path, reason);
+        scan.assess(&mut cruft_dir, &path);
+        // Without progress: this scan's count of zero directories would replace the real one
+        scan.send_found(cruft_dir);
+    }
+    while let Some(path) = scan.size_queue.pop() {
+        let _done = scan.size_queue.done_on_drop();
+        scan.fill_in_details(&path);
+    }
+}
+
+/// State shared by the scanner's worker threads
+struct Scan<'a> {
+    options: &'a ScanOptions,
+    deadline: Option<Instant>,
+    incomplete: AtomicBool,
+    cancel: &'a AtomicBool,
+    // The starting directory's filesystem, if the scan must not leave it
+    root_device: Option<u64>,
+    start_dir: &'a Path,
+    canonical_root: PathBuf,
+    // Symlink targets outside the starting directory that are being scanned too
+    followed: Mutex<Vec<PathBuf>>,
+    events: &'a Sender<ScanEvent>,
+    scanned: AtomicU64,
+    metrics: &'a ScanMetrics,
+    // Canonical paths of everything found so far (with the path it was reported as), as
+    // the same directory can be reached both directly and through symlinks (or through
+    // several Bazel convenience symlinks), and one finding can turn out to be inside another
+    reported: Mutex<BTreeMap<PathBuf, PathBuf>>,
+    // Directories still to be read, with their depth below the starting directory
+    // and the `.decruftignore` rules of the directories above them
+    queue: WorkQueue<(PathBuf, usize, IgnoreRules)>,
+    // Found directories whose size and age are still to be computed
+    size_queue: WorkQueue<PathBuf>,
+    // Finder metadata files and directories found so far, if they're being looked for
+    finder_metadata: Mutex<Vec<PathBuf>>,
+    // To tell which findings live on overlayfs or tmpfs
+    mounts: MountTable,
+    // decruft's own data and configuration, never reported
+    own_dirs: Vec<PathBuf>,
+    // Time spent reading each subtree near the top, and how many directories that was
+    subtree_times: Mutex<HashMap<PathBuf, (Duration, u64)>>,
+    // Whether sizes may be taken from the size cache, rather than only stored in it
+    use_cache: bool,
+    // For asking git which findings it ignores
+    git_ignores: IgnoreChecker,
+}
+
+impl<'a> Scan<'a> {
+    fn new(
+        start_dir: &'a Path,
+        options: &'a ScanOptions,
+        events: &'a Sender<ScanEvent>,
+        metrics: &'a ScanMetrics,
+        cancel: &'a AtomicBool,
+    ) -> Self {
+        Scan {
+            options,
+            deadline: options.max_scan_time.map(|limit| Instant::now() + limit),
+            incomplete: AtomicBool::new(false),
+            cancel,
+            root_device: if options.one_file_system {
+                device_id(start_dir)
             } else {
-                true // Not cruft, so continue recursion
+                None
+            },
+            start_dir,
+            canonical_root: fs::canonicalize(start_dir).unwrap_or_else(|_| start_dir.to_path_buf()),
+            followed: Mutex::new(Vec::new()),
+            events,
+            scanned: AtomicU64::new(0),
+            metrics,
+            reported: Mutex::new(BTreeMap::new()),
+            queue: WorkQueue::new(),
+            size_queue: WorkQueue::new(),
+            finder_metadata: Mutex::new(Vec::new()),
+            mounts: MountTable::load(),
+            own_dirs: crate::app_dirs::own_dirs(),
+            subtree_times: Mutex::new(HashMap::new()),
+            use_cache: true,
+            git_ignores: IgnoreChecker::default(),
+        }
+    }
+
+    /// Works out how sure detection is of `cruft_dir`, found at `path`, asking git whether
+    /// it ignores `path` along the way
+    fn assess(&self, cruft_dir: &mut CruftDirectory, path: &Path) {
+        cruft_dir.git_ignored = self.git_ignores.is_ignored(path);
+        cruft_dir.confidence =
+            assess_confidence(path, &cruft_dir.crufty_reason, cruft_dir.git_ignored);
+    }
+
+    /// The cruft directory that a scan would report `path` as, or as part of, if any.
+    /// Symlinks aren't followed; what's found through them is left to a rescan.
+    fn cruft_dir_containing(&self, path: &Path) -> Option<(PathBuf, CruftyReason)> {
+        let relative = path.strip_prefix(self.start_dir).ok()?;
+        if self.options.max_depth == Some(0) {
+            return None;
+        }
+        let mut dir = self.start_dir.to_path_buf();
+        let mut rules = IgnoreRules::default();
+        for (depth, component) in relative.components().enumerate() {
+            // The directory `component` is in must have been read, as at `depth - 1`
+            if depth > 0 && !self.may_descend(depth - 1) {
+                return None;
+            }
+            rules = rules.enter(&dir);
+            dir.push(component);
+            if dir.is_symlink()
+                || !dir.is_dir()
+                || self.is_excluded(&dir)
+                || rules.is_ignored(&dir, true)
+                || (self.options.strict && is_system_directory(&dir))
+                || self.is_other_file_system(&dir)
+            {
+                return None;
             }
+            if let Some(reason) = check_crufty(&dir) {
+                return Some((dir, reason));
+            }
+        }
+        None
+    }
+
+    fn is_other_file_system(&self, path: &Path) -> bool {
+        self.root_device
+            .is_some_and(|root_device| device_id(path) != Some(root_device))
+    }
+
+    /// Decides whether to descend into the symlinked directory `link` found in `dir`,
+    /// returning its target if so. Targets inside the tree being scanned (which are
+    /// reached directly) or inside one already followed are skipped, and so are links
+    /// back to a directory above `dir`, which would otherwise loop forever.
+    fn follow(&self, link: &Path, dir: &Path) -> Option<PathBuf> {
+        let target = fs::canonicalize(link).ok()?;
+        if !target.is_dir()
+            || target.starts_with(&self.canonical_root)
+            || (self.options.strict && is_system_directory(&target))
+            || self.is_other_file_system(&target)
+        {
+            return None;
+        }
+        let mut followed = self.followed.lock().unwrap();
+        if followed.iter().any(|earlier| target.starts_with(earlier))
+            || fs::canonicalize(dir).is_ok_and(|dir| dir.starts_with(&target))
+        {
+            return None;
+        }
+        followed.push(target.clone());
+        Some(target)
+    }
+
+    /// Whether `path` is in decruft's own data or configuration directories
+    fn is_own_dir(&self, path: &Path) -> bool {
+        self.own_dirs.iter().any(|own| path.starts_with(own))
+    }
+
+    /// Whether `path` is to be left out of the scan: one of decruft's own directories,
+    /// a protected one, or matched by an `--exclude` pattern
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.is_own_dir(path) || self.options.protected.contains(path) {
+            return true;
+        }
+        let exclude = &self.options.exclude;
+        !exclude.is_empty()
+            && (exclude.is_match(path)
+                || path
+                    .strip_prefix(self.start_dir)
+                    .is_ok_and(|relative| exclude.is_match(relative)))
+    }
+
+    /// Adds the time it took to read `dir` to each of the timed subtrees it is in
+    fn time_subtrees(&self, dir: &Path, elapsed: Duration) {
+        // Symlink targets outside the tree aren't something an exclude could leave out
+        let Ok(relative) = dir.strip_prefix(self.start_dir) else {
+            return;
+        };
+        let mut subtree = self.start_dir.to_path_buf();
+        let mut times = self.subtree_times.lock().unwrap();
+        for component in relative.components().take(TIMED_DEPTH) {
+            subtree.push(component);
+            let (total, directories) = times.entry(subtree.clone()).or_default();
+            *total += elapsed;
+            *directories += 1;
+        }
+    }
+
+    /// The timed subtrees that were slow to read and hold nothing that was reported, leaving
+    /// out those inside another one (excluding that covers them too), slowest first
+    fn slow_subtrees(&self) -> Vec<SlowSubtree> {
+        let times = std::mem::take(&mut *self.subtree_times.lock().unwrap());
+        let reported = self.reported.lock().unwrap();
+        let mut slow: Vec<SlowSubtree> = times
+            .into_iter()
+            .filter(|(path, (elapsed, _))| {
+                *elapsed >= SLOW_SUBTREE
+                    && !reported.iter().any(|(canonical, reported_as)| {
+                        reported_as.starts_with(path) || canonical.starts_with(path)
+                    })
+            })
+            .map(|(path, (elapsed, directories))| SlowSubtree {
+                path,
+                elapsed,
+                directories,
+            })
+            .collect();
+        slow.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut outermost: Vec<SlowSubtree> = Vec::new();
+        for subtree in slow {
+            if !outermost
+                .iter()
+                .any(|outer| subtree.path.starts_with(&outer.path))
+            {
+                outermost.push(subtree);
+            }
+        }
+        outermost.sort_by_key(|subtree| std::cmp::Reverse(subtree.elapsed));
+        outermost
+    }
+
+    /// Whether directories found at `depth` are within the depth limit, to be read in turn
+    fn may_descend(&self, depth: usize) -> bool {
+        self.options
+            .max_depth
+            .is_none_or(|max_depth| depth + 1 < max_depth)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Records that the directory at `path` is being reported; false if it already was,
+    /// or if it is inside something that was (as a symlink's target can be), since its size
+    /// is already counted there. Findings inside this one are withdrawn for the same reason.
+    fn claim(&self, path: &Path) -> bool {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut reported = lock_counting(&self.reported, &self.metrics.reported_contention);
+        if canonical
+            .ancestors()
+            .any(|ancestor| reported.contains_key(ancestor))
+        {
+            return false;
+        }
+        // Paths sort by component, so everything inside `canonical` directly follows it
+        let nested: Vec<PathBuf> = reported
+            .range::<Path, _>((Bound::Excluded(canonical.as_path()), Bound::Unbounded))
+            .take_while(|(inner, _)| inner.starts_with(&canonical))
+            .map(|(inner, _)| inner.clone())
+            .collect();
+        for inner in nested {
+            if let Some(reported_as) = reported.remove(&inner) {
+                self.send(ScanEvent::Removed(reported_as));
+            }
+        }
+        reported.insert(canonical, path.to_path_buf());
+        true
+    }
+
+    fn send(&self, event: ScanEvent) {
+        // A receiver that has gone away no longer cares
+        let _ = self.events.send(event);
+    }
+
+    fn send_progress(&self) {
+        self.send(ScanEvent::Progress(ScanProgress {
+            scanned: self.scanned.load(Ordering::Relaxed),
+        }));
+    }
+
+    fn add_found(&self, cruft_dir: CruftDirectory) {
+        self.send_progress();
+        self.send_found(cruft_dir);
+    }
+
+    /// The size of `path` according to the first estimator that knows it
+    fn estimate_size(&self, path: &Path) -> Option<DirSize> {
+        self.options.estimators.iter().find_map(|estimator| {
+            let estimate = estimator.estimate(path)?;
+            Some(DirSize {
+                total: estimate.size,
+                disk_usage: estimate.disk_usage,
+                unique_disk_usage: estimate.disk_usage,
+                estimated_by: Some(estimator.name()),
+                ..DirSize::default()
+            })
+        })
+    }
+
+    /// Sends a found directory, and queues it for sizing if need be
+    fn send_found(&self, mut cruft_dir: CruftDirectory) {
+        cruft_dir.space_caveat = self.mounts.caveat(&cruft_dir.path);
+        // Queued only once it's been sent, so the size update can't overtake it
+        let path = cruft_dir.path.clone();
+        let pending = cruft_dir.pending;
+        self.send(ScanEvent::Found(cruft_dir));
+        if pending && self.options.sizes {
+            self.size_queue.push(path);
+            self.metrics
+                .size_queue
+                .store(self.size_queue.len(), Ordering::Relaxed);
+        }
+    }
+
+    fn fill_in_details(&self, path: &Path) {
+        if self.is_cancelled() {
+            return;
+        }
+        // Taken before walking, so that changes made meanwhile make it stale
+        let signature = self.options.cache.as_ref().and_then(|_| {
+            // Sizes differ depending on these
+            let variant = (self.options.follow_symlinks, self.options.one_file_system);
+            size_cache::signature(path, variant)
         });
+        if let Some(cache) = &self.options.cache
+            && self.use_cache
+            && let Some(signature) = signature
+            && let Some(size) = cache.get(path, signature)
+            // Sizes cached before files were counted are sized again, as are estimates
+            // (which are quick anyway)
+            && size.files.is_some()
+        {
+            // Files changed deeper down leave the signature alone, and the age is about those
+            let newest_file_age_days = io_scheduler::run(path, || {
+                get_newest_file_age_days(path, self.options).unwrap_or(None)
+            });
+            self.send(ScanEvent::SizeUpdated {
+                path: path.to_path_buf(),
+                size,
+                newest_file_age_days,
+            });
+            return;
+        }
+        let (size, newest_file_age_days) = io_scheduler::run(path, || {
+            let size = match self.estimate_size(path) {
+                Some(size) => size,
+                None => calculate_dir_size(path, self.options, self.cancel).unwrap_or_default(),
+            };
+            if self.is_cancelled() {
+                return (size, None);
+            }
+            (
+                size,
+                get_newest_file_age_days(path, self.options).unwrap_or(None),
+            )
+        });
+        if self.is_cancelled() {
+            return; // The size is only partial
+        }
+        for error in &size.errors {
+            self.send(ScanEvent::Error(error.clone()));
+        }
+        // What couldn't be read may be readable next time, and estimates are cheap anyway
+        if let Some(cache) = &self.options.cache
+            && let Some(signature) = signature
+            && size.errors.is_empty()
+            && size.estimated_by.is_none()
+        {
+            cache.insert(path, signature, &size);
+        }
+        self.send(ScanEvent::SizeUpdated {
+            path: path.to_path_buf(),
+            size,
+            newest_file_age_days,
+        });
+    }
 
-    for _ in walker.filter_map(Result::ok) {
-        // Do nothing - the work is done in filter_entry
+    /// Checks each subdirectory of `dir` for cruftiness, queueing the non-crufty ones for reading
+    fn read_dir(&self, dir: &Path, depth: usize, rules: &IgnoreRules) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.send(ScanEvent::Error(format!("{}: {}", dir.display(), e)));
+                return;
+            }
+        };
+        let rules = rules.enter(dir);
+        for entry in entries {
+            if self.is_cancelled() {
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.send(ScanEvent::Error(format!("{}: {}", dir.display(), e)));
+                    continue;
+                }
+            };
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    self.send(ScanEvent::Error(format!(
+                        "{}: {}",
+                        entry.path().display(),
+                        e
+                    )));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if self.is_excluded(&path) || rules.is_ignored(&path, file_type.is_dir()) {
+                continue;
+            }
+            if file_type.is_symlink() {
+                // Symlinks to cruft are reported as their target; others are only followed on request
+                if let Some((target, reason)) = check_symlinked_cruft(&path)
+                    && !self.is_own_dir(&target)
+                    && !self.options.protected.contains(&target)
+                    && !(self.options.strict && is_system_directory(&target))
+                    && !self.is_other_file_system(&target)
+                    && self.claim(&target)
+                {
+                    let mut cruft_dir = make_cruft_directory(&target, reason);
+                    self.assess(&mut cruft_dir, &path);
+                    cruft_dir.via_symlink = Some(path);
+                    self.add_found(cruft_dir);
+                } else if self.options.follow_symlinks
+                    && self.may_descend(depth)
+                    && let Some(target) = self.follow(&path, dir)
+                {
+                    // A followed target lives outside the tree, out of reach of its ignore files
+                    self.queue.push((target, depth + 1, IgnoreRules::default()));
+                }
+                continue;
+            }
+            if !file_type.is_dir() {
+                if self.options.finder_metadata && is_finder_metadata_file(&entry.file_name()) {
+                    self.finder_metadata.lock().unwrap().push(path);
+                }
+                continue; // Skip checking (other) files for their cruftiness
+            }
+            if self
+                .scanned
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(PROGRESS_INTERVAL)
+            {
+                self.send_progress();
+            }
+
+            if self.options.strict && is_system_directory(&path) {
+                continue; // Off-limits under the root profile
+            }
+            if self.is_other_file_system(&path) {
+                continue; // A mount point, and we were asked to stay on this filesystem
+            }
+            if self.options.finder_metadata && entry.file_name() == "__MACOSX" {
+                self.finder_metadata.lock().unwrap().push(path);
+                continue;
+            }
+
+            if let Some(reason) = check_crufty(&path) {
+                // We found cruft, so add it to our list instead of recursing into it
+                // (unless it has already been found through a symlink)
+                if self.claim(&path) {
+                    let mut cruft_dir = make_cruft_directory(&path, reason);
+                    self.assess(&mut cruft_dir, &path);
+                    self.add_found(cruft_dir);
+                }
+            } else if self.may_descend(depth) {
+                // Not cruft, so continue recursion
+                self.queue.push((path, depth + 1, rules.clone()));
+            }
+        }
+    }
+}
+
+/// Describes a found cruft directory; its size and age are filled in by `Scan::fill_in_details`.
+fn make_cruft_directory(path: &Path, reason: CruftyReason) -> CruftDirectory {
+    CruftDirectory {
+        path: path.to_path_buf(),
+        size: 0,
+        disk_usage: 0,
+        unique_disk_usage: 0,
+        crufty_reason: reason,
+        newest_file_age_days: None,
+        owner: ownership::owner_uid(path),
+        deletable: ownership::can_delete(path),
+        breakdown: Vec::new(),
+        pending: true,
+        confidence: Confidence::Certain,
+        identity: DirIdentity::of(path),
+        via_symlink: None,
+        members: Vec::new(),
+        space_caveat: None,
+        unreadable: 0,
+        size_estimated_by: None,
+        size_cached: false,
+        file_count: None,
+        staged_from: None,
+        project: find_project(path),
+        git_ignored: false,
     }
+}
+
+/// An entry for a directory in the staging area at `at`, deleted from `original`, for
+/// purging what an earlier session left behind
+pub fn staged_entry(at: &Path, original: &Path, reason: CruftyReason, size: u64) -> CruftDirectory {
+    CruftDirectory {
+        size,
+        pending: false,
+        staged_from: Some(original.to_path_buf()),
+        project: None,
+        ..make_cruft_directory(at, reason)
+    }
+}
+
+/// What makes a directory a project of its own, outside a git repository, for telling which
+/// one a finding belongs to
+const PROJECT_MANIFESTS: &[&str] = &[
+    "package.json",
+    "Cargo.toml",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "mix.exs",
+    "build.zig",
+    "Package.swift",
+    "CMakeLists.txt",
+    "Gemfile",
+    "composer.json",
+];
+
+/// The git repository `path` is in, so that the parts of a monorepo count as one project;
+/// otherwise the nearest directory above it with one of the `PROJECT_MANIFESTS` in it
+fn find_project(path: &Path) -> Option<PathBuf> {
+    let mut ancestors = path.ancestors().skip(1);
+    ancestors
+        .clone()
+        .find(|dir| dir.join(".git").exists())
+        .or_else(|| {
+            ancestors.find(|dir| {
+                PROJECT_MANIFESTS
+                    .iter()
+                    .any(|manifest| dir.join(manifest).exists())
+            })
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Finder's `.DS_Store` files, and AppleDouble `._*` files (resource forks and extended
+/// attributes, written when copying to filesystems that can't store them natively)
+fn is_finder_metadata_file(name: &std::ffi::OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name == ".DS_Store" || name.starts_with("._")
+}
 
-    Ok(())
+/// Collects Finder metadata files and `__MACOSX` directories (left behind by
+/// extracting zips made on a Mac) found throughout `root` into one entry.
+fn make_finder_metadata_entry(
+    root: &Path,
+    members: Vec<PathBuf>,
+    options: &ScanOptions,
+    cancel: &AtomicBool,
+) -> CruftDirectory {
+    let (mut size, mut disk_usage, mut files) = (0, 0, 0);
+    let mut newest = None;
+    for member in &members {
+        let Ok(metadata) = fs::symlink_metadata(member) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            let dir_size = calculate_dir_size(member, options, cancel).unwrap_or_default();
+            size += dir_size.total;
+            disk_usage += dir_size.disk_usage;
+            files += dir_size.files.unwrap_or(0);
+        } else {
+            size += metadata.len();
+            disk_usage += allocated_size(&metadata);
+            files += 1;
+        }
+        newest = newest.max(metadata.modified().ok());
+    }
+    let age_days = newest
+        .and_then(|newest| SystemTime::now().duration_since(newest).ok())
+        .map(|age| age.as_secs() as f64 / 86400.0);
+    let mut entry = make_cruft_directory(root, CruftyReason::FinderMetadata);
+    entry.size = size;
+    entry.disk_usage = disk_usage;
+    entry.unique_disk_usage = disk_usage;
+    entry.newest_file_age_days = age_days;
+    entry.file_count = Some(files);
+    entry.deletable = members
+        .iter()
+        .all(|member| member.parent().is_some_and(ownership::can_remove_from));
+    entry.pending = false;
+    entry.members = members;
+    entry
+}
+
+/// Per-user cache locations, relative to the home directory
+const USER_CACHE_DIRS: &[&str] = &[
+    ".cache",
+    "Library/Caches",
+    ".cargo/registry",
+    ".cargo/git",
+    ".npm",
+    ".m2/repository",
+    ".gradle/caches",
+];
+
+fn known_user_caches(home: Option<&Path>) -> Vec<PathBuf> {
+    let Some(home) = home else {
+        return Vec::new();
+    };
+    USER_CACHE_DIRS.iter().map(|dir| home.join(dir)).collect()
+}
+
+const BAZEL_WORKSPACE_FILES: &[&str] = &["MODULE.bazel", "WORKSPACE", "WORKSPACE.bazel"];
+
+/// Resolves a symlink to a cruft directory (say, `node_modules -> /cache/shared/node_modules`)
+/// to its real target.
+fn check_symlinked_cruft(path: &Path) -> Option<(PathBuf, CruftyReason)> {
+    if let Some(output_base) = check_bazel_symlink(path) {
+        return Some((output_base, CruftyReason::BazelOutputBase));
+    }
+    let target = fs::canonicalize(path).ok()?;
+    if !target.is_dir() {
+        return None;
+    }
+    // Judged by the link's name, which is what the project expects to find there
+    Some((target, check_crufty(path)?))
+}
+
+/// Resolves a Bazel convenience symlink (`bazel-out`, `bazel-bin`, ...) in a workspace
+/// to the output base it points into, which is where the actual gigabytes live.
+fn check_bazel_symlink(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy();
+    if !name.starts_with("bazel-")
+        || !BAZEL_WORKSPACE_FILES
+            .iter()
+            .any(|file| has_sibling_file(path, file))
+    {
+        return None;
+    }
+    let target = fs::canonicalize(path).ok()?;
+    if !target.is_dir() {
+        return None;
+    }
+    // The symlinks point to <output base>/execroot/<workspace>[/bazel-out[/...]]
+    let output_base = target
+        .ancestors()
+        .find(|ancestor| ancestor.file_name().is_some_and(|n| n == "execroot"))
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or(target);
+    Some(output_base)
 }
 
 const PROTECTED_DIRS: &[&str] = &[".git", ".github", ".idea", ".vscode"];
@@ -139,6 +1283,50 @@ fn is_protected_directory(path: &Path) -> bool {
     false
 }
 
+/// System locations that the root profile refuses to scan (and thus to delete from)
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/lib", "/lib32", "/lib64", "/libx32", "/proc", "/run",
+    "/sbin", "/snap", "/sys", "/usr", "/var/lib",
+];
+
+fn is_system_directory(path: &Path) -> bool {
+    SYSTEM_DIRS.iter().any(|system| path.starts_with(system))
+}
+
+/// Which rule, if any, `path` matches, without looking at its surroundings in a scan
+/// (excludes, ignore files, the root profile)
+pub fn detect(path: &Path) -> Option<CruftyReason> {
+    check_crufty(path)
+}
+
+/// Whether `dir` would still be found as what it was found as, going by the rules that
+/// look at a path by itself (the name, marker files next to it or inside it, where a
+/// symlink to it points). Entries that aren't found that way (user caches, aggregates, and
+/// what's in the staging area) count as unchanged.
+pub fn still_detected(dir: &CruftDirectory) -> bool {
+    if dir.staged_from.is_some()
+        || !dir.members.is_empty()
+        || dir.crufty_reason == CruftyReason::UserCache
+    {
+        return true;
+    }
+    match dir.via_symlink {
+        Some(ref link) => check_symlinked_cruft(link)
+            .is_some_and(|(target, reason)| target == dir.path && reason == dir.crufty_reason),
+        None => check_crufty(&dir.path).as_ref() == Some(&dir.crufty_reason),
+    }
+}
+
+/// Whether what's at `path` is still detected as `reason`, for an entry only known by its path
+/// and reason, like a deletion the audit log says was cut short
+pub fn still_detected_at(path: &Path, reason: &CruftyReason) -> bool {
+    match reason {
+        // Found some other way than by looking at the path itself, as in `still_detected`
+        CruftyReason::UserCache | CruftyReason::FinderMetadata => true,
+        reason => check_crufty(path).as_ref() == Some(reason),
+    }
+}
+
 fn check_crufty(path: &Path) -> Option<CruftyReason> {
     // Skip protected directories
     if is_protected_directory(path) {
@@ -157,6 +1345,47 @@ fn check_crufty(path: &Path) -> Option<CruftyReason> {
         return Some(CruftyReason::NodeModules);
     }
 
+    // Check for Elixir/Erlang (Mix) build artifacts and fetched dependencies;
+    // these must come before the generic build check since `_build` contains "build"
+    if (file_name == "_build" || file_name == "deps") && has_sibling_file(path, "mix.exs") {
+        return Some(if file_name == "_build" {
+            CruftyReason::MixBuildDir
+        } else {
+            CruftyReason::MixDepsDir
+        });
+    }
+
+    // Check for Zig build artifacts (`.zig-cache` since Zig 0.13, `zig-cache` before that)
+    if (file_name == "zig-cache" || file_name == ".zig-cache" || file_name == "zig-out")
+        && has_sibling_file(path, "build.zig")
+    {
+        return Some(if file_name == "zig-out" {
+            CruftyReason::ZigOutDir
+        } else {
+            CruftyReason::ZigCacheDir
+        });
+    }
+
+    // Check for SwiftPM build directories
+    if file_name == ".build" && has_sibling_file(path, "Package.swift") {
+        return Some(CruftyReason::SwiftBuildDir);
+    }
+
+    // Check for CMake build trees, which can be called anything (`cmake-build-debug`, `bld`, ...)
+    if path.join("CMakeCache.txt").is_file() {
+        return Some(CruftyReason::CMakeBuildDir);
+    }
+
+    // Check for infrastructure-as-code caches (re-downloadable providers, synthesized templates);
+    // these are never flagged if they hold state, which is not re-creatable, but the caches
+    // inside them are
+    if let Some(reason) = check_iac_cache(path, &file_name) {
+        if contains_iac_state(path) {
+            return None;
+        }
+        return Some(reason);
+    }
+
     // Check for cache directories
     if path_str.contains(".cache") || file_name.contains("cache") {
         return Some(CruftyReason::CacheDir);
@@ -199,9 +1428,14 @@ fn check_crufty(path: &Path) -> Option<CruftyReason> {
         return Some(CruftyReason::DistDir);
     }
 
-    // Check for test report directories
-    if file_name == "htmlcov" {
-        return Some(CruftyReason::TestReportDir);
+    // Check for coverage reports; a plain `coverage` directory could be source code,
+    // so it needs to look like one of the usual report formats
+    if file_name == "htmlcov"
+        || file_name == ".nyc_output"
+        || file_name == "lcov-report"
+        || (file_name == "coverage" && COVERAGE_MARKERS.iter().any(|m| path.join(m).exists()))
+    {
+        return Some(CruftyReason::CoverageDir);
     }
 
     // Check for tox directories
@@ -218,11 +1452,168 @@ fn check_crufty(path: &Path) -> Option<CruftyReason> {
     None
 }
 
+/// Cache directory names that are unambiguous, as opposed to anything merely containing "cache"
+const CACHE_DIR_NAMES: &[&str] = &[
+    "cache",
+    ".cache",
+    "__pycache__",
+    ".pytest_cache",
+    ".mypy_cache",
+    ".ruff_cache",
+    ".parcel-cache",
+    ".sass-cache",
+];
+
+/// How sure `check_crufty` can be about `path`: a guess if it only matched a substring
+/// of the name, certain if a marker file backs it up, and likely for an exact name alone
+/// (git ignoring it raising each of those a notch)
+fn assess_confidence(path: &Path, reason: &CruftyReason, git_ignored: bool) -> Confidence {
+    let Some(file_name) = path.file_name() else {
+        return Confidence::Likely;
+    };
+    let file_name = file_name.to_string_lossy().to_lowercase();
+    let guess = match reason {
+        CruftyReason::CacheDir => !CACHE_DIR_NAMES.contains(&file_name.as_str()),
+        CruftyReason::BuildDir => file_name != "build",
+        CruftyReason::DistDir => file_name != "dist" && file_name != "out",
+        _ => false,
+    };
+    if guess {
+        return if git_ignored {
+            Confidence::Likely
+        } else {
+            Confidence::Guess
+        };
+    }
+    let certain = match reason {
+        // Found by their marker files, or sibling project files
+        CruftyReason::CacheTagFound
+        | CruftyReason::RustTargetDir
+        | CruftyReason::MixBuildDir
+        | CruftyReason::MixDepsDir
+        | CruftyReason::ZigCacheDir
+        | CruftyReason::ZigOutDir
+        | CruftyReason::SwiftBuildDir
+        | CruftyReason::CMakeBuildDir
+        | CruftyReason::BazelOutputBase
+        | CruftyReason::UserCache
+        | CruftyReason::FinderMetadata => true,
+        CruftyReason::NodeModules => has_sibling_file(path, "package.json"),
+        CruftyReason::VenvDir => path.join("pyvenv.cfg").is_file(),
+        CruftyReason::CoverageDir => file_name == "coverage",
+        CruftyReason::ToxDir => has_sibling_file(path, "tox.ini"),
+        CruftyReason::CacheDir => path.join("CACHEDIR.TAG").is_file(),
+        _ => false,
+    };
+    // A project that git-ignores a directory has said it's generated
+    if certain || git_ignored {
+        Confidence::Certain
+    } else {
+        Confidence::Likely
+    }
+}
+
+/// Files inside a finding that detection or its confidence goes by
+const INSIDE_MARKERS: &[&str] = &[
+    "CACHEDIR.TAG",
+    ".rustc_info.json",
+    "CMakeCache.txt",
+    "pyvenv.cfg",
+];
+
+/// Project files next to a finding that detection or its confidence goes by
+const SIBLING_MARKERS: &[&str] = &[
+    "package.json",
+    "mix.exs",
+    "build.zig",
+    "Package.swift",
+    "tox.ini",
+];
+
+/// What backs up a finding, for the inspector: how its name matched, the marker files
+/// inside it or next to it that detection and confidence go by, and whether git ignores it
+/// (as found by the scan).
+pub fn evidence(path: &Path, git_ignored: bool) -> Vec<String> {
+    let mut evidence = Vec::new();
+    if let Some(name) = path.file_name() {
+        evidence.push(format!("named {}", name.to_string_lossy()));
+    }
+    for marker in INSIDE_MARKERS.iter().chain(COVERAGE_MARKERS) {
+        if path.join(marker).exists() {
+            evidence.push(format!("{} inside", marker));
+        }
+    }
+    for marker in SIBLING_MARKERS {
+        if has_sibling_file(path, marker) {
+            evidence.push(format!("{} next to it", marker));
+        }
+    }
+    if git_ignored {
+        evidence.push("ignored by git".to_string());
+    }
+    evidence
+}
+
+/// Files found in `coverage/` directories written by Istanbul/nyc, Jest, c8 and friends
+const COVERAGE_MARKERS: &[&str] = &[
+    "lcov.info",
+    "lcov-report",
+    "coverage-final.json",
+    "coverage-summary.json",
+    "clover.xml",
+    "cobertura-coverage.xml",
+];
+
+fn check_iac_cache(path: &Path, file_name: &str) -> Option<CruftyReason> {
+    match file_name {
+        ".terraform" => Some(CruftyReason::TerraformDir),
+        "cdk.out" => Some(CruftyReason::CdkOutDir),
+        ".pulumi" => Some(CruftyReason::PulumiDir),
+        "plugins" if parent_is(path, ".pulumi") => Some(CruftyReason::PulumiDir),
+        "providers" | "modules" if parent_is(path, ".terraform") => {
+            Some(CruftyReason::TerraformDir)
+        }
+        _ => None,
+    }
+}
+
+fn parent_is(path: &Path, name: &str) -> bool {
+    path.parent()
+        .and_then(|parent| parent.file_name())
+        .is_some_and(|parent_name| parent_name == name)
+}
+
+/// Pulumi keeps stacks (state), backups, history and credentials next to its plugin cache
+const PULUMI_STATE: &[&str] = &["stacks", "backups", "history", "credentials.json"];
+
+/// Terraform keeps the backend's state, and which workspace is selected, next to its
+/// provider and module caches
+const TERRAFORM_STATE: &[&str] = &[
+    "terraform.tfstate",
+    "terraform.tfstate.backup",
+    "environment",
+];
+
+/// Checks whether an IaC cache directory contains Terraform or Pulumi state, where those
+/// tools keep it (the caches themselves, which can be huge, aren't walked)
+fn contains_iac_state(path: &Path) -> bool {
+    PULUMI_STATE
+        .iter()
+        .chain(TERRAFORM_STATE)
+        .any(|name| path.join(name).exists())
+}
+
+/// Checks whether the directory's parent contains a file with the given name
+fn has_sibling_file(path: &Path, name: &str) -> bool {
+    path.parent()
+        .is_some_and(|parent| parent.join(name).is_file())
+}
+
 /// Calculates the age of the newest file in a directory in days
-fn get_newest_file_age_days(path: &Path) -> Result<Option<f64>> {
+fn get_newest_file_age_days(path: &Path, options: &ScanOptions) -> Result<Option<f64>> {
     let now = SystemTime::now();
 
-    let newest_child_mtime = WalkDir::new(path)
+    let newest_child_mtime = walk(path, options)
         .max_depth(3)
         .into_iter()
         .filter_map(Result::ok)
@@ -251,18 +1642,259 @@ fn get_newest_file_age_days(path: &Path) -> Result<Option<f64>> {
     Ok(None)
 }
 
-fn calculate_dir_size(path: &Path) -> Result<u64> {
+/// How many of the largest immediate children to keep for the breakdown view
+const BREAKDOWN_ENTRIES: usize = 5;
+
+/// Label used in breakdowns for files directly inside the directory
+pub const OWN_FILES_LABEL: &str = "(files)";
+
+/// What a directory's files add up to
+#[derive(Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DirSize {
+    total: u64,
+    disk_usage: u64,
+    unique_disk_usage: u64,
+    breakdown: Vec<BreakdownEntry>,
+    /// How many files there are, if they were walked for
+    #[serde(default)]
+    files: Option<u64>,
+    /// What couldn't be read, and so isn't counted
+    #[serde(skip)]
+    errors: Vec<String>,
+    /// The estimator that came up with the size, if it wasn't walked for (and so has no breakdown)
+    #[serde(skip)]
+    estimated_by: Option<&'static str>,
+    /// Set if it's from the size cache rather than walked for in this scan
+    #[serde(skip)]
+    cached: bool,
+}
+
+impl DirSize {
+    /// The size as served from the size cache
+    pub fn as_cached(&self) -> DirSize {
+        DirSize {
+            cached: true,
+            ..self.clone()
+        }
+    }
+}
+
+/// A file with several hard links, as seen while calculating a directory's size
+struct HardLink {
+    links: u64,
+    seen: u64,
+    disk_usage: u64,
+}
+
+/// Walks `path` for sizes and ages, honoring the scan's filesystem and symlink options.
+/// When following symlinks, walkdir skips links back to an ancestor, so loops end.
+fn walk(path: &Path, options: &ScanOptions) -> WalkDir {
+    WalkDir::new(path)
+        .same_file_system(options.one_file_system)
+        .follow_links(options.follow_symlinks)
+}
+
+/// Sums up the sizes of the files in `path`, counting hard-linked files only once.
+fn calculate_dir_size(path: &Path, options: &ScanOptions, cancel: &AtomicBool) -> Result<DirSize> {
     let mut total_size = 0;
+    let mut total_disk_usage = 0;
+    let mut files = 0;
+    let mut child_sizes: HashMap<String, (u64, u64)> = HashMap::new();
+    let mut hard_links: HashMap<(u64, u64), HardLink> = HashMap::new();
+    let mut errors = Vec::new();
 
-    for entry in WalkDir::new(path)
+    for entry in walk(path, options)
         .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
+        .take_while(|_| !cancel.load(Ordering::Relaxed))
     {
-        if let Ok(metadata) = fs::metadata(entry.path()) {
-            total_size += metadata.len();
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let error_path = e.path().unwrap_or(path).display().to_string();
+                match e.io_error() {
+                    Some(io_error) => errors.push(format!("{}: {}", error_path, io_error)),
+                    None => errors.push(format!("{}: {}", error_path, e)),
+                }
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
         }
+        let metadata = match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                errors.push(format!("{}: {}", entry.path().display(), e));
+                continue;
+            }
+        };
+        let disk_usage = allocated_size(&metadata);
+        // Files reachable through several symlinks are counted once too
+        if let Some((key, links)) = file_key(&metadata)
+            && (links > 1 || options.follow_symlinks)
+        {
+            let link = hard_links.entry(key).or_insert(HardLink {
+                links,
+                seen: 0,
+                disk_usage,
+            });
+            link.seen += 1;
+            if link.seen > 1 {
+                continue; // Already counted
+            }
+        }
+        total_size += metadata.len();
+        total_disk_usage += disk_usage;
+        files += 1;
+        // Attribute the file to the immediate child of `path` it lives in
+        let child = if entry.depth() == 1 {
+            OWN_FILES_LABEL.to_string()
+        } else {
+            entry
+                .path()
+                .strip_prefix(path)
+                .ok()
+                .and_then(|rel| rel.components().next())
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_else(|| OWN_FILES_LABEL.to_string())
+        };
+        let child_size = child_sizes.entry(child).or_default();
+        child_size.0 += metadata.len();
+        child_size.1 += disk_usage;
     }
 
-    Ok(total_size)
+    // Files that also have links outside the directory won't be freed by deleting it
+    let shared_disk_usage: u64 = hard_links
+        .values()
+        .filter(|link| link.seen < link.links)
+        .map(|link| link.disk_usage)
+        .sum();
+
+    let mut breakdown: Vec<BreakdownEntry> = child_sizes
+        .into_iter()
+        .map(|(name, (size, disk_usage))| BreakdownEntry {
+            name,
+            size,
+            disk_usage,
+        })
+        .collect();
+    breakdown.sort_by_key(|entry| std::cmp::Reverse(entry.size.max(entry.disk_usage)));
+    breakdown.truncate(BREAKDOWN_ENTRIES);
+
+    Ok(DirSize {
+        total: total_size,
+        disk_usage: total_disk_usage,
+        unique_disk_usage: total_disk_usage - shared_disk_usage,
+        breakdown,
+        files: Some(files),
+        errors,
+        estimated_by: None,
+        cached: false,
+    })
+}
+
+/// The device a directory lives on, to tell mount points apart
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Identifies a file by device and inode, along with its hard link count.
+#[cfg(unix)]
+fn file_key(metadata: &fs::Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some(((metadata.dev(), metadata.ino()), metadata.nlink()))
+}
+
+#[cfg(not(unix))]
+fn file_key(_metadata: &fs::Metadata) -> Option<((u64, u64), u64)> {
+    None
+}
+
+#[cfg(unix)]
+pub fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512 // st_blocks is always in 512-byte units
+}
+
+#[cfg(windows)]
+pub fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::windows::fs::MetadataExt;
+    if is_cloud_placeholder(metadata.file_attributes()) {
+        0
+    } else {
+        metadata.len()
+    }
+}
+
+// Windows file attributes, from winnt.h; defined everywhere so that the tests run everywhere
+#[cfg_attr(not(windows), allow(dead_code))]
+const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+#[cfg_attr(not(windows), allow(dead_code))]
+const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+#[cfg_attr(not(windows), allow(dead_code))]
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+// Only the tests need these, to check that ordinary files aren't taken for placeholders
+#[cfg(test)]
+const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+#[cfg(test)]
+const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x20;
+#[cfg(test)]
+const FILE_ATTRIBUTE_NORMAL: u32 = 0x80;
+#[cfg(test)]
+const FILE_ATTRIBUTE_PINNED: u32 = 0x80000;
+#[cfg(test)]
+const FILE_ATTRIBUTE_UNPINNED: u32 = 0x100000;
+
+/// Whether a file with these Windows file attributes is a cloud placeholder (OneDrive "files
+/// on demand" and friends): those report their full size, but their contents are only
+/// fetched when accessed, so they take up no space
+#[cfg_attr(not(windows), allow(dead_code))]
+fn is_cloud_placeholder(file_attributes: u32) -> bool {
+    let placeholder = FILE_ATTRIBUTE_OFFLINE
+        | FILE_ATTRIBUTE_RECALL_ON_OPEN
+        | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS;
+    file_attributes & placeholder != 0
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn onedrive_placeholders_take_no_space() {
+        // Online-only files: not downloaded until opened or read
+        assert!(is_cloud_placeholder(
+            FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_UNPINNED
+        ));
+        assert!(is_cloud_placeholder(
+            FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_ARCHIVE
+        ));
+        // Offline storage, as with older sync clients and HSM
+        assert!(is_cloud_placeholder(FILE_ATTRIBUTE_OFFLINE));
+    }
+
+    #[test]
+    fn local_files_are_not_placeholders() {
+        assert!(!is_cloud_placeholder(0));
+        assert!(!is_cloud_placeholder(FILE_ATTRIBUTE_NORMAL));
+        assert!(!is_cloud_placeholder(
+            FILE_ATTRIBUTE_ARCHIVE | FILE_ATTRIBUTE_READONLY
+        ));
+        // "Always keep on this device": downloaded, so it takes up its full size
+        assert!(!is_cloud_placeholder(
+            FILE_ATTRIBUTE_PINNED | FILE_ATTRIBUTE_ARCHIVE
+        ));
+    }
 }
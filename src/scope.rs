@@ -0,0 +1,72 @@
+use crate::keymap::{Command, keymap};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// What a run is set up to look at and what it leaves out, one line per setting, shown in a
+/// panel over the list as the scan starts, so that a wrong root or a stray config file is
+/// noticed (and the run given up with `q`) before anything gets deleted.
+#[derive(Debug, Default)]
+pub struct Scope {
+    lines: Vec<(&'static str, String)>,
+}
+
+impl Scope {
+    pub fn add(&mut self, label: &'static str, value: impl Into<String>) {
+        self.lines.push((label, value.into()));
+    }
+
+    /// Lists the items (or says there are none)
+    pub fn add_list(&mut self, label: &'static str, items: &[String]) {
+        let value = if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.join(", ")
+        };
+        self.add(label, value);
+    }
+
+    pub fn draw(&self, f: &mut Frame) {
+        let area = f.area();
+        let height = (self.lines.len() as u16 + 2).min(area.height);
+        let popup_area = Rect::new(
+            area.width / 10,
+            area.height.saturating_sub(height) / 2,
+            area.width - area.width / 5,
+            height,
+        );
+        let width = self
+            .lines
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", label),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(value.clone()),
+                ])
+            })
+            .collect();
+        let panel = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(format!(
+                    "Scan scope ({} to quit, any other key to continue, {} to show again)",
+                    keymap().key_for(Command::Quit),
+                    keymap().key_for(Command::Scope)
+                )),
+        );
+        f.render_widget(Clear, popup_area);
+        f.render_widget(panel, popup_area);
+    }
+}
@@ -0,0 +1,18 @@
+use crate::cycle::Cycle;
+
+/// What the TUI shows below the header; Tab goes from one to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    List,
+    /// Bar charts of the totals per type, project and age
+    Totals,
+    /// A rectangle per entry, sized by its size; selecting and deleting work as in the list
+    Treemap,
+}
+
+impl Cycle for Screen {
+    fn all_values() -> &'static [Self] {
+        static ALL: [Screen; 3] = [Screen::List, Screen::Totals, Screen::Treemap];
+        &ALL
+    }
+}
@@ -0,0 +1,84 @@
+use crate::ownership;
+use crate::scanner::CruftDirectory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Path,
+    Reason,
+    Project,
+    Owner,
+}
+
+impl Column {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Column::Path),
+            "reason" | "type" => Some(Column::Reason),
+            "project" => Some(Column::Project),
+            "owner" | "user" => Some(Column::Owner),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    column: Column,
+    needle: String,
+}
+
+/// A search query: whitespace-separated terms, all of which must match.
+/// A term is either plain text (matched against the path) or `column:text`.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    raw: String,
+    terms: Vec<Term>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let terms = query
+            .split_whitespace()
+            .map(|term| match term.split_once(':') {
+                Some((column, needle)) => match Column::from_name(&column.to_lowercase()) {
+                    Some(column) => Ok(Term {
+                        column,
+                        needle: needle.to_lowercase(),
+                    }),
+                    None => Err(format!(
+                        "Unknown search column '{}' (try path, reason, project or owner)",
+                        column
+                    )),
+                },
+                None => Ok(Term {
+                    column: Column::Path,
+                    needle: term.to_lowercase(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            raw: query.trim().to_string(),
+            terms,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, dir: &CruftDirectory) -> bool {
+        self.terms.iter().all(|term| {
+            let haystack = match term.column {
+                Column::Path => dir.path.to_string_lossy().to_string(),
+                Column::Reason => format!("{} {:?}", dir.crufty_reason, dir.crufty_reason),
+                Column::Project => dir.project_name().unwrap_or_default(),
+                Column::Owner => dir.owner.map(ownership::user_name).unwrap_or_default(),
+            };
+            haystack.to_lowercase().contains(&term.needle)
+        })
+    }
+}
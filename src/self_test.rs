@@ -0,0 +1,181 @@
+use crate::metrics::ScanMetrics;
+use crate::protect::Protected;
+use crate::scanner::{self, CruftyReason, ScanOptions, ScanResults};
+use crate::staging::{self, Staging};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+
+/// The fixture tree: files to create (relative path and length), and what should be found
+const FILES: &[(&str, usize)] = &[
+    ("web/package.json", 2),
+    ("web/node_modules/left-pad/index.js", 1000),
+    ("web/node_modules/left-pad/package.json", 24),
+    ("crate/Cargo.toml", 10),
+    ("crate/target/.rustc_info.json", 2),
+    ("crate/target/debug/app", 4096),
+    ("py/.venv/pyvenv.cfg", 30),
+    ("py/.venv/lib/site.py", 500),
+    ("store/CACHEDIR.TAG", 43),
+    ("store/blob", 777),
+    ("src/templates/page.html", 100),
+];
+
+const EXPECTED: &[(&str, CruftyReason)] = &[
+    ("web/node_modules", CruftyReason::NodeModules),
+    ("crate/target", CruftyReason::RustTargetDir),
+    ("py/.venv", CruftyReason::VenvDir),
+    ("store", CruftyReason::CacheTagFound),
+];
+
+/// `--self-test`: builds a small tree of known cruft in a temporary directory, scans it,
+/// checks what was found and how big it is, and deletes one of the findings (first into a
+/// staging area and back, then for good), to tell whether this build works on this
+/// platform and filesystem. Cleans up after itself.
+pub fn run() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("decruft-self-test-{}", std::process::id()));
+    // Never anything that was there already, as all of it goes afterwards
+    std::fs::create_dir(&root)
+        .with_context(|| format!("Cannot create {}", root.display()))
+        .context("Self-test failed")?;
+    let result = run_in(&root);
+    let _ = std::fs::remove_dir_all(&root);
+    match result {
+        Ok(()) => {
+            println!("Self-test passed");
+            Ok(())
+        }
+        Err(e) => Err(e.context("Self-test failed")),
+    }
+}
+
+fn run_in(root: &Path) -> Result<()> {
+    for (file, len) in FILES {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap())
+            .with_context(|| format!("Cannot create {}", path.display()))?;
+        std::fs::write(&path, vec![b'x'; *len])
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+    }
+    println!("Created a fixture tree in {}", root.display());
+
+    let results = scan(root);
+    if !results.errors.is_empty() {
+        anyhow::bail!("the scan could not read {}", results.errors.join(", "));
+    }
+    for (relative, reason) in EXPECTED {
+        let path = root.join(relative);
+        let dir = results
+            .dirs
+            .iter()
+            .find(|dir| dir.path == path)
+            .with_context(|| format!("{} was not found", relative))?;
+        if dir.crufty_reason != *reason {
+            anyhow::bail!(
+                "{} was found as {:?} instead of {:?}",
+                relative,
+                dir.crufty_reason,
+                reason
+            );
+        }
+        let expected_size = expected_size(relative);
+        if dir.pending || dir.size != expected_size {
+            anyhow::bail!(
+                "{} was sized at {} bytes instead of {}",
+                relative,
+                dir.size,
+                expected_size
+            );
+        }
+        println!("ok: {} found as {} ({} bytes)", relative, reason, dir.size);
+    }
+    if results.dirs.len() != EXPECTED.len() {
+        let unexpected: Vec<String> = results
+            .dirs
+            .iter()
+            .filter(|dir| {
+                !EXPECTED
+                    .iter()
+                    .any(|(relative, _)| dir.path == root.join(relative))
+            })
+            .map(|dir| dir.path.display().to_string())
+            .collect();
+        anyhow::bail!("also found {}", unexpected.join(", "));
+    }
+    println!("ok: nothing else found");
+
+    let doomed = &results.dirs[results
+        .dirs
+        .iter()
+        .position(|dir| dir.crufty_reason == CruftyReason::NodeModules)
+        .unwrap()];
+    let mut staging = Staging::new(&root.join("pending"));
+    let at = staging.next_slot()?;
+    staging.add(staging::stage(doomed, &at, None)?);
+    if doomed.path.exists() || !at.is_dir() {
+        anyhow::bail!(
+            "{} was not moved into {}",
+            doomed.path.display(),
+            at.display()
+        );
+    }
+    println!("ok: staged {}", doomed.path.display());
+    staging.undo(None).context("nothing was staged")??;
+    if !doomed.path.join("left-pad/index.js").is_file() || at.exists() {
+        anyhow::bail!("{} was not put back by undoing", doomed.path.display());
+    }
+    println!("ok: restored {}", doomed.path.display());
+
+    crate::delete::delete_entry(doomed, None)?;
+    if doomed.path.exists() {
+        anyhow::bail!("{} is still there after deleting it", doomed.path.display());
+    }
+    if !root.join("web/package.json").exists() {
+        anyhow::bail!("deleting node_modules took its project with it");
+    }
+    println!("ok: deleted {}", doomed.path.display());
+    Ok(())
+}
+
+fn scan(root: &Path) -> ScanResults {
+    let options = ScanOptions {
+        max_depth: None,
+        strict: false,
+        user_caches: false,
+        home: None,
+        max_scan_time: None,
+        one_file_system: false,
+        follow_symlinks: false,
+        finder_metadata: false,
+        exclude: globset::GlobSet::empty(),
+        estimators: Vec::new(),
+        sizes: true,
+        protected: Protected::default(),
+        cache: None,
+    };
+    let (events, received) = mpsc::channel();
+    scanner::scan_directories(
+        root,
+        &options,
+        &events,
+        &ScanMetrics::default(),
+        &AtomicBool::new(false),
+    );
+    drop(events);
+    let mut results = ScanResults::default();
+    for event in received {
+        results.apply(event);
+    }
+    results
+}
+
+/// The summed length of the fixture files under `relative`
+fn expected_size(relative: &str) -> u64 {
+    let dir = PathBuf::from(relative);
+    FILES
+        .iter()
+        .filter(|(file, _)| Path::new(file).starts_with(&dir))
+        .map(|(_, len)| *len as u64)
+        .sum()
+}
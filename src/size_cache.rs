@@ -0,0 +1,122 @@
+use crate::scanner::DirSize;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Sizes of found directories from earlier scans, kept in decruft's data directory, so
+/// that a repeat scan of a mostly unchanged tree only walks what changed.
+///
+/// A cached size is used as long as the modification times of the directory and of its
+/// top-level subdirectories are what they were. Changes deeper down that leave those
+/// alone (rewriting a file in place two levels down, say) go unnoticed until something
+/// does touch them; `--no-cache` sizes everything from scratch. Ages aren't cached, as
+/// those changes are just what they are about, and sizes are taken again before they
+/// decide how a deletion is confirmed.
+pub struct SizeCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<PathBuf, CachedSize>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSize {
+    signature: u64,
+    size: DirSize,
+}
+
+impl SizeCache {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::data_dir().map(|dir| dir.join("size-cache.json"))
+    }
+
+    /// Loads the cache, starting afresh if it's missing or unreadable (it's only a cache)
+    pub fn load(path: PathBuf) -> Self {
+        let entries = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Writes the cache back, leaving out directories that have since gone away
+    pub fn save(&self) -> Result<()> {
+        let path = &self.path;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|path, _| path.is_dir());
+        let bytes = serde_json::to_vec(&*entries)?;
+        std::fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The cached size of `path`, if it looks unchanged since
+    pub fn get(&self, path: &Path, signature: u64) -> Option<DirSize> {
+        let entries = self.entries.lock().unwrap();
+        let cached = entries
+            .get(path)
+            .filter(|cached| cached.signature == signature)?;
+        Some(cached.size.as_cached())
+    }
+
+    pub fn insert(&self, path: &Path, signature: u64, size: &DirSize) {
+        self.entries.lock().unwrap().insert(
+            path.to_path_buf(),
+            CachedSize {
+                signature,
+                size: size.clone(),
+            },
+        );
+    }
+}
+
+/// What a cached size is only good for: the modification times of `path` and of its
+/// top-level subdirectories, which change whenever entries are added to or removed from them,
+/// and whatever else (like the options it was sized with) is in `variant`
+pub fn signature(path: &Path, variant: impl Hash) -> Option<u64> {
+    let mut hasher = Fnv1a::default();
+    variant.hash(&mut hasher);
+    std::fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .hash(&mut hasher);
+    let mut subdirs: Vec<(std::ffi::OsString, SystemTime)> = std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|entry| Some((entry.file_name(), entry.metadata().ok()?.modified().ok()?)))
+        .collect();
+    subdirs.sort();
+    subdirs.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// FNV-1a, for signatures that stay the same from one run (and Rust release) to the next,
+/// unlike those of `DefaultHasher`
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
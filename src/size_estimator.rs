@@ -0,0 +1,62 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A cheaper way than walking to learn the size of some kinds of directory, such as ones
+/// that keep track of it themselves. The scanner asks each estimator in turn before
+/// walking a found directory, and only walks it if none of them knows.
+pub trait SizeEstimator: Send + Sync {
+    /// Names the source of the size, as shown next to it
+    fn name(&self) -> &'static str;
+
+    /// The size of `path`, or `None` if this estimator doesn't know about such directories
+    /// (or couldn't find out, in which case walking it is the fallback)
+    fn estimate(&self, path: &Path) -> Option<Estimate>;
+}
+
+pub struct Estimate {
+    pub size: u64,
+    pub disk_usage: u64,
+}
+
+/// The estimators used unless `--exact-sizes` is given
+pub fn builtin() -> Vec<Box<dyn SizeEstimator>> {
+    vec![Box::new(GitObjects)]
+}
+
+/// Git repositories (bare ones, or `.git` directories) know what their objects take up,
+/// which is nearly all of their size: `git count-objects` reads it off the pack indexes.
+struct GitObjects;
+
+impl SizeEstimator for GitObjects {
+    fn name(&self) -> &'static str {
+        "git count-objects"
+    }
+
+    fn estimate(&self, path: &Path) -> Option<Estimate> {
+        if !path.join("HEAD").is_file() || !path.join("objects").is_dir() {
+            return None;
+        }
+        let output = Command::new("git")
+            .arg("--git-dir")
+            .arg(path)
+            .args(["count-objects", "-v"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // Sizes are in KiB, for loose objects, packs and garbage
+        let kib: u64 = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .filter(|(key, _)| matches!(*key, "size" | "size-pack" | "size-garbage"))
+            .filter_map(|(_, value)| value.trim().parse::<u64>().ok())
+            .sum();
+        Some(Estimate {
+            size: kib * 1024,
+            disk_usage: kib * 1024,
+        })
+    }
+}
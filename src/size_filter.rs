@@ -1,30 +1,68 @@
 use crate::cycle::Cycle;
 
+const MB: u64 = 1_048_576;
+
+/// The smallest entry shown, in bytes; 0 shows everything. `s` cycles through the steps,
+/// `+` and `-` go a step up or down, and `=` takes any other threshold, as does `--min-size`
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum SizeFilter {
-    ShowAll,
-    SkipSmall,
-}
+pub struct SizeFilter(pub u64);
 
 impl SizeFilter {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            SizeFilter::ShowAll => "all",
-            SizeFilter::SkipSmall => "skip small",
+    /// Where the TUI starts without `--min-size`
+    pub const DEFAULT: SizeFilter = SizeFilter(MB);
+
+    pub fn label(&self) -> String {
+        match self.0 {
+            0 => "all sizes".to_string(),
+            bytes => format!("≥ {}", format_threshold(bytes)),
         }
     }
 
     pub fn as_bytes(&self) -> u64 {
-        match self {
-            SizeFilter::ShowAll => 0,
-            SizeFilter::SkipSmall => 1_048_576, // 1 MB in bytes
+        self.0
+    }
+
+    /// The next step up from the threshold, or the threshold itself if it's past the last step
+    pub fn bigger(&self) -> Self {
+        Self::all_values()
+            .iter()
+            .find(|step| step.0 > self.0)
+            .copied()
+            .unwrap_or(*self)
+    }
+
+    /// The next step down from the threshold, down to showing everything
+    pub fn smaller(&self) -> Self {
+        Self::all_values()
+            .iter()
+            .rev()
+            .find(|step| step.0 < self.0)
+            .copied()
+            .unwrap_or(*self)
+    }
+}
+
+/// A size threshold the way it would have been typed (`1.5G`, `500M`)
+pub fn format_threshold(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["", "K", "M", "G"] {
+        if size < 1024.0 {
+            return format!("{}{}", (size * 10.0).round() / 10.0, unit);
         }
+        size /= 1024.0;
     }
+    format!("{}T", (size * 10.0).round() / 10.0)
 }
 
 impl Cycle for SizeFilter {
     fn all_values() -> &'static [Self] {
-        static ALL: [SizeFilter; 2] = [SizeFilter::ShowAll, SizeFilter::SkipSmall];
+        static ALL: [SizeFilter; 5] = [
+            SizeFilter(0),
+            SizeFilter(MB),
+            SizeFilter(10 * MB),
+            SizeFilter(100 * MB),
+            SizeFilter(1024 * MB),
+        ];
         &ALL
     }
 }
@@ -0,0 +1,47 @@
+use crate::cycle::Cycle;
+use crate::scanner::CruftDirectory;
+
+/// Which size is shown and sorted by: the summed file lengths, the space actually
+/// allocated on disk (which differs wildly from the former for sparse files and on
+/// compressed filesystems), or just the part of that which deleting would free.
+/// Hard-linked files are only counted once in all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMode {
+    Apparent,
+    DiskUsage,
+    /// Disk usage, minus files that are hard-linked from outside the directory
+    Unique,
+}
+
+impl SizeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SizeMode::Apparent => "size",
+            SizeMode::DiskUsage => "disk usage",
+            SizeMode::Unique => "unique size",
+        }
+    }
+
+    /// Picks the size to show for a breakdown entry; those only have disk usage, not unique size
+    pub fn pick(&self, size: u64, disk_usage: u64) -> u64 {
+        match self {
+            SizeMode::Apparent => size,
+            SizeMode::DiskUsage | SizeMode::Unique => disk_usage,
+        }
+    }
+
+    pub fn size_of(&self, dir: &CruftDirectory) -> u64 {
+        match self {
+            SizeMode::Apparent => dir.size,
+            SizeMode::DiskUsage => dir.disk_usage,
+            SizeMode::Unique => dir.unique_disk_usage,
+        }
+    }
+}
+
+impl Cycle for SizeMode {
+    fn all_values() -> &'static [Self] {
+        static ALL: [SizeMode; 3] = [SizeMode::Apparent, SizeMode::DiskUsage, SizeMode::Unique];
+        &ALL
+    }
+}
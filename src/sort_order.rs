@@ -1,54 +1,114 @@
+use crate::collation::Collation;
 use crate::cycle::Cycle;
 use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use std::cmp::Ordering;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortOrder {
-    SizeDescending,
-    AgeDescending,
+    Size,
+    Age,
     Trashiness,
     Alphabetical,
+    FileCount,
+    Depth,
 }
 
 impl Cycle for SortOrder {
     fn all_values() -> &'static [Self] {
-        static ALL: [SortOrder; 4] = [
-            SortOrder::SizeDescending,
-            SortOrder::AgeDescending,
+        static ALL: [SortOrder; 6] = [
+            SortOrder::Size,
+            SortOrder::Age,
             SortOrder::Trashiness,
             SortOrder::Alphabetical,
+            SortOrder::FileCount,
+            SortOrder::Depth,
         ];
         &ALL
     }
 }
 
+/// Which way round the list is sorted (`~`): each sort starts the way it's most useful,
+/// biggest (or oldest, or deepest) first, or A to Z for names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Descending,
+    Ascending,
+}
+
+impl Cycle for SortDirection {
+    fn all_values() -> &'static [Self] {
+        static ALL: [SortDirection; 2] = [SortDirection::Descending, SortDirection::Ascending];
+        &ALL
+    }
+}
+
 impl SortOrder {
     pub fn as_str(&self) -> &'static str {
         match self {
-            SortOrder::SizeDescending => "size",
-            SortOrder::AgeDescending => "age",
+            SortOrder::Size => "size",
+            SortOrder::Age => "age",
             SortOrder::Trashiness => "trashiness",
             SortOrder::Alphabetical => "name",
+            SortOrder::FileCount => "files",
+            SortOrder::Depth => "depth",
         }
     }
 
-    pub fn sort_entries(&self, entries: &mut [CruftDirectory]) {
+    /// The direction the list is sorted in when switching to this sort
+    pub fn default_direction(&self) -> SortDirection {
         match self {
-            SortOrder::SizeDescending => {
-                entries.sort_by(|a, b| b.size.cmp(&a.size));
-            }
-            SortOrder::AgeDescending => {
-                entries.sort_by(|a, b| {
-                    let age1 = b.newest_file_age_days.unwrap_or(0.0);
-                    let age2 = a.newest_file_age_days.unwrap_or(0.0);
-                    age1.total_cmp(&age2)
-                });
-            }
-            SortOrder::Trashiness => {
-                entries.sort_by(|a, b| b.trashiness().total_cmp(&a.trashiness()));
-            }
-            SortOrder::Alphabetical => {
-                entries.sort_by(|a, b| a.path.to_string_lossy().cmp(&b.path.to_string_lossy()));
+            SortOrder::Alphabetical => SortDirection::Ascending,
+            _ => SortDirection::Descending,
+        }
+    }
+
+    /// What comes first, as in "sort: size, biggest first"
+    pub fn describe(&self, direction: SortDirection) -> &'static str {
+        let (descending, ascending) = match self {
+            SortOrder::Size => ("biggest first", "smallest first"),
+            SortOrder::Age => ("oldest first", "newest first"),
+            SortOrder::Trashiness => ("trashiest first", "least trashy first"),
+            SortOrder::Alphabetical => ("Z to A", "A to Z"),
+            SortOrder::FileCount => ("most files first", "fewest files first"),
+            SortOrder::Depth => ("deepest first", "shallowest first"),
+        };
+        match direction {
+            SortDirection::Descending => descending,
+            SortDirection::Ascending => ascending,
+        }
+    }
+
+    pub fn sort_entries(
+        &self,
+        direction: SortDirection,
+        entries: &mut [&CruftDirectory],
+        collation: Collation,
+        size_mode: SizeMode,
+    ) {
+        // In ascending order; entries not counted or sized yet go with the smallest
+        let compare = |a: &CruftDirectory, b: &CruftDirectory| -> Ordering {
+            match self {
+                SortOrder::Size => size_mode.size_of(a).cmp(&size_mode.size_of(b)),
+                SortOrder::Age => a
+                    .newest_file_age_days
+                    .unwrap_or(0.0)
+                    .total_cmp(&b.newest_file_age_days.unwrap_or(0.0)),
+                SortOrder::Trashiness => a.trashiness().total_cmp(&b.trashiness()),
+                SortOrder::Alphabetical => {
+                    collation.compare(&a.path.to_string_lossy(), &b.path.to_string_lossy())
+                }
+                SortOrder::FileCount => a.file_count.unwrap_or(0).cmp(&b.file_count.unwrap_or(0)),
+                SortOrder::Depth => a
+                    .path
+                    .components()
+                    .count()
+                    .cmp(&b.path.components().count()),
             }
+        };
+        match direction {
+            SortDirection::Ascending => entries.sort_by(|a, b| compare(a, b)),
+            SortDirection::Descending => entries.sort_by(|a, b| compare(b, a)),
         }
     }
 }
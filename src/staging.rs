@@ -0,0 +1,344 @@
+use crate::archive;
+use crate::audit::AuditLog;
+use crate::delete;
+use crate::scanner::{self, CruftDirectory, CruftyReason};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where the TUI moves what it deletes (`pending/<session>` in decruft's data directory),
+/// so that a mis-delete can be undone until the staged directories are purged, which
+/// happens on `P` or when the TUI exits.
+///
+/// Staging is a rename, so it only works for directories on the same filesystem as the
+/// data directory; anything else is deleted straight away, which its confirmation says. Next to each staged directory
+/// is a `.json` manifest saying where it came from, so that what a crashed session leaves
+/// behind can still be listed and restored.
+pub struct Staging {
+    root: PathBuf,
+    dir: PathBuf,
+    staged: Vec<Staged>,
+    next_id: u64,
+}
+
+/// A directory that was moved into the staging area
+pub struct Staged {
+    /// As it was found, at its original path
+    pub dir: CruftDirectory,
+    pub at: PathBuf,
+    /// Where it was archived before it was staged, if it was; undoing makes that unneeded
+    archive: Option<PathBuf>,
+}
+
+impl Staged {
+    /// The entry with its path in the staging area, so that it can go through the usual deletion
+    fn into_entry(self) -> CruftDirectory {
+        CruftDirectory {
+            staged_from: Some(self.dir.path.clone()),
+            path: self.at,
+            ..self.dir
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    path: PathBuf,
+    reason: String,
+    size: u64,
+    /// Seconds since the epoch
+    staged_at: u64,
+}
+
+/// Something in the staging area, from this session or an earlier one, as listed for restoring
+#[derive(Clone)]
+pub struct StagedItem {
+    pub original: PathBuf,
+    pub at: PathBuf,
+    pub reason: String,
+    pub size: u64,
+    pub staged_at: SystemTime,
+    /// Staged by this session and still to be purged when it ends; the rest (from earlier
+    /// sessions, or failed purges) stay until restored
+    pub this_session: bool,
+}
+
+impl Staging {
+    pub fn default_root() -> Option<PathBuf> {
+        crate::app_dirs::data_dir().map(|dir| dir.join("pending"))
+    }
+
+    /// A staging area of this session's own under `root`
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dir: root.join(std::process::id().to_string()),
+            staged: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Whether `dir` can be moved in, rather than deleted for good: not if it's scattered
+    /// about, or on another filesystem
+    pub fn can_hold(&self, dir: &CruftDirectory) -> bool {
+        dir.members.is_empty() && same_filesystem(&dir.path, &self.root)
+    }
+
+    /// Where the next directory to be staged goes, for `stage`
+    pub fn next_slot(&mut self) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let at = self.dir.join(self.next_id.to_string());
+        self.next_id += 1;
+        Ok(at)
+    }
+
+    /// Takes in a directory `stage` has moved into this session's staging area
+    pub fn add(&mut self, staged: Staged) {
+        self.staged.push(staged);
+    }
+
+    /// Moves the most recently staged directory back to where it was, returning it
+    pub fn undo(&mut self, audit_log: Option<&AuditLog>) -> Option<Result<CruftDirectory>> {
+        let staged = self.staged.pop()?;
+        Some(self.restore(staged, audit_log))
+    }
+
+    fn restore(&mut self, staged: Staged, audit_log: Option<&AuditLog>) -> Result<CruftDirectory> {
+        let original = &staged.dir.path;
+        if original.symlink_metadata().is_ok() {
+            let message = format!(
+                "Cannot restore {}: something else is there now",
+                original.display()
+            );
+            self.staged.push(staged);
+            anyhow::bail!(message);
+        }
+        if let Err(e) = std::fs::rename(&staged.at, original) {
+            let message = format!("Failed to restore {}", original.display());
+            self.staged.push(staged);
+            return Err(e).context(message);
+        }
+        let _ = std::fs::remove_file(manifest_path(&staged.at));
+        if let Some(archive) = &staged.archive {
+            let _ = std::fs::remove_file(archive);
+        }
+        if let Some(audit_log) = audit_log {
+            audit_log.record("restore", &staged.dir)?;
+        }
+        Ok(staged.dir)
+    }
+
+    /// Everything in the staging area, this session's and what earlier ones left behind,
+    /// most recently staged first
+    pub fn list(&self) -> Vec<StagedItem> {
+        let mut items = Vec::new();
+        let sessions = std::fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten();
+        for session in sessions {
+            let manifests = std::fs::read_dir(session.path())
+                .into_iter()
+                .flatten()
+                .flatten();
+            for entry in manifests {
+                let path = entry.path();
+                if path.extension().is_none_or(|extension| extension != "json") {
+                    continue;
+                }
+                let Some(manifest) = std::fs::read(&path)
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<Manifest>(&bytes).ok())
+                else {
+                    continue;
+                };
+                let at = path.with_extension("");
+                if !at.is_dir() {
+                    continue;
+                }
+                items.push(StagedItem {
+                    this_session: self.staged.iter().any(|staged| staged.at == at),
+                    original: manifest.path,
+                    at,
+                    reason: manifest.reason,
+                    size: manifest.size,
+                    staged_at: UNIX_EPOCH + Duration::from_secs(manifest.staged_at),
+                });
+            }
+        }
+        items.sort_by_key(|item| std::cmp::Reverse(item.staged_at));
+        items
+    }
+
+    /// Moves a listed item back to where it came from. Items of this session come back as
+    /// the entries they were; for ones from earlier sessions, only the path is known.
+    pub fn restore_item(
+        &mut self,
+        item: &StagedItem,
+        audit_log: Option<&AuditLog>,
+    ) -> Result<Option<CruftDirectory>> {
+        if let Some(i) = self.staged.iter().position(|staged| staged.at == item.at) {
+            let staged = self.staged.remove(i);
+            return self.restore(staged, audit_log).map(Some);
+        }
+        if item.original.symlink_metadata().is_ok() {
+            anyhow::bail!(
+                "Cannot restore {}: something else is there now",
+                item.original.display()
+            );
+        }
+        std::fs::rename(&item.at, &item.original)
+            .with_context(|| format!("Failed to restore {}", item.original.display()))?;
+        let _ = std::fs::remove_file(manifest_path(&item.at));
+        if let Some(audit_log) = audit_log {
+            audit_log.record_other("restore", &item.reason, &item.original, item.size)?;
+        }
+        Ok(None)
+    }
+
+    /// Hands over a listed item for deleting for good, as `take_for_purge` does for all of
+    /// this session's: so that what earlier sessions left behind doesn't pile up forever
+    pub fn take_item_for_purge(&mut self, item: &StagedItem) -> Result<CruftDirectory> {
+        if let Some(i) = self.staged.iter().position(|staged| staged.at == item.at) {
+            return Ok(self.staged.remove(i).into_entry());
+        }
+        let reason = CruftyReason::from_label(&item.reason).with_context(|| {
+            format!(
+                "Cannot purge {}: {:?} isn't a kind of cruft decruft knows",
+                item.at.display(),
+                item.reason
+            )
+        })?;
+        Ok(scanner::staged_entry(
+            &item.at,
+            &item.original,
+            reason,
+            item.size,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// The total size of what's staged, as it was found
+    pub fn size(&self) -> u64 {
+        self.staged.iter().map(|staged| staged.dir.size).sum()
+    }
+
+    /// Hands over what's staged for deleting for good. Their manifests stay until
+    /// `purged` says they're gone, so that what fails to be deleted can still be listed.
+    pub fn take_for_purge(&mut self) -> Vec<CruftDirectory> {
+        std::mem::take(&mut self.staged)
+            .into_iter()
+            .map(Staged::into_entry)
+            .collect()
+    }
+
+    /// Deletes everything staged, and the session's staging area with it, carrying on past
+    /// failures; what couldn't be deleted stays listed, to be restored or purged later
+    pub fn purge(mut self, audit_log: Option<&AuditLog>) -> Result<()> {
+        let dirs = self.take_for_purge();
+        let mut failures = Vec::new();
+        for dir in &dirs {
+            match delete::delete_entry(dir, audit_log) {
+                Ok(()) => purged(&dir.path),
+                Err(e) => failures.push(format!("{:#}", e)),
+            }
+        }
+        // Only empty if nothing was left behind
+        let _ = std::fs::remove_dir(&self.dir);
+        match failures.first() {
+            None => Ok(()),
+            Some(first) => anyhow::bail!(
+                "{} of {} could not be deleted, e.g. {}",
+                failures.len(),
+                dirs.len(),
+                first
+            ),
+        }
+    }
+}
+
+/// Forgets a directory that was purged from the staging area at `at`, now that it's gone,
+/// along with the session's staging area if that was the last of it
+pub fn purged(at: &Path) {
+    let _ = std::fs::remove_file(manifest_path(at));
+    if let Some(session) = at.parent() {
+        let _ = std::fs::remove_dir(session);
+    }
+}
+
+/// Moves `dir` into the staging area at `at` (from `Staging::next_slot`), after making sure
+/// it is still the directory that was scanned, and archiving it if it's of a kind that is.
+/// That can take a while for a big tree, so the deletion worker does it rather than the UI.
+/// What can't be moved there is left alone: it was confirmed as something to be undone.
+pub fn stage(dir: &CruftDirectory, at: &Path, audit_log: Option<&AuditLog>) -> Result<Staged> {
+    delete::check_unchanged(dir)?;
+    let archive = archive::archive_if_wanted(dir, audit_log)?;
+    let origin = manifest_path(at);
+    let manifest = Manifest {
+        path: dir.path.clone(),
+        reason: dir.crufty_reason.to_string(),
+        size: dir.size,
+        staged_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+    };
+    std::fs::write(&origin, serde_json::to_vec(&manifest)?)
+        .with_context(|| format!("Failed to write {}", origin.display()))?;
+    match std::fs::rename(&dir.path, at) {
+        Ok(()) => {}
+        Err(e) => {
+            let _ = std::fs::remove_file(&origin);
+            if let Some(archive) = &archive {
+                let _ = std::fs::remove_file(archive);
+            }
+            // Bind mounts of the same filesystem can't be renamed across either
+            if e.kind() == std::io::ErrorKind::CrossesDevices {
+                anyhow::bail!(
+                    "{} can't be moved into the staging area, so can't be undone; not deleted",
+                    dir.path.display()
+                );
+            }
+            return Err(e).with_context(|| format!("Failed to move {}", dir.path.display()));
+        }
+    }
+    if let Some(audit_log) = audit_log {
+        audit_log.record_path("stage", dir, at, dir.size)?;
+    }
+    Ok(Staged {
+        dir: dir.clone(),
+        at: at.to_path_buf(),
+        archive,
+    })
+}
+
+/// Whether `path` is on the same filesystem as `dir` (or, as that may not have been
+/// created yet, the nearest directory above it that has)
+#[cfg(unix)]
+fn same_filesystem(path: &Path, dir: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let existing = dir.ancestors().find_map(|dir| dir.metadata().ok());
+    match (path.symlink_metadata(), existing) {
+        (Ok(metadata), Some(existing)) => metadata.dev() == existing.dev(),
+        _ => false,
+    }
+}
+
+/// Whether `path` is on the same drive as `dir`
+#[cfg(not(unix))]
+fn same_filesystem(path: &Path, dir: &Path) -> bool {
+    path.components().next() == dir.components().next()
+}
+
+/// Where the manifest of a staged directory is: next to it, as `<id>.json`
+fn manifest_path(at: &Path) -> PathBuf {
+    at.with_extension("json")
+}
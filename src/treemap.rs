@@ -0,0 +1,218 @@
+use crate::scanner::CruftDirectory;
+use crate::size_mode::SizeMode;
+use crate::units::format_size;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+
+/// Colours for the kinds of cruft, handed out in order of name
+const PALETTE: [Color; 10] = [
+    Color::Green,
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Red,
+    Color::LightGreen,
+    Color::LightCyan,
+    Color::LightMagenta,
+    Color::LightBlue,
+];
+
+/// Terminal cells are about twice as tall as they are wide, so a square is twice as many
+/// columns as rows
+const CELL_ASPECT: f64 = 2.0;
+
+/// A rectangle in columns and (aspect-corrected) rows, before rounding to cells
+#[derive(Clone, Copy)]
+struct Area {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// The treemap screen: a rectangle per entry shown, its area in proportion to its size and
+/// its colour by its kind, with a legend of the kinds along the bottom. `selected` (an index
+/// into `dirs`, as in the list) is highlighted.
+pub fn draw(
+    f: &mut Frame,
+    area: Rect,
+    dirs: &[&CruftDirectory],
+    selected: Option<usize>,
+    size_mode: SizeMode,
+) {
+    if area.height < 2 || area.width == 0 {
+        return;
+    }
+    let mut reasons: Vec<String> = dirs
+        .iter()
+        .map(|dir| dir.crufty_reason.to_string())
+        .collect();
+    reasons.sort();
+    reasons.dedup();
+    let color_of = |dir: &CruftDirectory| {
+        let reason = dir.crufty_reason.to_string();
+        let i = reasons.binary_search(&reason).unwrap_or(0);
+        PALETTE[i % PALETTE.len()]
+    };
+
+    let map_area = Rect {
+        height: area.height - 1,
+        ..area
+    };
+    // Biggest first, which is what squarifying wants; empty ones would have no area anyway
+    let mut order: Vec<usize> = (0..dirs.len())
+        .filter(|&i| size_mode.size_of(dirs[i]) > 0)
+        .collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(size_mode.size_of(dirs[i])));
+    let sizes: Vec<f64> = order
+        .iter()
+        .map(|&i| size_mode.size_of(dirs[i]) as f64)
+        .collect();
+    let rects = squarify(
+        &sizes,
+        Area {
+            x: 0.0,
+            y: 0.0,
+            w: map_area.width as f64,
+            h: map_area.height as f64 * CELL_ASPECT,
+        },
+    );
+
+    let buf = f.buffer_mut();
+    for (&i, rect) in order.iter().zip(rects) {
+        let dir = dirs[i];
+        let x0 = map_area.x + rect.x.round() as u16;
+        let x1 = map_area.x + (rect.x + rect.w).round() as u16;
+        let y0 = map_area.y + (rect.y / CELL_ASPECT).round() as u16;
+        let y1 = map_area.y + ((rect.y + rect.h) / CELL_ASPECT).round() as u16;
+        if x1 <= x0 || y1 <= y0 {
+            // Too small to show; still in the list, and reachable with j/k
+            continue;
+        }
+        let is_selected = selected == Some(i);
+        let color = if is_selected {
+            Color::White
+        } else {
+            color_of(dir)
+        };
+        // The last column and row are left blank (where there's room), to tell neighbours apart
+        let fill_x1 = if x1 - x0 > 1 { x1 - 1 } else { x1 };
+        let fill_y1 = if y1 - y0 > 1 { y1 - 1 } else { y1 };
+        for y in y0..fill_y1 {
+            for x in x0..fill_x1 {
+                buf[(x, y)].set_char('█').set_fg(color);
+            }
+        }
+        let name = dir
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let label = format!(
+            "{}/{} {}",
+            dir.project_name().unwrap_or_default(),
+            name,
+            format_size(size_mode.size_of(dir))
+        );
+        let mut style = Style::default().fg(Color::Black).bg(color);
+        if is_selected {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        buf.set_stringn(x0, y0, &label, (fill_x1 - x0) as usize, style);
+    }
+
+    let legend: Vec<Span> = reasons
+        .iter()
+        .enumerate()
+        .flat_map(|(i, reason)| {
+            [
+                Span::styled("█ ", Style::default().fg(PALETTE[i % PALETTE.len()])),
+                Span::raw(format!("{}  ", reason)),
+            ]
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(Line::from(legend)),
+        Rect {
+            y: area.y + area.height - 1,
+            height: 1,
+            ..area
+        },
+    );
+}
+
+/// Splits `area` into a rectangle per size (biggest first), in proportion to the sizes and
+/// as close to square as it goes: the squarified layout of Bruls, Huizing and van Wijk.
+/// Rows are filled along the shorter side of what's left, as long as adding another
+/// rectangle doesn't make the row's most elongated one worse.
+fn squarify(sizes: &[f64], area: Area) -> Vec<Area> {
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let scale = area.w * area.h / total;
+    let areas: Vec<f64> = sizes.iter().map(|size| size * scale).collect();
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut rest = area;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = rest.w.min(rest.h);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..=end], side) <= worst(&areas[start..end], side)
+        {
+            end += 1;
+        }
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if rest.w >= rest.h {
+            // A column down the left of what's left
+            let width = if rest.h > 0.0 { row_area / rest.h } else { 0.0 };
+            let mut y = rest.y;
+            for cell in row {
+                let height = if width > 0.0 { cell / width } else { 0.0 };
+                rects.push(Area {
+                    x: rest.x,
+                    y,
+                    w: width,
+                    h: height,
+                });
+                y += height;
+            }
+            rest.x += width;
+            rest.w -= width;
+        } else {
+            // A row along the top of what's left
+            let height = if rest.w > 0.0 { row_area / rest.w } else { 0.0 };
+            let mut x = rest.x;
+            for cell in row {
+                let width = if height > 0.0 { cell / height } else { 0.0 };
+                rects.push(Area {
+                    x,
+                    y: rest.y,
+                    w: width,
+                    h: height,
+                });
+                x += width;
+            }
+            rest.y += height;
+            rest.h -= height;
+        }
+        start = end;
+    }
+    rects
+}
+
+/// How far from square the most elongated rectangle is when `row` is laid along `side`
+fn worst(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side = side * side;
+    let sum = sum * sum;
+    (side * max / sum).max(sum / (side * min))
+}
@@ -0,0 +1,98 @@
+use crate::cycle::Cycle;
+use crate::scanner::{CruftDirectory, CruftyReason};
+use std::collections::HashSet;
+
+/// Which kinds of cruft are shown (`a`): all of them, or only the common ones. Kinds can
+/// further be hidden one by one with the picker (`A`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeFilter {
+    All,
+    Common,
+}
+
+impl Cycle for TypeFilter {
+    fn all_values() -> &'static [Self] {
+        static ALL: [TypeFilter; 2] = [TypeFilter::All, TypeFilter::Common];
+        &ALL
+    }
+}
+
+impl TypeFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TypeFilter::All => "all types",
+            TypeFilter::Common => "common types",
+        }
+    }
+
+    pub fn admits(&self, reason: &CruftyReason, hidden: &HashSet<CruftyReason>) -> bool {
+        if hidden.contains(reason) {
+            return false;
+        }
+        match self {
+            TypeFilter::All => true,
+            TypeFilter::Common => reason.is_common_cruft(),
+        }
+    }
+}
+
+/// The picker for hiding kinds of cruft one by one (`A`): each kind found so far, how many
+/// entries it has, and whether it's to be shown. Nothing changes until it's applied.
+pub struct TypePicker {
+    pub types: Vec<(CruftyReason, usize, bool)>,
+    pub selected: usize,
+}
+
+impl TypePicker {
+    pub fn new(dirs: &[CruftDirectory], hidden: &HashSet<CruftyReason>) -> Self {
+        let mut types: Vec<(CruftyReason, usize, bool)> = Vec::new();
+        for dir in dirs {
+            match types
+                .iter_mut()
+                .find(|(reason, _, _)| *reason == dir.crufty_reason)
+            {
+                Some((_, count, _)) => *count += 1,
+                None => types.push((
+                    dir.crufty_reason.clone(),
+                    1,
+                    !hidden.contains(&dir.crufty_reason),
+                )),
+            }
+        }
+        // Kinds hidden earlier stay in the picker even if none of them are found any more
+        for reason in hidden {
+            if !types.iter().any(|(r, _, _)| r == reason) {
+                types.push((reason.clone(), 0, false));
+            }
+        }
+        types.sort_by_key(|(reason, _, _)| reason.to_string());
+        Self { types, selected: 0 }
+    }
+
+    pub fn select_next_or_previous(&mut self, next: bool) {
+        let count = self.types.len();
+        if count == 0 {
+            return;
+        }
+        self.selected = if next {
+            (self.selected + 1) % count
+        } else {
+            (self.selected + count - 1) % count
+        };
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some((_, _, shown)) = self.types.get_mut(self.selected) {
+            *shown = !*shown;
+        }
+    }
+
+    /// The kinds to hide
+    pub fn hidden(&self) -> HashSet<CruftyReason> {
+        self.types
+            .iter()
+            .filter(|(_, _, shown)| !shown)
+            .map(|(reason, _, _)| reason.clone())
+            .collect()
+    }
+}
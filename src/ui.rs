@@ -1,30 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use crate::action_menu::{self, ActionMenu};
 use crate::age_filter::AgeFilter;
+use crate::audit::AuditLog;
+use crate::browser::Browser;
+use crate::collation::Collation;
+use crate::confidence::Confidence;
+use crate::confirm_policy::{ConfirmPolicy, Confirmation};
 use crate::cycle::Cycle;
-use crate::scanner::CruftDirectory;
-use crate::size_filter::SizeFilter;
-use crate::sort_order::SortOrder;
+use crate::dashboard;
+use crate::delete;
+use crate::delete_worker::{DeleteWorker, Done, Progress};
+use crate::frame_stats::FrameStats;
+use crate::git_status::{self, TrackedFiles};
+use crate::grouping::{self, Grouping, Row};
+use crate::help;
+use crate::history;
+use crate::inspector::Inspector;
+use crate::keymap::{Command, keymap};
+use crate::metadata::{IgnorePattern, Metadata};
+use crate::metrics::ScanMetrics;
+use crate::open_files::{self, OpenFiles};
+use crate::ownership;
+use crate::preview::{Facts, Previews};
+use crate::rebuild::RebuildHints;
+use crate::scanner::{
+    BackgroundScan, CruftDirectory, CruftyReason, OWN_FILES_LABEL, ScanResults, SlowSubtree,
+};
+use crate::scope::Scope;
+use crate::screen::Screen;
+use crate::search::SearchQuery;
+use crate::size_filter::{SizeFilter, format_threshold};
+use crate::size_mode::SizeMode;
+use crate::sort_order::{SortDirection, SortOrder};
+use crate::staging::{self, StagedItem, Staging};
+use crate::treemap;
+use crate::type_filter::{TypeFilter, TypePicker};
+use crate::units::format_size;
+use crate::watch::Watch;
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use ratatui::Terminal;
 use ratatui::backend::Backend;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::widgets::{
+    Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+    ScrollbarState,
+};
+use regex::Regex;
+
+pub struct UiOptions {
+    pub owner_filter: Option<u32>,
+    /// Root safety profile: typed confirmations only
+    pub root_profile: bool,
+    pub audit_log: Option<AuditLog>,
+    pub collation: Collation,
+    /// How much confirmation each deletion needs
+    pub confirm_policy: ConfirmPolicy,
+    pub rebuild_hints: RebuildHints,
+    pub size_mode: SizeMode,
+    /// The size and age filters to start with (`--min-size`, `--older-than`)
+    pub size_filter: SizeFilter,
+    pub age_filter: AgeFilter,
+    /// Entries with fewer files than this are left out (`--min-files`)
+    pub min_files: Option<u64>,
+    /// Only paths matching this are shown (`--filter-regex`)
+    pub path_regex: Option<Regex>,
+    pub metadata: Metadata,
+    /// Where changes to the metadata are saved, if anywhere
+    pub metadata_path: Option<PathBuf>,
+    /// What the run is set up to do, shown until dismissed
+    pub scope: Scope,
+    /// Where deleted entries go until the session ends, to be undone; `None` deletes right away
+    pub staging: Option<Staging>,
+    /// No animations; progress is only shown as text
+    pub reduced_motion: bool,
+    /// No decorations that don't carry information, to keep frame diffs small
+    pub low_bandwidth: bool,
+    /// How long to wait for a key press before redrawing
+    pub poll_interval: Duration,
+    /// Start with the preview pane open
+    pub preview: bool,
+    /// Bytes written to the terminal since the last frame was accounted for
+    pub bytes_written: Arc<AtomicU64>,
+    pub scan_metrics: Arc<ScanMetrics>,
+}
 
 pub struct AppState {
     pub list_state: ListState,
     pub selected_path: Option<String>,
     pub confirm_delete: Option<String>, // Path of directory to delete, if confirmation is pending
+    pub confirm_typed: bool,            // Whether the pending deletion must be confirmed by typing
+    pub confirm_input: String,          // Typed confirmation text
+    pub confirm_warning: Option<String>, // Shown with the pending confirmation, like what uses it
+    pub status_message: Option<String>,
     pub age_filter: AgeFilter,
+    pub min_files: Option<u64>,
+    pub min_confidence: Confidence,
+    pub type_filter: TypeFilter,
+    pub hidden_types: HashSet<CruftyReason>, // Kinds of cruft hidden with the picker
+    pub type_picker: Option<TypePicker>,     // Open while picking kinds of cruft to hide
     pub sort_order: SortOrder,
+    pub sort_direction: SortDirection,
+    pub collation: Collation,
     pub size_filter: SizeFilter,
+    pub size_mode: SizeMode,
     pub scan_complete: bool,
     pub spinner_frame: usize, // For animation
+    pub show_ownership: bool,
+    pub deletable_only: bool,
+    pub owner_filter: Option<u32>,
+    pub expanded: HashSet<String>, // Entries whose size breakdown is shown
+    pub search_input: Option<String>, // Search prompt text, while the prompt is open
+    pub search: Option<SearchQuery>,
+    pub path_regex: Option<Regex>,   // Only paths matching it are shown
+    pub regex_input: Option<String>, // Regex prompt text, while the prompt is open
+    pub age_input: Option<String>,   // Age threshold prompt text, while the prompt is open
+    pub min_size_input: Option<String>, // Size threshold prompt text, while the prompt is open
+    pub min_files_input: Option<String>, // File count threshold prompt text, while the prompt is open
+    pub show_possible: bool,             // Whether the "possible cruft" section is expanded
+    pub metadata: Metadata,
+    pub show_ignored: bool,
+    pub note_input: Option<String>, // Note being edited for the selected entry
+    pub show_debug: bool,
+    pub frame_stats: FrameStats,
+    pub action_menu: Option<ActionMenu>, // Open for the selected entry
+    pub ignore_similar: Option<(Vec<IgnorePattern>, usize)>, // Patterns on offer, and the chosen one
+    pub error_scroll: Option<usize>, // How far the error panel is scrolled, while it's open
+    pub help_scroll: Option<usize>,  // How far the help is scrolled, while it's open
+    pub inspector: Option<Inspector>, // Open for the entry it inspects
+    pub browser: Option<Browser>,    // Open for the entry being browsed
+    pub browsed: HashMap<PathBuf, Browser>, // Closed browsers, to reopen without sizing again
+    pub show_preview: bool,          // The preview pane is open beside the list
+    pub grouping: Grouping,
+    pub collapsed: HashSet<String>, // Groups whose entries are hidden under their header
+    pub selected_group: Option<String>, // The group whose header is selected, if one is
+    pub previews: Previews,
+    pub quit_warned: bool, // Told that quitting now leaves deletions unfinished
+    pub freed: Vec<CruftDirectory>, // Deleted for good this session, for the tally in the header
+    pub show_scope: bool,  // The scope panel is open
+    pub history_view: Option<Vec<String>>, // The history screen's lines, while it's open
+    pub screen: Screen,    // What's shown below the header
+    pub page_rows: usize,  // How many rows of the list fit on screen, for paging
+    pub confirm_delete_all: Option<BulkDelete>, // Everything shown, if deleting it is to be confirmed
+    pub offered_excludes: Vec<SlowSubtree>, // Slow subtrees without cruft; `E` excludes the first
+    pub staged_view: Option<(Vec<StagedItem>, usize)>, // What's in the staging area, and the chosen one
+    pub confirm_purge: Option<Purge>, // What's about to be purged from the staging area, once confirmed
+}
+
+/// What `P` (or `d` in the list of deleted items) is about to delete for good
+pub struct Purge {
+    /// The one item chosen, or everything this session deleted if none was
+    pub item: Option<StagedItem>,
+    pub count: usize,
+    pub size: u64,
+}
+
+/// The entries `D` is about to delete: everything shown that can be deleted without further choices
+pub struct BulkDelete {
+    pub ids: Vec<String>,
+    pub total_size: u64,
+    /// The group being deleted, if it's a group rather than everything shown
+    pub group: Option<String>,
+    /// Shown, but left out: kept, not sized yet, found through a symlink, or already being deleted
+    pub skipped: usize,
 }
 
 impl AppState {
@@ -33,16 +178,72 @@ impl AppState {
             list_state: ListState::default(),
             selected_path: None,
             confirm_delete: None,
-            size_filter: SizeFilter::SkipSmall,
+            confirm_typed: false,
+            confirm_input: String::new(),
+            status_message: None,
+            size_filter: SizeFilter::DEFAULT,
+            size_mode: SizeMode::Apparent,
             age_filter: AgeFilter::None,
-            sort_order: SortOrder::SizeDescending,
+            min_files: None,
+            min_confidence: Confidence::Guess,
+            type_filter: TypeFilter::All,
+            hidden_types: HashSet::new(),
+            type_picker: None,
+            sort_order: SortOrder::Size,
+            sort_direction: SortDirection::Descending,
+            collation: Collation::Natural,
             scan_complete: false,
             spinner_frame: 0,
+            show_ownership: false,
+            deletable_only: false,
+            owner_filter: None,
+            expanded: HashSet::new(),
+            search_input: None,
+            search: None,
+            path_regex: None,
+            regex_input: None,
+            age_input: None,
+            min_size_input: None,
+            min_files_input: None,
+            show_possible: false,
+            metadata: Metadata::default(),
+            show_ignored: false,
+            note_input: None,
+            show_debug: false,
+            frame_stats: FrameStats::default(),
+            action_menu: None,
+            ignore_similar: None,
+            error_scroll: None,
+            help_scroll: None,
+            inspector: None,
+            browser: None,
+            browsed: HashMap::new(),
+            show_preview: false,
+            grouping: Grouping::None,
+            collapsed: HashSet::new(),
+            selected_group: None,
+            previews: Previews::default(),
+            quit_warned: false,
+            freed: Vec::new(),
+            show_scope: true,
+            history_view: None,
+            screen: Screen::List,
+            page_rows: 0,
+            confirm_warning: None,
+            confirm_delete_all: None,
+            offered_excludes: Vec::new(),
+            staged_view: None,
+            confirm_purge: None,
         }
     }
 
     pub fn toggle_sort_order(&mut self) {
         self.sort_order = self.sort_order.next();
+        self.sort_direction = self.sort_order.default_direction();
+    }
+
+    pub fn toggle_sort_direction(&mut self) {
+        self.sort_direction = self.sort_direction.next();
     }
 
     pub fn update_spinner(&mut self) {
@@ -53,152 +254,849 @@ impl AppState {
         self.scan_complete = true;
     }
 
-    pub fn toggle_skip_small(&mut self) {
+    pub fn toggle_min_size(&mut self) {
         self.size_filter = self.size_filter.next();
     }
 
+    pub fn raise_min_size(&mut self) {
+        self.size_filter = self.size_filter.bigger();
+    }
+
+    pub fn lower_min_size(&mut self) {
+        self.size_filter = self.size_filter.smaller();
+    }
+
+    pub fn toggle_size_mode(&mut self) {
+        self.size_mode = self.size_mode.next();
+    }
+
     pub fn toggle_old_dirs(&mut self) {
         self.age_filter = self.age_filter.next();
     }
 
-    pub fn request_delete_confirmation(&mut self, path: String) {
-        self.confirm_delete = Some(path);
+    pub fn toggle_min_confidence(&mut self) {
+        self.min_confidence = self.min_confidence.next();
     }
 
-    pub fn cancel_delete_confirmation(&mut self) {
-        self.confirm_delete = None;
+    pub fn toggle_type_filter(&mut self) {
+        self.type_filter = self.type_filter.next();
     }
 
-    pub fn select_next_or_previous(&mut self, filtered_dirs: &[CruftDirectory], forward: bool) {
-        if filtered_dirs.is_empty() {
+    pub fn start_type_picker(&mut self, dirs: &[CruftDirectory]) {
+        self.type_picker = Some(TypePicker::new(dirs, &self.hidden_types));
+    }
+
+    pub fn submit_type_picker(&mut self) {
+        if let Some(picker) = self.type_picker.take() {
+            self.hidden_types = picker.hidden();
+        }
+    }
+
+    pub fn toggle_ownership_columns(&mut self) {
+        self.show_ownership = !self.show_ownership;
+    }
+
+    pub fn toggle_deletable_only(&mut self) {
+        self.deletable_only = !self.deletable_only;
+    }
+
+    pub fn toggle_breakdown(&mut self) {
+        if let Some(ref selected_path) = self.selected_path
+            && !self.expanded.remove(selected_path)
+        {
+            self.expanded.insert(selected_path.clone());
+        }
+    }
+
+    pub fn toggle_possible_cruft(&mut self) {
+        self.show_possible = !self.show_possible;
+    }
+
+    pub fn toggle_show_ignored(&mut self) {
+        self.show_ignored = !self.show_ignored;
+    }
+
+    /// Ignores (or un-ignores) the selected entry
+    pub fn toggle_ignore_selected(&mut self, filtered_dirs: &[&CruftDirectory]) {
+        let Some(dir) = self.selected(filtered_dirs) else {
+            return;
+        };
+        let removed = self.metadata.toggle_ignore(dir);
+        if !removed.is_empty() {
+            let removed: Vec<String> = removed.iter().map(|pattern| pattern.describe()).collect();
+            self.status_message = Some(format!("No longer ignoring {}", removed.join("; ")));
+        } else if self.metadata.is_ignored(dir) && !self.show_ignored {
+            self.status_message = Some(format!(
+                "Ignored {}; press {} to show ignored entries",
+                dir.path.display(),
+                keymap().key_for(Command::ShowIgnored)
+            ));
+        }
+    }
+
+    /// Offers to ignore everything like the selected entry, generalized in a few ways
+    pub fn start_ignore_similar(&mut self, filtered_dirs: &[&CruftDirectory], root: &Path) {
+        if let Some(dir) = self.selected(filtered_dirs) {
+            self.ignore_similar = Some((IgnorePattern::suggestions(dir, root), 0));
+        }
+    }
+
+    pub fn submit_ignore_similar(&mut self, dirs: &[CruftDirectory]) {
+        let Some((mut patterns, selected)) = self.ignore_similar.take() else {
+            return;
+        };
+        let pattern = patterns.swap_remove(selected);
+        let n_hidden = count_newly_ignored(dirs, &self.metadata, &pattern);
+        self.status_message = Some(format!(
+            "Ignoring {} ({} entries); press {} to show ignored entries",
+            pattern.describe(),
+            n_hidden,
+            keymap().key_for(Command::ShowIgnored)
+        ));
+        self.metadata.add_ignore_pattern(pattern);
+    }
+
+    fn selected<'a>(&self, filtered_dirs: &[&'a CruftDirectory]) -> Option<&'a CruftDirectory> {
+        let selected_path = self.selected_path.as_ref()?;
+        filtered_dirs
+            .iter()
+            .find(|dir| dir.id() == *selected_path)
+            .copied()
+    }
+
+    /// Marks (or unmarks) the selected entry as one to keep
+    pub fn toggle_keep_selected(&mut self) {
+        if let Some(ref selected_path) = self.selected_path {
+            self.metadata.toggle_keep(Path::new(selected_path));
+        }
+    }
+
+    pub fn start_note(&mut self) {
+        if let Some(ref selected_path) = self.selected_path {
+            let current = self
+                .metadata
+                .note(Path::new(selected_path))
+                .unwrap_or_default();
+            self.note_input = Some(current.to_string());
+        }
+    }
+
+    pub fn submit_note(&mut self) {
+        if let (Some(note), Some(selected_path)) = (self.note_input.take(), &self.selected_path) {
+            self.metadata.set_note(Path::new(selected_path), &note);
+        }
+    }
+
+    /// Refuses to delete entries marked as ones to keep
+    pub fn check_not_kept(&mut self, path: &str) -> bool {
+        if self.metadata.is_kept(Path::new(path)) {
+            self.status_message = Some(format!(
+                "{} is marked keep (press {} to unmark)",
+                path,
+                keymap().key_for(Command::Keep)
+            ));
+            return false;
+        }
+        true
+    }
+
+    pub fn start_regex(&mut self) {
+        let current = self.path_regex.as_ref().map(|regex| regex.to_string());
+        self.regex_input = Some(current.unwrap_or_default());
+    }
+
+    /// Applies the regex typed at the prompt, or takes the filter away if it's empty
+    pub fn submit_regex(&mut self) {
+        let Some(input) = self.regex_input.take() else {
+            return;
+        };
+        if input.is_empty() {
+            self.path_regex = None;
+            return;
+        }
+        match Regex::new(&input) {
+            Ok(regex) => self.path_regex = Some(regex),
+            Err(e) => self.status_message = Some(format!("Invalid regex: {}", e)),
+        }
+    }
+
+    pub fn start_age(&mut self) {
+        let current = self.age_filter.as_days().map(|days| format!("{}d", days));
+        self.age_input = Some(current.unwrap_or_default());
+    }
+
+    /// Applies the age typed at the prompt, or takes the age filter away if it's empty
+    pub fn submit_age(&mut self) {
+        let Some(input) = self.age_input.take() else {
+            return;
+        };
+        if input.trim().is_empty() {
+            self.age_filter = AgeFilter::None;
+            return;
+        }
+        match crate::parse::parse_age(&input) {
+            Ok(0) => self.age_filter = AgeFilter::None,
+            Ok(days) => self.age_filter = AgeFilter::OlderThan(days),
+            Err(e) => self.status_message = Some(e),
+        }
+    }
+
+    pub fn start_min_size(&mut self) {
+        let current = match self.size_filter.as_bytes() {
+            0 => String::new(),
+            bytes => format_threshold(bytes),
+        };
+        self.min_size_input = Some(current);
+    }
+
+    /// Applies the size typed at the prompt; empty shows everything
+    pub fn submit_min_size(&mut self) {
+        let Some(input) = self.min_size_input.take() else {
+            return;
+        };
+        if input.trim().is_empty() {
+            self.size_filter = SizeFilter(0);
+            return;
+        }
+        match crate::parse::parse_size(&input) {
+            Ok(bytes) => self.size_filter = SizeFilter(bytes),
+            Err(e) => self.status_message = Some(e),
+        }
+    }
+
+    pub fn start_min_files(&mut self) {
+        let current = self.min_files.map(|files| files.to_string());
+        self.min_files_input = Some(current.unwrap_or_default());
+    }
+
+    /// Applies the file count typed at the prompt, or takes the filter away if it's empty
+    pub fn submit_min_files(&mut self) {
+        let Some(input) = self.min_files_input.take() else {
+            return;
+        };
+        let input = input.trim();
+        if input.is_empty() {
+            self.min_files = None;
+            return;
+        }
+        match input.parse::<u64>() {
+            Ok(0) => self.min_files = None,
+            Ok(files) => self.min_files = Some(files),
+            Err(_) => self.status_message = Some(format!("invalid number of files '{}'", input)),
+        }
+    }
+
+    pub fn start_search(&mut self) {
+        let current = self.search.as_ref().map(|q| q.as_str().to_string());
+        self.search_input = Some(current.unwrap_or_default());
+    }
+
+    pub fn submit_search(&mut self, filtered_dirs: &[&CruftDirectory]) {
+        let Some(input) = self.search_input.take() else {
             return;
+        };
+        match SearchQuery::parse(&input) {
+            Ok(query) if query.is_empty() => self.search = None,
+            Ok(query) => {
+                self.search = Some(query);
+                self.jump_to_match(filtered_dirs, true, true);
+            }
+            Err(message) => self.status_message = Some(message),
         }
+    }
 
-        let current_pos = if let Some(ref selected_path) = self.selected_path {
+    /// Moves the selection to the next (or previous) entry matching the search, wrapping around
+    pub fn jump_to_match(
+        &mut self,
+        filtered_dirs: &[&CruftDirectory],
+        forward: bool,
+        include_current: bool,
+    ) {
+        let Some(ref query) = self.search else {
+            return;
+        };
+        if filtered_dirs.is_empty() {
+            return;
+        }
+        let len = filtered_dirs.len();
+        let current = self.selected_path.as_ref().and_then(|selected_path| {
             filtered_dirs
                 .iter()
                 .position(|dir| dir.id() == *selected_path)
-        } else {
-            None
+        });
+        let start = match current {
+            Some(pos) if include_current => pos,
+            Some(pos) if forward => pos + 1,
+            Some(pos) => pos + len - 1,
+            None if forward => 0,
+            None => len - 1,
         };
+        let found = (0..len)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step) % len
+                }
+            })
+            .find(|&pos| query.matches(filtered_dirs[pos]));
+        match found {
+            Some(pos) => {
+                // The list follows on the next frame, with the match's group opened up
+                if let Some(label) = self.grouping.label(filtered_dirs[pos]) {
+                    self.collapsed.remove(&label);
+                }
+                self.selected_path = Some(filtered_dirs[pos].id());
+                self.selected_group = None;
+            }
+            None => self.status_message = Some(format!("No matches for '{}'", query.as_str())),
+        }
+    }
+
+    pub fn open_action_menu(&mut self, filtered_dirs: &[&CruftDirectory]) {
+        if let Some(dir) = self.selected(filtered_dirs) {
+            self.action_menu = Some(ActionMenu::for_entry(dir, &self.metadata));
+        }
+    }
+
+    pub fn inspect_selected(&mut self, filtered_dirs: &[&CruftDirectory]) {
+        if let Some(dir) = self.selected(filtered_dirs) {
+            self.inspector = Some(Inspector::start(dir));
+        }
+    }
+
+    /// Opens the browser on the selected entry, where it was left if it's been browsed before
+    pub fn browse_selected(&mut self, filtered_dirs: &[&CruftDirectory]) {
+        if let Some(dir) = self.selected(filtered_dirs) {
+            let browser = self
+                .browsed
+                .remove(&dir.path)
+                .unwrap_or_else(|| Browser::start(&dir.path));
+            self.browser = Some(browser);
+        }
+    }
+
+    pub fn close_browser(&mut self) {
+        if let Some(browser) = self.browser.take() {
+            self.browsed.insert(browser.root.clone(), browser);
+        }
+    }
+
+    pub fn copy_selected_path(&mut self) {
+        if let Some(ref selected_path) = self.selected_path {
+            self.status_message = Some(match action_menu::copy_to_clipboard(selected_path) {
+                Ok(()) => format!("Copied {}", selected_path),
+                Err(e) => format!("Could not copy the path: {}", e),
+            });
+        }
+    }
 
-        let new_pos = match current_pos {
-            Some(current_pos) => {
-                let list_len = (filtered_dirs.len() - 1) as i64;
-                ((current_pos as i64) + if forward { 1 } else { -1 })
-                    .max(0)
-                    .min(list_len) as usize
+    pub fn open_selected(&mut self) {
+        if let Some(ref selected_path) = self.selected_path
+            && let Err(e) = action_menu::open_in_file_manager(Path::new(selected_path))
+        {
+            self.status_message = Some(format!("Could not open {}: {}", selected_path, e));
+        }
+    }
+
+    pub fn request_delete_confirmation(
+        &mut self,
+        path: String,
+        typed: bool,
+        warning: Option<String>,
+    ) {
+        self.confirm_delete = Some(path);
+        self.confirm_typed = typed;
+        self.confirm_warning = warning;
+        self.confirm_input.clear();
+    }
+
+    /// Asks to delete everything in `filtered_dirs` (what's shown, or the entries of `group`);
+    /// the confirmation is typed if any one of them would need it
+    pub fn request_delete_all(
+        &mut self,
+        filtered_dirs: &[&CruftDirectory],
+        group: Option<String>,
+        deletions: &DeleteWorker,
+        staging: Option<&Staging>,
+        confirm_policy: &ConfirmPolicy,
+        root_profile: bool,
+    ) {
+        let mut bulk = BulkDelete {
+            ids: Vec::new(),
+            total_size: 0,
+            group,
+            skipped: 0,
+        };
+        let mut typed = root_profile;
+        let open_files = OpenFiles::snapshot();
+        let mut in_use = 0;
+        let mut users = Vec::new();
+        // Asked of git once per repository, after the loop
+        let mut in_repos: HashMap<&Path, Vec<&Path>> = HashMap::new();
+        let mut permanent = 0;
+        for dir in filtered_dirs {
+            if dir.pending
+                || dir.via_symlink.is_some()
+                || self.metadata.is_kept(&dir.path)
+                || deletions.progress(&dir.path).is_some()
+            {
+                bulk.skipped += 1;
+                continue;
+            }
+            let size = self.size_mode.size_of(dir);
+            // A size from an earlier scan may be out of date, so counts as unknown
+            let known_size = (!dir.size_cached).then_some(size);
+            typed |= confirm_policy.required(dir, known_size) == Confirmation::Typed;
+            if let Some(repo) = &dir.project
+                && repo.join(".git").exists()
+            {
+                in_repos.entry(repo).or_default().push(&dir.path);
+            }
+            if staging.is_some_and(|staging| !staging.can_hold(dir)) {
+                permanent += 1;
             }
+            let dir_users = open_files.users_of(&dir.path);
+            if !dir_users.is_empty() {
+                in_use += 1;
+                for process in dir_users {
+                    if !users.contains(&process) {
+                        users.push(process);
+                    }
+                }
+            }
+            bulk.ids.push(dir.id());
+            bulk.total_size += size;
+        }
+        if bulk.ids.is_empty() {
+            self.status_message = Some("Nothing shown can be deleted".to_string());
+            return;
+        }
+        let with_tracked_files: usize = in_repos
+            .iter()
+            .map(|(repo, dirs)| git_status::count_with_tracked_files(repo, dirs))
+            .sum();
+        self.confirm_delete_all = Some(bulk);
+        self.confirm_typed = typed;
+        let mut warnings = Vec::new();
+        if in_use > 0 {
+            warnings.push(format!(
+                "{} of them in use by {}!",
+                in_use,
+                open_files::describe(&users)
+            ));
+        }
+        if with_tracked_files > 0 {
+            warnings.push(format!(
+                "{} of them hold files tracked by git!",
+                with_tracked_files
+            ));
+        }
+        if permanent > 0 {
+            warnings.push(format!("{} of them {}", permanent, CANNOT_BE_UNDONE));
+        }
+        self.confirm_warning = (!warnings.is_empty()).then(|| warnings.join(" "));
+        self.confirm_input.clear();
+    }
+
+    pub fn cancel_delete_confirmation(&mut self) {
+        self.confirm_delete = None;
+        self.confirm_input.clear();
+    }
+
+    pub fn select_next_or_previous(&mut self, rows: &[Row], forward: bool) {
+        self.move_selection(rows, if forward { 1 } else { -1 });
+    }
+
+    /// Moves the selection `by` rows (up, if negative), stopping at the first or last one
+    pub fn move_selection(&mut self, rows: &[Row], by: isize) {
+        if rows.is_empty() {
+            return;
+        }
+        let new_pos = match self.selected_row(rows) {
+            Some(current_pos) => current_pos.saturating_add_signed(by).min(rows.len() - 1),
             None => 0,
         };
         self.list_state.select(Some(new_pos));
-        self.selected_path = Some(filtered_dirs[new_pos].id());
+        self.select_row(&rows[new_pos]);
     }
 
-    // Update selection position based on filtered directories
-    pub fn update_selection(&mut self, filtered_dirs: &[CruftDirectory]) {
-        if filtered_dirs.is_empty() {
+    fn select_row(&mut self, row: &Row) {
+        match row {
+            Row::Group(group) => {
+                self.selected_path = None;
+                self.selected_group = Some(group.label.clone());
+            }
+            Row::Entry(dir) => {
+                self.selected_path = Some(dir.id());
+                self.selected_group = None;
+            }
+        }
+    }
+
+    /// Where the selected entry or group header is among `rows`, if it's there
+    fn selected_row(&self, rows: &[Row]) -> Option<usize> {
+        rows.iter().position(|row| match row {
+            Row::Group(group) => self.selected_group.as_ref() == Some(&group.label),
+            Row::Entry(dir) => self.selected_path.as_ref() == Some(&dir.id()),
+        })
+    }
+
+    // Update selection position based on the rows shown
+    pub fn update_selection(&mut self, rows: &[Row]) {
+        if rows.is_empty() {
             self.selected_path = None;
+            self.selected_group = None;
             self.list_state.select(None);
             return;
         }
 
-        if let Some(ref selected_path) = self.selected_path {
-            let position = filtered_dirs
-                .iter()
-                .position(|dir| dir.id() == *selected_path);
-            self.list_state.select(position);
-        } else {
-            self.selected_path = None;
-            self.list_state.select(None);
+        self.list_state.select(self.selected_row(rows));
+    }
+
+    pub fn cycle_grouping(&mut self) {
+        self.grouping = self.grouping.next();
+        self.selected_group = None;
+    }
+
+    /// Collapses the selected group, or expands it if it's collapsed
+    pub fn toggle_selected_group(&mut self) {
+        if let Some(ref label) = self.selected_group
+            && !self.collapsed.remove(label)
+        {
+            self.collapsed.insert(label.clone());
         }
     }
 }
 
-/// Filters the directory list based on size, type, and age criteria
-fn filter_dirs(dirs: &[CruftDirectory], app_state: &AppState) -> Vec<CruftDirectory> {
+/// Filters the directory list based on size, type, age, ownership and search criteria
+fn filter_dirs<'a>(dirs: &'a [CruftDirectory], app_state: &AppState) -> Vec<&'a CruftDirectory> {
     let min_size_bytes = app_state.size_filter.as_bytes();
     let max_age_days = app_state.age_filter.as_days();
     let sort_order = app_state.sort_order;
+    // While the search prompt is open, the list narrows down as the query is typed
+    let typed_query = app_state
+        .search_input
+        .as_deref()
+        .and_then(|input| SearchQuery::parse(input).ok());
+    let query = typed_query
+        .as_ref()
+        .or(app_state.search.as_ref())
+        .filter(|query| !query.is_empty());
 
     let mut filtered = dirs
         .iter()
         .filter(|dir| {
-            if dir.size < min_size_bytes {
+            if !app_state.show_ignored && app_state.metadata.is_ignored(dir) {
+                return false;
+            }
+            // Keep showing entries until we know they're small or recent
+            if !dir.pending && app_state.size_mode.size_of(dir) < min_size_bytes {
+                return false;
+            }
+            // Entries whose size was estimated have no count, and stay
+            if let (Some(min_files), Some(files)) = (app_state.min_files, dir.file_count)
+                && files < min_files
+            {
                 return false;
             }
             if let Some(days) = max_age_days
+                && !dir.pending
                 && dir.newest_file_age_days.unwrap_or(0.0) < days as f64
             {
                 return false;
             }
+            if app_state.deletable_only && !dir.deletable {
+                return false;
+            }
+            if dir.confidence < app_state.min_confidence {
+                return false;
+            }
+            if !app_state
+                .type_filter
+                .admits(&dir.crufty_reason, &app_state.hidden_types)
+            {
+                return false;
+            }
+            if let Some(uid) = app_state.owner_filter
+                && dir.owner != Some(uid)
+            {
+                return false;
+            }
+            if let Some(query) = query
+                && !query.matches(dir)
+            {
+                return false;
+            }
+            if let Some(ref regex) = app_state.path_regex
+                && !regex.is_match(&dir.path.to_string_lossy())
+            {
+                return false;
+            }
             true
         })
-        .cloned() // Clone the CruftDirectory objects
         .collect::<Vec<_>>();
 
-    sort_order.sort_entries(&mut filtered);
+    sort_order.sort_entries(
+        app_state.sort_direction,
+        &mut filtered,
+        app_state.collation,
+        app_state.size_mode,
+    );
+    // Low-confidence findings go last, in their own section (the sort is stable)
+    filtered.sort_by_key(|dir| dir.confidence == Confidence::Guess);
 
     filtered
 }
 
+/// How long each frame may spend taking in scanner events
+const EVENT_BUDGET: Duration = Duration::from_millis(50);
+
+/// How many of the biggest things inside the selected entry the preview pane lists
+const PREVIEW_CHILDREN: usize = 10;
+
 pub fn run_ui<B: Backend>(
     terminal: &mut Terminal<B>,
-    found_dirs: &Arc<Mutex<Vec<CruftDirectory>>>,
-    scan_complete: &Arc<std::sync::atomic::AtomicBool>,
-    n_scanned_ents: &Arc<AtomicU64>,
-) -> Result<()>
+    scan: &mut BackgroundScan,
+    watch: Option<&Watch>,
+    mut options: UiOptions,
+) -> Result<Option<Staging>>
 where
     B::Error: Send + Sync + 'static,
 {
     let mut app_state = AppState::new();
+    app_state.owner_filter = options.owner_filter;
+    app_state.collation = options.collation;
+    app_state.size_mode = options.size_mode;
+    app_state.size_filter = options.size_filter;
+    app_state.age_filter = options.age_filter;
+    app_state.min_files = options.min_files;
+    app_state.show_preview = options.preview;
+    app_state.path_regex = options.path_regex.take();
+    app_state.metadata = std::mem::take(&mut options.metadata);
+    let audit_log = options.audit_log.as_ref();
+    let mut deletions = DeleteWorker::start(options.audit_log.clone());
+    let mut staging = options.staging.take();
 
     const SPINNER_CHARS: [&str; 8] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧"];
+    // The status bar's separator line is left out in low-bandwidth mode
+    let header_block = || {
+        if options.low_bandwidth {
+            Block::default()
+        } else {
+            Block::default().borders(Borders::BOTTOM)
+        }
+    };
 
+    let mut results = ScanResults::default();
     loop {
-        let is_scan_complete = scan_complete.load(std::sync::atomic::Ordering::Relaxed);
-        if is_scan_complete && !app_state.scan_complete {
+        // A fast scan can send events quicker than they're taken in, so keep some time for the rest
+        let drain_start = std::time::Instant::now();
+        let mut n_events = 0;
+        while drain_start.elapsed() < EVENT_BUDGET
+            && let Ok(event) = scan.events().try_recv()
+        {
+            results.apply(event);
+            n_events += 1;
+        }
+        if let Some(watch) = watch {
+            while drain_start.elapsed() < EVENT_BUDGET
+                && let Ok(event) = watch.events().try_recv()
+            {
+                results.apply(event);
+                n_events += 1;
+            }
+        }
+        app_state.frame_stats.last_frame_events = n_events;
+        for (dir, result) in deletions.poll() {
+            let path = &dir.path;
+            match result {
+                Ok(Done::Staged(staged)) => {
+                    results.remove(path);
+                    if let Some(staging) = staging.as_mut() {
+                        staging.add(*staged);
+                    }
+                }
+                Ok(Done::Deleted) => {
+                    if dir.staged_from.is_some() {
+                        staging::purged(path);
+                    }
+                    results.remove(path);
+                    if !delete::is_dry_run() {
+                        app_state.freed.push(dir);
+                    }
+                }
+                Err(e) => {
+                    app_state.status_message = Some(format!("Not deleted: {:#}", e));
+                    // It may have got partway, so what's left is sized again (unless it was
+                    // being purged from the staging area)
+                    if path.starts_with(scan.start_dir()) {
+                        scan.refresh(path);
+                    }
+                }
+            }
+        }
+        if results.summary.is_some() && !app_state.scan_complete {
             app_state.mark_scan_complete();
+            history::record_scan(scan.start_dir(), &results.dirs);
+            app_state.offered_excludes = results.slow_subtrees.clone();
         }
 
-        if !app_state.scan_complete {
+        if !app_state.scan_complete && !options.reduced_motion {
             app_state.update_spinner();
         }
 
-        let (n_total_dirs, filtered_dirs) = {
-            let dirs = found_dirs.lock().unwrap();
-            (dirs.len(), filter_dirs(&dirs, &app_state))
+        let filter_start = std::time::Instant::now();
+        let (n_total_dirs, n_possible, filtered_dirs) = {
+            let dirs = &results.dirs;
+            let mut filtered = filter_dirs(dirs, &app_state);
+            let n_possible = filtered
+                .iter()
+                .filter(|dir| dir.confidence == Confidence::Guess)
+                .count();
+            if !app_state.show_possible {
+                filtered.retain(|dir| dir.confidence != Confidence::Guess);
+            }
+            (dirs.len(), n_possible, filtered)
         };
+        app_state.frame_stats.last_filter_time = filter_start.elapsed();
+
+        let rows = grouping::rows(
+            &filtered_dirs,
+            app_state.grouping,
+            &app_state.collapsed,
+            app_state.size_mode,
+        );
+        app_state.update_selection(&rows);
+        if app_state.search_input.is_some() && app_state.list_state.selected().is_none() {
+            // What was selected no longer matches what's being typed, so the first match is
+            app_state.select_next_or_previous(&rows, true);
+        }
 
-        app_state.update_selection(&filtered_dirs);
+        // For entries found through a symlink, deleting means choosing between the link and its target
+        let confirm_link = app_state.confirm_delete.as_ref().and_then(|path| {
+            results
+                .dirs
+                .iter()
+                .find(|dir| dir.id() == *path)?
+                .via_symlink
+                .clone()
+        });
+        let previewed = if app_state.show_preview && app_state.screen == Screen::List {
+            app_state.selected(&filtered_dirs)
+        } else {
+            None
+        };
+        if let Some(dir) = previewed {
+            app_state.previews.want(&dir.path);
+        }
+        let confirm_hint = app_state.confirm_delete.as_ref().and_then(|path| {
+            let dir = results.dirs.iter().find(|dir| dir.id() == *path)?;
+            options.rebuild_hints.for_reason(&dir.crufty_reason)
+        });
+        if let Some(ref mut inspector) = app_state.inspector {
+            inspector.poll();
+        }
+        if let Some(ref mut browser) = app_state.browser {
+            browser.poll();
+        }
 
         terminal.draw(|f| {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Length(2), // Status bar with border
+                    Constraint::Length(if options.low_bandwidth { 1 } else { 2 }), // Status bar with border
                     Constraint::Min(10),   // List content
                     Constraint::Length(1), // Help line
                 ])
                 .split(f.area());
 
-            let total_size: u64 = filtered_dirs.iter().map(|d| d.size).sum();
+            let total_size: u64 = filtered_dirs.iter().map(|d| app_state.size_mode.size_of(d)).sum();
+            // Shown alongside, so that it's clear when relaxing the filters would be worth it
+            let found_size: u64 = results.dirs.iter().map(|d| app_state.size_mode.size_of(d)).sum();
+            let caveat_size: u64 = filtered_dirs
+                .iter()
+                .filter(|d| d.space_caveat.is_some())
+                .map(|d| app_state.size_mode.size_of(d))
+                .sum();
+
+            let possible_header = |arrow: &str| {
+                Line::styled(
+                    format!("{} possible cruft ({} more) - press {} to {}", arrow, n_possible, keymap().key_for(Command::PossibleCruft), if app_state.show_possible { "hide" } else { "show" }),
+                    Style::default().fg(Color::DarkGray),
+                )
+            };
+            // Grouped, possible cruft is found in its groups instead of a section of its own
+            let first_possible = if app_state.grouping == Grouping::None {
+                filtered_dirs.iter().position(|dir| dir.confidence == Confidence::Guess)
+            } else {
+                None
+            };
 
-            let items: Vec<ListItem> = filtered_dirs
+            let mut items: Vec<ListItem> = rows
                 .iter()
-                .map(|dir| {
-                    let size_mb = dir.size as f64 / 1_048_576.0;
+                .enumerate()
+                .map(|(i, row)| {
+                    let dir = match row {
+                        Row::Group(group) => {
+                            return ListItem::new(Line::styled(
+                                format!(
+                                    "{} {} ({} {}, {})",
+                                    if group.collapsed { "▸" } else { "▾" },
+                                    group.label,
+                                    group.count,
+                                    if group.count == 1 { "dir" } else { "dirs" },
+                                    format_size(group.size)
+                                ),
+                                Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                            ));
+                        }
+                        Row::Entry(dir) => dir,
+                    };
+                    let size = format_size(app_state.size_mode.size_of(dir));
 
-                    let size_str = format!("{:.2} MB", size_mb);
+                    let size_str = if let Some(progress) = deletions.progress(&dir.path) {
+                        // Files are counted by their apparent size as they go
+                        if dir.size > 0 && !dir.pending {
+                            format!("deleting {}%", (progress.bytes * 100 / dir.size).min(100))
+                        } else {
+                            format!("deleting {}", progress.files)
+                        }
+                    } else if dir.pending && app_state.scan_complete {
+                        "not sized".to_string()
+                    } else if dir.pending {
+                        "sizing...".to_string()
+                    } else if dir.size_estimated_by.is_some() {
+                        format!("~{}", size)
+                    } else {
+                        size
+                    };
                     let size_formatted = format!("{:>15} ", size_str);
 
-                    let age_str = format!("{} days", dir.newest_file_age_days.unwrap_or(0.0).round());
+                    let age_str = if dir.pending {
+                        "...".to_string()
+                    } else {
+                        format!("{} days", dir.newest_file_age_days.unwrap_or(0.0).round())
+                    };
                     let age_formatted = format!("{:>10} ", age_str);
 
+                    let files_str = match dir.file_count {
+                        _ if dir.pending => "...".to_string(),
+                        Some(1) => "1 file".to_string(),
+                        Some(files) => format!("{} files", format_count(files)),
+                        None => "? files".to_string(),
+                    };
+                    let files_formatted = format!("{:>11} ", files_str);
+
                     let type_str = format!("{}", dir.crufty_reason);
                     let type_formatted = format!("{:<15} ", type_str);
+                    let confidence_color = match dir.confidence {
+                        Confidence::Certain => Color::Green,
+                        Confidence::Likely => Color::Gray,
+                        Confidence::Guess => Color::DarkGray,
+                    };
 
-                    let line = Line::from(vec![
+                    let mut spans = vec![
                         Span::styled(
                             size_formatted,
                             Style::default().fg(Color::Yellow),
@@ -207,80 +1105,854 @@ where
                             age_formatted,
                             Style::default().fg(Color::Magenta),
                         ),
+                        Span::styled(
+                            files_formatted,
+                            Style::default().fg(Color::Blue),
+                        ),
                         Span::styled(
                             type_formatted,
                             Style::default().fg(Color::Green),
                         ),
-                        Span::raw(dir.path.to_string_lossy().to_string()),
-                    ]);
-                    ListItem::new(line)
+                        Span::styled(
+                            format!("{:<8}", dir.confidence.as_str()),
+                            Style::default().fg(confidence_color),
+                        ),
+                    ];
+                    if app_state.show_ownership {
+                        let owner_str = dir.owner.map(ownership::user_name).unwrap_or_else(|| "?".to_string());
+                        spans.push(Span::styled(
+                            format!("{:<12} ", owner_str),
+                            Style::default().fg(Color::Cyan),
+                        ));
+                        let (deletable_str, deletable_color) = if dir.deletable {
+                            ("ok", Color::Green)
+                        } else {
+                            ("denied", Color::Red)
+                        };
+                        spans.push(Span::styled(
+                            format!("{:<7} ", deletable_str),
+                            Style::default().fg(deletable_color),
+                        ));
+                    }
+                    let path_style = match app_state.search {
+                        Some(ref query) if query.matches(dir) => Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::UNDERLINED),
+                        _ => Style::default(),
+                    };
+                    if app_state.metadata.is_kept(&dir.path) {
+                        spans.push(Span::styled("[keep] ", Style::default().fg(Color::Green)));
+                    }
+                    if app_state.metadata.is_ignored(dir) {
+                        spans.push(Span::styled("[ignored] ", Style::default().fg(Color::DarkGray)));
+                    }
+                    spans.push(Span::styled(dir.path.to_string_lossy().to_string(), path_style));
+                    if let Some(ref link) = dir.via_symlink {
+                        spans.push(Span::styled(
+                            format!(" (via {})", link.display()),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    if !dir.members.is_empty() {
+                        spans.push(Span::styled(
+                            format!(" ({} items throughout)", dir.members.len()),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    if dir.unreadable > 0 {
+                        // So the size is only a lower bound
+                        spans.push(Span::styled(
+                            format!(" ({} unreadable inside)", dir.unreadable),
+                            Style::default().fg(Color::Red),
+                        ));
+                    }
+                    if let Some(caveat) = dir.space_caveat {
+                        spans.push(Span::styled(
+                            format!(" [{}]", caveat.label()),
+                            Style::default().fg(Color::LightRed),
+                        ));
+                    }
+                    if let Some(note) = app_state.metadata.note(&dir.path) {
+                        spans.push(Span::styled(
+                            format!("  # {}", note),
+                            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                        ));
+                    }
+                    let mut lines = Vec::new();
+                    if first_possible == Some(i) {
+                        lines.push(possible_header("▾"));
+                    }
+                    lines.push(Line::from(spans));
+                    if app_state.expanded.contains(&dir.id()) {
+                        lines.extend(breakdown_lines(dir, app_state.size_mode));
+                    }
+                    ListItem::new(lines)
                 })
                 .collect();
+            if !app_state.show_possible && n_possible > 0 {
+                // Not an entry, so it can't be selected: it always comes after the last one
+                items.push(ListItem::new(possible_header("▸")));
+            }
 
             let list = List::new(items)
                 .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            app_state.page_rows = chunks[1].height as usize;
 
-            f.render_stateful_widget(list, chunks[1], &mut app_state.list_state);
+            match app_state.screen {
+                Screen::List if app_state.show_preview => {
+                    let panes = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                        .split(chunks[1]);
+                    f.render_stateful_widget(list, panes[0], &mut app_state.list_state);
+                    draw_scrollbar(f, panes[0], rows.len(), app_state.list_state.offset());
+                    draw_preview(
+                        f,
+                        panes[1],
+                        previewed,
+                        previewed.and_then(|dir| app_state.previews.get(&dir.path)),
+                        app_state.size_mode,
+                    );
+                }
+                Screen::List => {
+                    f.render_stateful_widget(list, chunks[1], &mut app_state.list_state);
+                    draw_scrollbar(f, chunks[1], rows.len(), app_state.list_state.offset());
+                }
+                Screen::Totals => dashboard::draw(f, chunks[1], &filtered_dirs, app_state.size_mode, scan.start_dir()),
+                Screen::Treemap => treemap::draw(
+                    f,
+                    chunks[1],
+                    &filtered_dirs,
+                    app_state.selected_path.as_ref().and_then(|selected_path| {
+                        filtered_dirs.iter().position(|dir| dir.id() == *selected_path)
+                    }),
+                    app_state.size_mode,
+                ),
+            }
 
-            if let Some(ref path_to_delete) = app_state.confirm_delete {
-                let confirm_text = format!(
-                    "Delete {}? Press y to confirm, n to cancel.",
-                    path_to_delete
-                );
+            if let Some(ref input) = app_state.search_input {
+                let prompt = Paragraph::new(format!(
+                    "Search (text, path:, reason:, project:, owner:): {}█",
+                    input
+                ))
+                .style(Style::default().fg(Color::LightCyan))
+                .block(header_block());
+                f.render_widget(prompt, chunks[0]);
+            } else if let Some(ref input) = app_state.regex_input {
+                let prompt = Paragraph::new(format!("Only paths matching (regex, empty for all): {}█", input))
+                    .style(Style::default().fg(Color::LightCyan))
+                    .block(header_block());
+                f.render_widget(prompt, chunks[0]);
+            } else if let Some(ref input) = app_state.age_input {
+                let prompt = Paragraph::new(format!(
+                    "Only entries untouched for (e.g. 30d, 2w, 6mo; empty for all): {}█",
+                    input
+                ))
+                .style(Style::default().fg(Color::LightCyan))
+                .block(header_block());
+                f.render_widget(prompt, chunks[0]);
+            } else if let Some(ref input) = app_state.min_size_input {
+                let prompt = Paragraph::new(format!(
+                    "Only entries of at least (e.g. 50M, 1.5G; empty for all): {}█",
+                    input
+                ))
+                .style(Style::default().fg(Color::LightCyan))
+                .block(header_block());
+                f.render_widget(prompt, chunks[0]);
+            } else if let Some(ref input) = app_state.min_files_input {
+                let prompt = Paragraph::new(format!(
+                    "Only entries with at least this many files (empty for all): {}█",
+                    input
+                ))
+                .style(Style::default().fg(Color::LightCyan))
+                .block(header_block());
+                f.render_widget(prompt, chunks[0]);
+            } else if let Some(ref input) = app_state.note_input {
+                let prompt = Paragraph::new(format!("Note (empty to remove): {}█", input))
+                    .style(Style::default().fg(Color::LightCyan))
+                    .block(header_block());
+                f.render_widget(prompt, chunks[0]);
+            } else if let Some(ref purge) = app_state.confirm_purge {
+                let what = match purge.item {
+                    Some(ref item) => format!(
+                        "Delete {} ({}) for good? It can't be restored after this.",
+                        item.original.display(),
+                        format_size(purge.size)
+                    ),
+                    None => format!(
+                        "Delete {} deleted {} ({}) for good? There's no restoring them after this.",
+                        purge.count,
+                        if purge.count == 1 { "item" } else { "items" },
+                        format_size(purge.size)
+                    ),
+                };
+                let confirm_text = if app_state.confirm_typed {
+                    format!(
+                        "{} Type '{}' and press Enter to confirm, Esc to cancel: {}",
+                        what, purge.count, app_state.confirm_input
+                    )
+                } else {
+                    format!("{} Press y to confirm, n to cancel.", what)
+                };
                 let confirm = Paragraph::new(confirm_text)
                     .style(Style::default().fg(Color::Red))
-                    .block(Block::default().borders(Borders::BOTTOM));
+                    .block(header_block());
+                f.render_widget(confirm, chunks[0]);
+            } else if let Some(ref bulk) = app_state.confirm_delete_all {
+                let what = format!(
+                    "Delete all {} {} entries ({}){}?",
+                    bulk.ids.len(),
+                    bulk.group.as_deref().unwrap_or("shown"),
+                    format_size(bulk.total_size),
+                    if bulk.skipped > 0 {
+                        format!(", leaving out {} kept, unsized or symlinked", bulk.skipped)
+                    } else {
+                        String::new()
+                    }
+                );
+                let confirm_text = if app_state.confirm_typed {
+                    format!(
+                        "{} Type '{}' and press Enter to confirm, Esc to cancel: {}",
+                        what,
+                        bulk.ids.len(),
+                        app_state.confirm_input
+                    )
+                } else {
+                    format!("{} Press y to confirm, n to cancel.", what)
+                };
+                let confirm = Paragraph::new(full_prompt(&app_state.confirm_warning, None, confirm_text))
+                    .style(Style::default().fg(Color::Red))
+                    .block(header_block());
+                f.render_widget(confirm, chunks[0]);
+            } else if let Some(ref path_to_delete) = app_state.confirm_delete {
+                let confirm_text = match confirm_link {
+                    Some(ref link) if app_state.confirm_typed => format!(
+                        "Delete {} (found via {})? Type '{}' and press Enter to delete it, or 'link' to remove only the link; Esc to cancel: {}",
+                        path_to_delete,
+                        link.display(),
+                        expected_confirmation(path_to_delete),
+                        app_state.confirm_input
+                    ),
+                    Some(ref link) => format!(
+                        "Delete {} (found via {})? Press t to delete the target, l to remove only the link, n to cancel.",
+                        path_to_delete,
+                        link.display()
+                    ),
+                    None if app_state.confirm_typed => format!(
+                        "Delete {}? Type '{}' and press Enter to confirm, Esc to cancel: {}",
+                        path_to_delete,
+                        expected_confirmation(path_to_delete),
+                        app_state.confirm_input
+                    ),
+                    None => format!(
+                        "Delete {}? Press y to confirm, n to cancel.",
+                        path_to_delete
+                    ),
+                };
+                let confirm = Paragraph::new(full_prompt(
+                    &app_state.confirm_warning,
+                    confirm_hint,
+                    confirm_text,
+                ))
+                    .style(Style::default().fg(Color::Red))
+                    .block(header_block());
                 f.render_widget(confirm, chunks[0]);
             } else {
                 let mut filter_parts = Vec::new();
-                filter_parts.push(app_state.size_filter.as_str().to_string());
+                filter_parts.push(app_state.size_filter.label());
+                if app_state.size_mode != SizeMode::Apparent {
+                    filter_parts.push(app_state.size_mode.as_str().to_string());
+                }
+                if app_state.min_confidence != Confidence::Guess {
+                    filter_parts.push(format!("confidence ≥ {}", app_state.min_confidence.as_str()));
+                }
                 if app_state.age_filter != AgeFilter::None {
-                    filter_parts.push(app_state.age_filter.as_str().to_string());
+                    filter_parts.push(app_state.age_filter.label());
+                }
+                if let Some(files) = app_state.min_files {
+                    filter_parts.push(format!("≥ {} files", files));
+                }
+                if app_state.type_filter != TypeFilter::All {
+                    filter_parts.push(app_state.type_filter.as_str().to_string());
+                }
+                if !app_state.hidden_types.is_empty() {
+                    let mut hidden: Vec<String> =
+                        app_state.hidden_types.iter().map(|r| r.to_string()).collect();
+                    hidden.sort();
+                    filter_parts.push(format!("hiding: {}", hidden.join(", ")));
+                }
+
+                if app_state.deletable_only {
+                    filter_parts.push("deletable only".to_string());
+                }
+                if app_state.show_ignored {
+                    filter_parts.push("showing ignored".to_string());
+                }
+                if let Some(uid) = app_state.owner_filter {
+                    filter_parts.push(format!("owner: {}", ownership::user_name(uid)));
+                }
+                if let Some(ref regex) = app_state.path_regex {
+                    filter_parts.push(format!("paths: /{}/", regex));
                 }
 
-                filter_parts.push(format!("sort: {}", app_state.sort_order.as_str()));
+                if let Some(ref query) = app_state.search {
+                    let n_matches = filtered_dirs.iter().filter(|d| query.matches(d)).count();
+                    filter_parts.push(format!("search: '{}' ({} matches)", query.as_str(), n_matches));
+                }
+
+                filter_parts.push(format!(
+                    "sort: {}, {}",
+                    app_state.sort_order.as_str(),
+                    app_state.sort_order.describe(app_state.sort_direction)
+                ));
+                if app_state.grouping != Grouping::None {
+                    filter_parts.push(format!("grouped by {}", app_state.grouping.as_str()));
+                }
 
-                let header = if app_state.scan_complete {
-                    format!("Decruft: Found {} dirs in {} entities", n_total_dirs, n_scanned_ents.load(Ordering::Relaxed))
+                let header = if let Some(ref message) = app_state.status_message {
+                    message.clone()
+                } else if let Some(subtree) = app_state.offered_excludes.first() {
+                    format!(
+                        "Reading {} took {:.1}s and found nothing: press {} to leave it out of future scans",
+                        subtree.path.display(),
+                        subtree.elapsed.as_secs_f64(),
+                        keymap().key_for(Command::ExcludeSuggestion)
+                    )
+                } else if app_state.scan_complete {
+                    format!(
+                        "Decruft: Found {} dirs in {} entities{}",
+                        n_total_dirs,
+                        results.scanned,
+                        match results.summary {
+                            Some(ref summary) if summary.cancelled => " (incomplete: stopped)",
+                            Some(ref summary) if summary.incomplete => " (incomplete: time limit reached)",
+                            _ => "",
+                        }
+                    ) + if watch.is_some() { " (watching for changes)" } else { "" }
                 } else {
-                    let spinner = SPINNER_CHARS[app_state.spinner_frame];
-                    format!("{} Decruft: Scanning {} entities, found {} dirs so far", spinner, n_scanned_ents.load(Ordering::Relaxed), n_total_dirs)
+                    let spinner = if options.reduced_motion { "…" } else { SPINNER_CHARS[app_state.spinner_frame] };
+                    let doing = if scan.is_cancelled() { "Stopping the scan after" } else { "Scanning" };
+                    format!("{} Decruft: {} {} entities, found {} dirs so far", spinner, doing, results.scanned, n_total_dirs)
+                };
+                let header = match results.errors.len() {
+                    0 => header,
+                    n => format!("{} ({} unreadable, {} to list)", header, n, keymap().key_for(Command::Errors)),
                 };
 
-                let status_text = format!(
-                    "{} (showing {}, {}). Total: {:.2} MB",
+                // Where the selection is, counting entries only (not group headers)
+                let entries = rows.iter().filter(|row| matches!(row, Row::Entry(_)));
+                let position = match app_state.selected_path {
+                    Some(ref path) => entries
+                        .clone()
+                        .position(|row| matches!(row, Row::Entry(dir) if dir.id() == *path))
+                        .map(|i| format!("item {}/{}", i + 1, entries.count())),
+                    None => None,
+                };
+                let mut status_text = format!(
+                    "{}{}{}{} ({}, {}). Visible: {}",
+                    if options.root_profile { "[root profile] " } else { "" },
+                    if delete::is_dry_run() { "[dry run] " } else { "" },
+                    if delete::is_shredding() { "[shred: slow] " } else { "" },
                     header,
-                    filtered_dirs.len(),
+                    position.unwrap_or_else(|| format!("showing {}", filtered_dirs.len())),
                     filter_parts.join(", "),
-                    total_size as f64 / 1_048_576.0
+                    format_size(total_size)
                 );
+                if caveat_size > 0 {
+                    // Deleting from an image layer or tmpfs doesn't give the host its disk space back
+                    status_text.push_str(&format!(
+                        ", of which {} may not free disk space",
+                        format_size(caveat_size)
+                    ));
+                }
+                if found_size > total_size {
+                    status_text.push_str(&format!(
+                        "; total found: {} ({} hidden by filters)",
+                        format_size(found_size),
+                        format_size(found_size - total_size)
+                    ));
+                }
+                if let Some(ref staging) = staging
+                    && !staging.is_empty()
+                {
+                    status_text.push_str(&format!(
+                        "; {} deleted ({}) until you quit: {} to undo, {} to purge now",
+                        staging.len(),
+                        format_size(staging.size()),
+                        keymap().key_for(Command::Undo),
+                        keymap().key_for(Command::Purge)
+                    ));
+                }
+                if !app_state.freed.is_empty() {
+                    let freed: u64 = app_state
+                        .freed
+                        .iter()
+                        .map(|dir| app_state.size_mode.size_of(dir))
+                        .sum();
+                    status_text.push_str(&format!(
+                        "; freed {} this session ({} {})",
+                        format_size(freed),
+                        app_state.freed.len(),
+                        if app_state.freed.len() == 1 { "directory" } else { "directories" }
+                    ));
+                }
+                let would_have_freed = delete::would_have_freed(app_state.size_mode);
+                if would_have_freed > 0 {
+                    status_text.push_str(&format!(
+                        "; deleting would have freed {}",
+                        format_size(would_have_freed)
+                    ));
+                }
 
                 let status = Paragraph::new(status_text)
                     .style(Style::default().fg(Color::White))
-                    .block(Block::default().borders(Borders::BOTTOM));
+                    .block(header_block());
                 f.render_widget(status, chunks[0]);
             }
 
-            let help_text = "j/k: Navigate | a: Toggle all types | s: Toggle small files | o: Toggle age filter | r: Toggle sort | d: Delete | D: Delete (no confirm) | q: Quit";
-            let help_line = Paragraph::new(help_text)
-                .style(Style::default().fg(Color::DarkGray));
-            f.render_widget(help_line, chunks[2]);
+            let help_text = keymap().hints(match app_state.screen {
+                Screen::Totals => &[
+                    (&[Command::NextScreen], "Treemap"),
+                    (&[Command::Back], "Back to the list"),
+                    (&[Command::SizeMode], "Size/disk usage/unique"),
+                    (&[Command::Quit], "Quit"),
+                ],
+                Screen::Treemap => &[
+                    (&[Command::NextScreen, Command::Back], "Back to the list"),
+                    (&[Command::Next, Command::Previous], "Select"),
+                    (&[Command::Browse], "Browse"),
+                    (&[Command::Actions], "Actions"),
+                    (&[Command::Inspect], "Why flagged"),
+                    (&[Command::Delete], "Delete"),
+                    (&[Command::SizeMode], "Size/disk usage/unique"),
+                    (&[Command::Quit], "Quit"),
+                ],
+                Screen::List => &[
+                    (&[Command::Help], "All keys and settings"),
+                    (&[Command::Next, Command::Previous], "Navigate"),
+                    (&[Command::Browse], "Browse"),
+                    (&[Command::Actions], "Actions"),
+                    (&[Command::Search], "Search"),
+                    (&[Command::CycleMinSize, Command::CycleAge], "Size/age filter"),
+                    (&[Command::Sort], "Sort"),
+                    (&[Command::Delete], "Delete"),
+                    (&[Command::DeleteAll], "Delete all shown"),
+                    (&[Command::Undo], "Undo"),
+                    (&[Command::NextScreen], "Totals/treemap"),
+                    (&[Command::Quit], "Quit"),
+                ],
+            });
+            if let Some((dir, progress)) = deletions.current() {
+                // Large deletions take a while, so show that one is moving along instead of the help
+                f.render_widget(deletion_gauge(dir, progress, deletions.queued()), chunks[2]);
+            } else {
+                let help_line = Paragraph::new(help_text)
+                    .style(Style::default().fg(Color::DarkGray));
+                f.render_widget(help_line, chunks[2]);
+            }
+
+            if let Some(ref menu) = app_state.action_menu
+                && let Some(dir) = app_state.selected_path.as_ref()
+                    .and_then(|path| filtered_dirs.iter().find(|dir| dir.id() == *path))
+            {
+                draw_action_menu(f, menu, dir, &app_state.metadata);
+            }
+            if let Some(scroll) = app_state.help_scroll {
+                help::draw(f, &help_settings(&app_state), scroll);
+            }
+            if let Some(scroll) = app_state.error_scroll {
+                draw_errors(f, &results.errors, scroll);
+            }
+            if let Some(ref inspector) = app_state.inspector
+                && let Some(dir) = results.dirs.iter().find(|dir| dir.path == inspector.path)
+            {
+                draw_inspector(
+                    f,
+                    inspector,
+                    dir,
+                    app_state.size_mode,
+                    options.rebuild_hints.for_reason(&dir.crufty_reason),
+                );
+            }
+            if let Some(ref browser) = app_state.browser {
+                draw_browser(f, browser, app_state.size_mode);
+            }
+            if let Some((ref patterns, selected)) = app_state.ignore_similar {
+                draw_ignore_similar(f, patterns, selected, &results.dirs, &app_state.metadata);
+            }
+            if let Some((ref items, selected)) = app_state.staged_view {
+                draw_staged(f, items, selected);
+            }
+            if let Some(ref picker) = app_state.type_picker {
+                draw_type_picker(f, picker);
+            }
+            if let Some(ref lines) = app_state.history_view {
+                draw_history(f, lines);
+            }
+            if app_state.show_scope {
+                options.scope.draw(f);
+            }
+            if app_state.show_debug {
+                draw_debug_overlay(f, &app_state, &options.scan_metrics);
+            }
         })?;
+        app_state.frame_stats.record_frame(&options.bytes_written);
 
-        if event::poll(Duration::from_millis(100))?
+        if event::poll(options.poll_interval)?
             && let Event::Key(key) = event::read()?
         {
+            app_state.status_message = None;
+            if let Some(ref mut input) = app_state.search_input {
+                match key.code {
+                    KeyCode::Enter => app_state.submit_search(&filtered_dirs),
+                    KeyCode::Esc => app_state.search_input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        input.push(c)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(ref mut input) = app_state.regex_input {
+                match key.code {
+                    KeyCode::Enter => app_state.submit_regex(),
+                    KeyCode::Esc => app_state.regex_input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        input.push(c)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(ref mut input) = app_state.age_input {
+                match key.code {
+                    KeyCode::Enter => app_state.submit_age(),
+                    KeyCode::Esc => app_state.age_input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        input.push(c)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(ref mut input) = app_state.min_size_input {
+                match key.code {
+                    KeyCode::Enter => app_state.submit_min_size(),
+                    KeyCode::Esc => app_state.min_size_input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        input.push(c)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(ref mut input) = app_state.min_files_input {
+                match key.code {
+                    KeyCode::Enter => app_state.submit_min_files(),
+                    KeyCode::Esc => app_state.min_files_input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        input.push(c)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(ref mut input) = app_state.note_input {
+                match key.code {
+                    KeyCode::Enter => {
+                        app_state.submit_note();
+                        save_metadata(&mut app_state, &options);
+                    }
+                    KeyCode::Esc => app_state.note_input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        input.push(c)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if app_state.inspector.is_some() {
+                app_state.inspector = None;
+                continue;
+            }
+            if app_state.history_view.is_some() {
+                app_state.history_view = None;
+                continue;
+            }
+            if let Some(scroll) = app_state.help_scroll {
+                let last = help::line_count(&help_settings(&app_state)).saturating_sub(1);
+                app_state.help_scroll = match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => Some((scroll + 1).min(last)),
+                    KeyCode::Char('k') | KeyCode::Up => Some(scroll.saturating_sub(1)),
+                    _ => None,
+                };
+                continue;
+            }
+            if let Some(ref mut browser) = app_state.browser {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => browser.select_next_or_previous(true),
+                    KeyCode::Char('k') | KeyCode::Up => browser.select_next_or_previous(false),
+                    KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => browser.enter(),
+                    KeyCode::Backspace | KeyCode::Char('h') | KeyCode::Left => {
+                        let at_top = !browser.leave();
+                        if at_top {
+                            app_state.close_browser();
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => app_state.close_browser(),
+                    _ => {}
+                }
+                continue;
+            }
+            let mut command = keymap().command(&key);
+            if app_state.show_scope {
+                app_state.show_scope = false;
+                if command == Some(Command::Quit) {
+                    break;
+                }
+                continue;
+            }
+            if app_state.screen == Screen::Totals {
+                match command {
+                    Some(Command::NextScreen) => app_state.screen = app_state.screen.next(),
+                    Some(Command::Back) => app_state.screen = Screen::List,
+                    Some(Command::SizeMode) => app_state.toggle_size_mode(),
+                    Some(Command::Quit) => break,
+                    _ => {}
+                }
+                continue;
+            }
+            if !app_state.offered_excludes.is_empty() {
+                if command == Some(Command::ExcludeSuggestion) {
+                    let subtree = app_state.offered_excludes.remove(0);
+                    app_state.metadata.exclude.insert(subtree.path.clone());
+                    save_metadata(&mut app_state, &options);
+                    if app_state.offered_excludes.is_empty() {
+                        app_state.status_message = Some(format!(
+                            "{} will be left out of future scans",
+                            subtree.path.display()
+                        ));
+                    }
+                    continue;
+                }
+                // Any other key turns the offer down, and does what it always does
+                app_state.offered_excludes.clear();
+            }
+            if let Some(ref mut scroll) = app_state.error_scroll {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        *scroll = (*scroll + 1).min(results.errors.len().saturating_sub(1))
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                    _ => app_state.error_scroll = None,
+                }
+                continue;
+            }
+            if let Some((ref items, ref mut selected)) = app_state.staged_view {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down if !items.is_empty() => {
+                        *selected = (*selected + 1) % items.len()
+                    }
+                    KeyCode::Char('k') | KeyCode::Up if !items.is_empty() => {
+                        *selected = (*selected + items.len() - 1) % items.len()
+                    }
+                    KeyCode::Enter | KeyCode::Char('r') if !items.is_empty() => {
+                        let item = &items[*selected];
+                        let restored = staging
+                            .as_mut()
+                            .map(|staging| staging.restore_item(item, audit_log));
+                        app_state.status_message = match restored {
+                            Some(Ok(restored)) => {
+                                match restored {
+                                    Some(dir) => {
+                                        results.apply(crate::scanner::ScanEvent::Found(dir))
+                                    }
+                                    // Only its path is known, so it's looked at afresh
+                                    None if item.original.starts_with(scan.start_dir()) => {
+                                        scan.refresh(&item.original)
+                                    }
+                                    None => {}
+                                }
+                                Some(format!("Restored {}", item.original.display()))
+                            }
+                            Some(Err(e)) => Some(format!("{:#}", e)),
+                            None => None,
+                        };
+                        app_state.staged_view = None;
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete if !items.is_empty() => {
+                        let item = items[*selected].clone();
+                        app_state.confirm_purge = Some(Purge {
+                            count: 1,
+                            size: item.size,
+                            item: Some(item),
+                        });
+                        app_state.confirm_typed = options.root_profile;
+                        app_state.staged_view = None;
+                    }
+                    _ => app_state.staged_view = None,
+                }
+                continue;
+            }
+            if let Some(ref mut picker) = app_state.type_picker {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => picker.select_next_or_previous(true),
+                    KeyCode::Char('k') | KeyCode::Up => picker.select_next_or_previous(false),
+                    KeyCode::Char(' ') => picker.toggle_selected(),
+                    KeyCode::Enter => app_state.submit_type_picker(),
+                    _ => app_state.type_picker = None,
+                }
+                continue;
+            }
+            if let Some((ref patterns, ref mut selected)) = app_state.ignore_similar {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        *selected = (*selected + 1) % patterns.len()
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        *selected = (*selected + patterns.len() - 1) % patterns.len()
+                    }
+                    KeyCode::Enter => {
+                        app_state.submit_ignore_similar(&results.dirs);
+                        save_metadata(&mut app_state, &options);
+                    }
+                    _ => app_state.ignore_similar = None,
+                }
+                continue;
+            }
+            // How far PageUp and PageDown go (Ctrl-u and Ctrl-d half that)
+            let page = app_state.page_rows.max(2) as isize;
+            if let Some(ref mut menu) = app_state.action_menu {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        menu.select_next_or_previous(true);
+                        continue;
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        menu.select_next_or_previous(false);
+                        continue;
+                    }
+                    // Carried out as if one of its keys had been pressed
+                    KeyCode::Enter => command = Some(menu.selected().command()),
+                    // An action's own key picks it from the menu directly
+                    _ if command.is_some_and(|command| menu.find(command).is_some()) => {}
+                    _ => {
+                        app_state.action_menu = None;
+                        continue;
+                    }
+                }
+                app_state.action_menu = None;
+            }
+            if let Some(ref purge) = app_state.confirm_purge {
+                let expected = purge.count.to_string();
+                if let Some(confirmed) = confirm_count(&mut app_state, key.code, &expected) {
+                    if let Some(purge) = app_state.confirm_purge.take()
+                        && confirmed
+                        && let Some(staging) = staging.as_mut()
+                    {
+                        match purge.item {
+                            Some(item) => match staging.take_item_for_purge(&item) {
+                                Ok(dir) => deletions.delete(&dir),
+                                Err(e) => app_state.status_message = Some(format!("{:#}", e)),
+                            },
+                            None => {
+                                for dir in staging.take_for_purge() {
+                                    deletions.delete(&dir);
+                                }
+                            }
+                        }
+                    }
+                    app_state.confirm_input.clear();
+                }
+                continue;
+            }
+            if let Some(ref bulk) = app_state.confirm_delete_all {
+                let expected = bulk.ids.len().to_string();
+                if let Some(confirmed) = confirm_count(&mut app_state, key.code, &expected) {
+                    if let Some(bulk) = app_state.confirm_delete_all.take()
+                        && confirmed
+                    {
+                        for id in &bulk.ids {
+                            start_delete(
+                                &results,
+                                &mut deletions,
+                                staging.as_mut(),
+                                id,
+                                &mut app_state,
+                            );
+                        }
+                    }
+                    app_state.confirm_input.clear();
+                }
+                continue;
+            }
             match &app_state.confirm_delete {
-                Some(_) => match key.code {
-                    KeyCode::Char('y') => {
+                Some(path_str) if app_state.confirm_typed => match key.code {
+                    KeyCode::Enter => {
+                        if app_state.confirm_input == expected_confirmation(path_str) {
+                            let path_str = path_str.clone();
+                            app_state.cancel_delete_confirmation();
+                            start_delete(
+                                &results,
+                                &mut deletions,
+                                staging.as_mut(),
+                                &path_str,
+                                &mut app_state,
+                            );
+                        } else if confirm_link.is_some() && app_state.confirm_input == "link" {
+                            let path_str = path_str.clone();
+                            app_state.cancel_delete_confirmation();
+                            if let Err(e) = do_unlink_now(&mut results, &path_str, audit_log) {
+                                app_state.status_message = Some(format!("Not removed: {:#}", e));
+                            }
+                        } else {
+                            app_state.confirm_input.clear();
+                        }
+                    }
+                    KeyCode::Esc => app_state.cancel_delete_confirmation(),
+                    KeyCode::Backspace => {
+                        app_state.confirm_input.pop();
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app_state.confirm_input.push(c)
+                    }
+                    _ => {}
+                },
+                Some(_) if confirm_link.is_some() => match key.code {
+                    KeyCode::Char('t') => {
                         if let Some(path_str) = app_state.confirm_delete.take() {
-                            terminal.draw(|f| {
-                                let confirm = Paragraph::new("Deleting...")
-                                    .style(Style::default().fg(Color::Red))
-                                    .block(Block::default().borders(Borders::BOTTOM));
-                                f.render_widget(confirm, f.area());
-                            })?;
-                            do_delete_now(found_dirs, &path_str);
+                            start_delete(
+                                &results,
+                                &mut deletions,
+                                staging.as_mut(),
+                                &path_str,
+                                &mut app_state,
+                            );
+                        }
+                    }
+                    KeyCode::Char('l') => {
+                        if let Some(path_str) = app_state.confirm_delete.take()
+                            && let Err(e) = do_unlink_now(&mut results, &path_str, audit_log)
+                        {
+                            app_state.status_message = Some(format!("Not removed: {:#}", e));
                         }
                     }
                     KeyCode::Char('n') => {
@@ -288,41 +1960,1145 @@ where
                     }
                     _ => {}
                 },
-                None => match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Char('j') | KeyCode::Down => {
-                        app_state.select_next_or_previous(&filtered_dirs, true)
-                    }
-                    KeyCode::Char('k') | KeyCode::Up => {
-                        app_state.select_next_or_previous(&filtered_dirs, false)
-                    }
-                    KeyCode::Char('s') => app_state.toggle_skip_small(),
-                    KeyCode::Char('o') => app_state.toggle_old_dirs(),
-                    KeyCode::Char('r') => app_state.toggle_sort_order(),
-                    KeyCode::Char('d') => {
-                        if let Some(ref selected_path) = app_state.selected_path {
-                            app_state.request_delete_confirmation(selected_path.clone());
+                Some(_) => match key.code {
+                    KeyCode::Char('y') => {
+                        if let Some(path_str) = app_state.confirm_delete.take() {
+                            start_delete(
+                                &results,
+                                &mut deletions,
+                                staging.as_mut(),
+                                &path_str,
+                                &mut app_state,
+                            );
                         }
                     }
-                    KeyCode::Char('D') => {
-                        if let Some(ref selected_path) = app_state.selected_path {
-                            do_delete_now(found_dirs, selected_path);
-                        }
+                    KeyCode::Char('n') => {
+                        app_state.cancel_delete_confirmation();
                     }
                     _ => {}
                 },
+                None => {
+                    let Some(command) = command else {
+                        continue;
+                    };
+                    match command {
+                        Command::Quit if deletions.is_busy() && !app_state.quit_warned => {
+                            app_state.quit_warned = true;
+                            app_state.status_message = Some(format!(
+                                "Deletions are still running; press {} again to quit anyway",
+                                keymap().key_for(Command::Quit)
+                            ));
+                        }
+                        Command::Quit => break,
+                        Command::Browse if app_state.selected_group.is_some() => {
+                            app_state.toggle_selected_group()
+                        }
+                        Command::Browse => app_state.browse_selected(&filtered_dirs),
+                        Command::Grouping => app_state.cycle_grouping(),
+                        Command::Actions => app_state.open_action_menu(&filtered_dirs),
+                        Command::Inspect => app_state.inspect_selected(&filtered_dirs),
+                        Command::Scope => app_state.show_scope = true,
+                        Command::Help => app_state.help_scroll = Some(0),
+                        Command::Preview => app_state.show_preview = !app_state.show_preview,
+                        Command::NextScreen => app_state.screen = app_state.screen.next(),
+                        Command::Back if app_state.screen != Screen::List => {
+                            app_state.screen = Screen::List
+                        }
+                        Command::History => {
+                            app_state.history_view = Some(history::stats().unwrap_or_else(|e| {
+                                vec![format!("Cannot read the history: {:#}", e)]
+                            }))
+                        }
+                        Command::Undo => {
+                            app_state.status_message = Some(
+                                match staging.as_mut().and_then(|staging| staging.undo(audit_log)) {
+                                    Some(Ok(dir)) => {
+                                        let message = format!("Restored {}", dir.path.display());
+                                        results.apply(crate::scanner::ScanEvent::Found(dir));
+                                        message
+                                    }
+                                    Some(Err(e)) => format!("{:#}", e),
+                                    None => "Nothing to undo".to_string(),
+                                },
+                            );
+                        }
+                        Command::DeletedItems => {
+                            app_state.staged_view =
+                                staging.as_ref().map(|staging| (staging.list(), 0));
+                            if app_state.staged_view.is_none() {
+                                app_state.status_message =
+                                    Some("Deletions aren't staged in this run".to_string());
+                            }
+                        }
+                        Command::Purge => match staging
+                            .as_ref()
+                            .filter(|staging| !staging.is_empty())
+                        {
+                            Some(staging) => {
+                                app_state.confirm_purge = Some(Purge {
+                                    item: None,
+                                    count: staging.len(),
+                                    size: staging.size(),
+                                });
+                                app_state.confirm_typed = options.root_profile;
+                            }
+                            None => app_state.status_message = Some("Nothing to purge".to_string()),
+                        },
+                        Command::CopyPath => app_state.copy_selected_path(),
+                        Command::Open => app_state.open_selected(),
+                        Command::CancelScan if !app_state.scan_complete => scan.cancel(),
+                        Command::Rescan => {
+                            // Start over from scratch, e.g. to check what's left after deleting a batch
+                            scan.restart();
+                            results = ScanResults::default();
+                            app_state.scan_complete = false;
+                        }
+                        Command::RescanEntry => {
+                            if let Some(selected) = app_state
+                                .selected_path
+                                .as_ref()
+                                .and_then(|path| filtered_dirs.iter().find(|dir| dir.id() == *path))
+                            {
+                                app_state.status_message = Some(
+                                    if selected.members.is_empty()
+                                        && selected.path.starts_with(scan.start_dir())
+                                    {
+                                        scan.refresh(&selected.path);
+                                        format!("Rescanning {}", selected.path.display())
+                                    } else {
+                                        format!(
+                                            "{} can only be rescanned with everything else ({})",
+                                            selected.path.display(),
+                                            keymap().key_for(Command::Rescan)
+                                        )
+                                    },
+                                );
+                            }
+                        }
+                        Command::Debug => app_state.show_debug = !app_state.show_debug,
+                        Command::Errors => app_state.error_scroll = Some(0),
+                        Command::Next => app_state.select_next_or_previous(&rows, true),
+                        Command::Previous => app_state.select_next_or_previous(&rows, false),
+                        Command::PageDown => app_state.move_selection(&rows, page),
+                        Command::PageUp => app_state.move_selection(&rows, -page),
+                        Command::HalfPageDown => app_state.move_selection(&rows, page / 2),
+                        Command::HalfPageUp => app_state.move_selection(&rows, -page / 2),
+                        Command::First => app_state.move_selection(&rows, isize::MIN),
+                        Command::Last => app_state.move_selection(&rows, isize::MAX),
+                        Command::CycleMinSize => app_state.toggle_min_size(),
+                        Command::RaiseMinSize => app_state.raise_min_size(),
+                        Command::LowerMinSize => app_state.lower_min_size(),
+                        Command::MinSize => app_state.start_min_size(),
+                        Command::MinFiles => app_state.start_min_files(),
+                        Command::CycleAge => app_state.toggle_old_dirs(),
+                        Command::Age => app_state.start_age(),
+                        Command::CycleConfidence => app_state.toggle_min_confidence(),
+                        Command::CommonTypes => app_state.toggle_type_filter(),
+                        Command::PickTypes => app_state.start_type_picker(&results.dirs),
+                        Command::SizeMode => app_state.toggle_size_mode(),
+                        Command::Sort => app_state.toggle_sort_order(),
+                        Command::ReverseSort => app_state.toggle_sort_direction(),
+                        Command::OwnerColumns => app_state.toggle_ownership_columns(),
+                        Command::DeletableOnly => app_state.toggle_deletable_only(),
+                        Command::Breakdown => app_state.toggle_breakdown(),
+                        Command::PossibleCruft => app_state.toggle_possible_cruft(),
+                        Command::Search => app_state.start_search(),
+                        Command::NextMatch => app_state.jump_to_match(&filtered_dirs, true, false),
+                        Command::PreviousMatch => {
+                            app_state.jump_to_match(&filtered_dirs, false, false)
+                        }
+                        Command::Ignore => {
+                            app_state.toggle_ignore_selected(&filtered_dirs);
+                            save_metadata(&mut app_state, &options);
+                        }
+                        Command::IgnoreSimilar => {
+                            app_state.start_ignore_similar(&filtered_dirs, scan.start_dir())
+                        }
+                        Command::ShowIgnored => app_state.toggle_show_ignored(),
+                        Command::Keep => {
+                            app_state.toggle_keep_selected();
+                            save_metadata(&mut app_state, &options);
+                        }
+                        Command::Note => app_state.start_note(),
+                        Command::RegexFilter => app_state.start_regex(),
+                        Command::DeleteAll => app_state.request_delete_all(
+                            &filtered_dirs,
+                            None,
+                            &deletions,
+                            staging.as_ref(),
+                            &options.confirm_policy,
+                            options.root_profile,
+                        ),
+                        Command::Delete if app_state.selected_group.is_some() => {
+                            let group: Vec<&CruftDirectory> = filtered_dirs
+                                .iter()
+                                .copied()
+                                .filter(|dir| {
+                                    app_state.grouping.label(dir) == app_state.selected_group
+                                })
+                                .collect();
+                            app_state.request_delete_all(
+                                &group,
+                                app_state.selected_group.clone(),
+                                &deletions,
+                                staging.as_ref(),
+                                &options.confirm_policy,
+                                options.root_profile,
+                            )
+                        }
+                        Command::Delete => {
+                            if let Some(selected) = app_state
+                                .selected_path
+                                .as_ref()
+                                .and_then(|path| filtered_dirs.iter().find(|dir| dir.id() == *path))
+                                && app_state.check_not_kept(&selected.id())
+                                && deletions.progress(&selected.path).is_none()
+                            {
+                                // The size decides how it's confirmed, so one from an earlier
+                                // scan is taken again first
+                                if selected.size_cached
+                                    && !selected.pending
+                                    && selected.path.starts_with(scan.start_dir())
+                                {
+                                    scan.refresh(&selected.path);
+                                    app_state.status_message = Some(format!(
+                                        "Sizing {} again before deleting it; press {} once that's done",
+                                        selected.path.display(),
+                                        keymap().key_for(Command::Delete)
+                                    ));
+                                    continue;
+                                }
+                                let size = (!selected.pending && !selected.size_cached)
+                                    .then(|| app_state.size_mode.size_of(selected));
+                                let mut confirmation = if options.root_profile {
+                                    Confirmation::Typed
+                                } else {
+                                    options.confirm_policy.required(selected, size)
+                                };
+                                let mut warning =
+                                    deletion_warning(selected, &OpenFiles::snapshot());
+                                if staging
+                                    .as_ref()
+                                    .is_some_and(|staging| !staging.can_hold(selected))
+                                {
+                                    let permanent = format!("It {}", CANNOT_BE_UNDONE);
+                                    warning = Some(match warning {
+                                        Some(warning) => format!("{} {}", warning, permanent),
+                                        None => permanent,
+                                    });
+                                }
+                                // Something that's worth a warning is worth a second look, however harmless
+                                if warning.is_some() {
+                                    confirmation = confirmation.max(Confirmation::Simple);
+                                }
+                                let id = selected.id();
+                                if confirmation == Confirmation::None {
+                                    start_delete(
+                                        &results,
+                                        &mut deletions,
+                                        staging.as_mut(),
+                                        &id,
+                                        &mut app_state,
+                                    );
+                                } else {
+                                    app_state.request_delete_confirmation(
+                                        id,
+                                        confirmation == Confirmation::Typed,
+                                        warning,
+                                    );
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
             }
         }
     }
 
-    Ok(())
+    Ok(staging)
+}
+
+/// The menu of actions for the selected entry, in the middle of the screen
+fn draw_action_menu(
+    f: &mut ratatui::Frame,
+    menu: &ActionMenu,
+    dir: &CruftDirectory,
+    metadata: &Metadata,
+) {
+    let keys: Vec<String> = menu
+        .actions
+        .iter()
+        .map(|action| keymap().key_for(action.command()))
+        .collect();
+    let width = keys
+        .iter()
+        .map(|key| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = menu
+        .actions
+        .iter()
+        .zip(&keys)
+        .map(|(action, key)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", key),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(action.label(dir, metadata)),
+            ]))
+        })
+        .collect();
+    let title = dir
+        .path
+        .file_name()
+        .map_or(dir.id(), |name| name.to_string_lossy().to_string());
+    let area = f.area();
+    let width = 36.min(area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let menu_area = ratatui::layout::Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(menu.selected));
+    f.render_widget(ratatui::widgets::Clear, menu_area);
+    f.render_stateful_widget(list, menu_area, &mut state);
+}
+
+/// How the list is filtered, sorted and shown right now, for the help
+fn help_settings(app_state: &AppState) -> Vec<(&'static str, String)> {
+    let shown_or_hidden = |shown: bool| if shown { "shown" } else { "hidden" }.to_string();
+    let mut types = app_state.type_filter.as_str().to_string();
+    if !app_state.hidden_types.is_empty() {
+        let mut hidden: Vec<String> = app_state
+            .hidden_types
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+        hidden.sort();
+        types.push_str(&format!(", hiding {}", hidden.join(", ")));
+    }
+    vec![
+        ("Minimum size", app_state.size_filter.label()),
+        ("Age", app_state.age_filter.label()),
+        (
+            "Minimum files",
+            app_state
+                .min_files
+                .map_or("any".to_string(), |files| files.to_string()),
+        ),
+        ("Types", types),
+        (
+            "Confidence",
+            format!("≥ {}", app_state.min_confidence.as_str()),
+        ),
+        (
+            "Owner",
+            app_state
+                .owner_filter
+                .map_or("anyone".to_string(), ownership::user_name),
+        ),
+        (
+            "Deletable only",
+            if app_state.deletable_only {
+                "yes"
+            } else {
+                "no"
+            }
+            .to_string(),
+        ),
+        (
+            "Paths",
+            app_state
+                .path_regex
+                .as_ref()
+                .map_or("all".to_string(), |regex| format!("/{}/", regex)),
+        ),
+        (
+            "Search",
+            app_state
+                .search
+                .as_ref()
+                .map_or("none".to_string(), |query| format!("'{}'", query.as_str())),
+        ),
+        ("Ignored", shown_or_hidden(app_state.show_ignored)),
+        ("Possible cruft", shown_or_hidden(app_state.show_possible)),
+        (
+            "Sort",
+            format!(
+                "{}, {}",
+                app_state.sort_order.as_str(),
+                app_state.sort_order.describe(app_state.sort_direction)
+            ),
+        ),
+        ("Grouped by", app_state.grouping.as_str().to_string()),
+        ("Sizes", app_state.size_mode.as_str().to_string()),
+    ]
+}
+
+/// A scrollbar down the right of the list, when there's more of it than fits; `offset` is
+/// the first row in view
+fn draw_scrollbar(f: &mut ratatui::Frame, area: ratatui::layout::Rect, rows: usize, offset: usize) {
+    let in_view = area.height as usize;
+    if rows <= in_view {
+        return;
+    }
+    let mut state = ScrollbarState::new(rows - in_view)
+        .position(offset)
+        .viewport_content_length(in_view);
+    f.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None),
+        area,
+        &mut state,
+    );
+}
+
+/// The patterns on offer for "ignore all like this", with how many entries each would hide
+fn draw_ignore_similar(
+    f: &mut ratatui::Frame,
+    patterns: &[IgnorePattern],
+    selected: usize,
+    dirs: &[CruftDirectory],
+    metadata: &Metadata,
+) {
+    let items: Vec<ListItem> = patterns
+        .iter()
+        .map(|pattern| {
+            ListItem::new(Line::from(vec![
+                Span::raw(pattern.describe()),
+                Span::styled(
+                    format!(" (hides {})", count_newly_ignored(dirs, metadata, pattern)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    let area = f.area();
+    let width = 80.min(area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup_area = ratatui::layout::Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Ignore all like this (Enter to ignore, Esc to cancel)"),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(selected));
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// The kinds of cruft found, ticked if shown, for picking which to hide (`A`)
+fn draw_type_picker(f: &mut ratatui::Frame, picker: &TypePicker) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let items: Vec<ListItem> = picker
+        .types
+        .iter()
+        .map(|(reason, count, shown)| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("[{}] {}", if *shown { "x" } else { " " }, reason)),
+                Span::styled(format!(" ({} found)", count), dim),
+                Span::styled(
+                    if reason.is_common_cruft() {
+                        ""
+                    } else {
+                        " (not a common type)"
+                    },
+                    dim,
+                ),
+            ]))
+        })
+        .collect();
+    let area = f.area();
+    let width = 70.min(area.width);
+    let height = (items.len().max(1) as u16 + 2).min(area.height);
+    let popup_area = ratatui::layout::Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Types shown (Space to toggle, Enter to apply, Esc to cancel)"),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// What's in the staging area, shown with `T`, newest first
+fn draw_staged(f: &mut ratatui::Frame, items: &[StagedItem], selected: usize) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut list_items: Vec<ListItem> = items
+        .iter()
+        .map(|item| {
+            let days = item
+                .staged_at
+                .elapsed()
+                .map_or(0.0, |elapsed| elapsed.as_secs_f64() / 86400.0);
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:>12} ", format_size(item.size)),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(format!("{:>10} ", format_days_ago(days)), dim),
+                Span::raw(item.original.display().to_string()),
+                Span::styled(
+                    if item.this_session {
+                        " (purged on exit)".to_string()
+                    } else {
+                        " (left by an earlier session)".to_string()
+                    },
+                    dim,
+                ),
+            ]))
+        })
+        .collect();
+    if list_items.is_empty() {
+        list_items.push(ListItem::new(Line::styled("Nothing is staged.", dim)));
+    }
+    let area = f.area();
+    let popup_area = ratatui::layout::Rect::new(
+        area.width / 10,
+        area.height / 10,
+        area.width - area.width / 5,
+        area.height - area.height / 5,
+    );
+    let list = List::new(list_items)
+        .block(Block::default().borders(Borders::ALL).title(
+            "Deleted, not yet purged (Enter to restore, d to purge now, any other key to close)",
+        ))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected((!items.is_empty()).then_some(selected));
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// What couldn't be read during the scan, and so isn't in the sizes, shown with `e`
+fn draw_errors(f: &mut ratatui::Frame, errors: &[String], scroll: usize) {
+    let area = f.area();
+    let popup_area = ratatui::layout::Rect::new(
+        area.width / 10,
+        area.height / 10,
+        area.width - area.width / 5,
+        area.height - area.height / 5,
+    );
+    let lines: Vec<Line> = if errors.is_empty() {
+        vec![Line::styled(
+            "Everything could be read.",
+            Style::default().fg(Color::DarkGray),
+        )]
+    } else {
+        errors
+            .iter()
+            .skip(scroll)
+            .map(|error| Line::raw(error.as_str()))
+            .collect()
+    };
+    let panel = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!(
+        "{} unreadable (j/k to scroll, any other key to close)",
+        errors.len()
+    )));
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_widget(panel, popup_area);
+}
+
+/// The pane beside the list (`v`) with more about the selected entry than fits on its line
+fn draw_preview(
+    f: &mut ratatui::Frame,
+    area: ratatui::layout::Rect,
+    dir: Option<&CruftDirectory>,
+    facts: Option<&Facts>,
+    size_mode: SizeMode,
+) {
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        "Preview ({} to close)",
+        keymap().key_for(Command::Preview)
+    ));
+    let heading = Style::default().add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(Color::DarkGray);
+    let Some(dir) = dir else {
+        f.render_widget(
+            Paragraph::new(Line::styled("Nothing selected", dim)).block(block),
+            area,
+        );
+        return;
+    };
+    let field = |label: &'static str, value: String| {
+        Line::from(vec![Span::styled(label, heading), Span::raw(value)])
+    };
+    let mut lines = vec![
+        Line::styled(dir.path.display().to_string(), heading),
+        field(
+            "Rule: ",
+            format!(
+                "{} ({} confidence)",
+                dir.crufty_reason,
+                dir.confidence.as_str()
+            ),
+        ),
+        field(
+            "Project: ",
+            match (
+                dir.project_name(),
+                dir.project.as_deref().or(dir.path.parent()),
+            ) {
+                (Some(name), Some(project)) => format!("{} ({})", name, project.display()),
+                _ => "none".to_string(),
+            },
+        ),
+        field(
+            "Size: ",
+            if dir.pending {
+                "still being sized".to_string()
+            } else {
+                format!(
+                    "{} ({})",
+                    format_size(size_mode.size_of(dir)),
+                    size_mode.as_str()
+                )
+            },
+        ),
+    ];
+    let file_line =
+        |label: &'static str, file: &Option<(PathBuf, std::time::SystemTime)>| match file {
+            Some((path, modified)) => {
+                let days = modified
+                    .elapsed()
+                    .map_or(0.0, |elapsed| elapsed.as_secs_f64() / 86400.0);
+                field(
+                    label,
+                    format!("{} ({})", path.display(), format_days_ago(days)),
+                )
+            }
+            None => field(label, "(no files)".to_string()),
+        };
+    match facts {
+        Some(facts) => {
+            lines.push(file_line("Newest file: ", &facts.newest));
+            lines.push(file_line("Oldest file: ", &facts.oldest));
+        }
+        None => lines.push(Line::styled(
+            "Looking for the newest and oldest files...",
+            dim,
+        )),
+    }
+    lines.push(Line::styled("Biggest inside:", heading));
+    if dir.pending {
+        lines.push(Line::styled("  (still being sized)", dim));
+    } else if dir.breakdown.is_empty() {
+        lines.push(Line::styled("  (nothing)", dim));
+    } else {
+        let mut breakdown: Vec<(&str, u64)> = dir
+            .breakdown
+            .iter()
+            .map(|entry| {
+                (
+                    entry.name.as_str(),
+                    size_mode.pick(entry.size, entry.disk_usage),
+                )
+            })
+            .collect();
+        breakdown.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        for (name, size) in breakdown.into_iter().take(PREVIEW_CHILDREN) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:>13} ", format_size(size)),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(name.to_string()),
+            ]));
+        }
+    }
+    let panel = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    f.render_widget(panel, area);
 }
 
-fn do_delete_now(found_dirs: &Arc<Mutex<Vec<CruftDirectory>>>, selected_path: &String) {
-    let mut dirs = found_dirs.lock().unwrap();
-    if let Some(cd) = dirs.iter().find(|dir| dir.id() == *selected_path) {
+/// What's inside the entry being browsed, biggest first, shown with Enter
+fn draw_browser(f: &mut ratatui::Frame, browser: &Browser, size_mode: SizeMode) {
+    let area = f.area();
+    let popup_area = ratatui::layout::Rect::new(
+        area.width / 10,
+        area.height / 10,
+        area.width - area.width / 5,
+        area.height - area.height / 5,
+    );
+    let title = format!(
+        "{} (Enter: open, Backspace: up, Esc: close)",
+        browser.current.display()
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let dim = Style::default().fg(Color::DarkGray);
+    let Some(children) = browser.children() else {
+        let panel = Paragraph::new(Line::styled("Sizing everything inside...", dim)).block(block);
+        f.render_widget(ratatui::widgets::Clear, popup_area);
+        f.render_widget(panel, popup_area);
+        return;
+    };
+    let sizes: Vec<u64> = children
+        .iter()
+        .map(|child| size_mode.pick(child.size, child.disk_usage))
+        .collect();
+    let total: u64 = sizes.iter().sum();
+    const BAR_WIDTH: usize = 20;
+    let mut items: Vec<ListItem> = children
+        .iter()
+        .zip(&sizes)
+        .map(|(child, &size)| {
+            let share = if total > 0 {
+                size as f64 / total as f64
+            } else {
+                0.0
+            };
+            let filled = (share * BAR_WIDTH as f64).round() as usize;
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:>15} ", format_size(size)),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(
+                    format!("{:>5.1}% ", share * 100.0),
+                    Style::default().fg(Color::Magenta),
+                ),
+                Span::styled(
+                    format!("{:<width$} ", "█".repeat(filled), width = BAR_WIDTH),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::raw(format!(
+                    "{}{}",
+                    child.name.to_string_lossy(),
+                    if child.is_dir { "/" } else { "" }
+                )),
+            ]))
+        })
+        .collect();
+    if items.is_empty() {
+        items.push(ListItem::new(Line::styled("  (empty)", dim)));
+    }
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    if !children.is_empty() {
+        state.select(Some(browser.selected));
+    }
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Why the entry is flagged, with what's newest and biggest in it, shown with `i`
+fn draw_inspector(
+    f: &mut ratatui::Frame,
+    inspector: &Inspector,
+    dir: &CruftDirectory,
+    size_mode: SizeMode,
+    rebuild_hint: Option<&str>,
+) {
+    let area = f.area();
+    let popup_area = ratatui::layout::Rect::new(
+        area.width / 10,
+        area.height / 10,
+        area.width - area.width / 5,
+        area.height - area.height / 5,
+    );
+    let heading = Style::default().add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::from(vec![
+        Span::styled("Rule: ", heading),
+        Span::raw(format!(
+            "{} ({} confidence)",
+            dir.crufty_reason,
+            dir.confidence.as_str()
+        )),
+    ])];
+    if let Some(hint) = rebuild_hint {
+        lines.push(Line::from(vec![
+            Span::styled("To get it back: ", heading),
+            Span::raw(hint.to_string()),
+        ]));
+    }
+    match inspector.inspection() {
+        None => lines.push(Line::styled("Looking inside...", dim)),
+        Some(inspection) => {
+            lines.push(Line::from(vec![
+                Span::styled("Found: ", heading),
+                Span::raw(inspection.evidence.join(", ")),
+            ]));
+            lines.push(Line::styled("Newest files:", heading));
+            if inspection.newest.is_empty() {
+                lines.push(Line::styled("  (no files)", dim));
+            }
+            for (path, modified) in &inspection.newest {
+                let days = modified
+                    .elapsed()
+                    .map_or(0.0, |elapsed| elapsed.as_secs_f64() / 86400.0);
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:>10} ", format_days_ago(days)), dim),
+                    Span::raw(path.display().to_string()),
+                ]));
+            }
+        }
+    }
+    lines.push(Line::styled("Biggest inside:", heading));
+    if dir.pending {
+        lines.push(Line::styled("  (still being sized)", dim));
+    } else {
+        let mut breakdown: Vec<(&str, u64)> = dir
+            .breakdown
+            .iter()
+            .map(|entry| {
+                (
+                    entry.name.as_str(),
+                    size_mode.pick(entry.size, entry.disk_usage),
+                )
+            })
+            .collect();
+        breakdown.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        if breakdown.is_empty() {
+            lines.push(Line::styled("  (nothing broken down)", dim));
+        }
+        for (name, size) in breakdown.into_iter().take(5) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:>10} ", format_size(size)),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(name.to_string()),
+            ]));
+        }
+    }
+    let panel = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("{} (any key to close)", dir.path.display())),
+    );
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_widget(panel, popup_area);
+}
+
+/// What decruft has freed over time, from `history::stats`
+fn draw_history(f: &mut ratatui::Frame, lines: &[String]) {
+    let area = f.area();
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup_area = ratatui::layout::Rect::new(
+        area.width / 10,
+        area.height.saturating_sub(height) / 2,
+        area.width - area.width / 5,
+        height,
+    );
+    let lines: Vec<Line> = lines.iter().map(|line| Line::raw(line.as_str())).collect();
+    let panel = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("History (any key to close)"),
+    );
+    f.render_widget(ratatui::widgets::Clear, popup_area);
+    f.render_widget(panel, popup_area);
+}
+
+/// A count of files, shortened past ten thousand (`12.3k`, `4.5M`)
+fn format_count(count: u64) -> String {
+    match count {
+        0..10_000 => count.to_string(),
+        10_000..1_000_000 => format!("{:.1}k", count as f64 / 1_000.0),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
+
+fn format_days_ago(days: f64) -> String {
+    if days < 1.0 {
+        "today".to_string()
+    } else {
+        format!("{:.0} days ago", days)
+    }
+}
+
+/// How many of the entries found so far `pattern` would hide that aren't hidden yet
+fn count_newly_ignored(
+    dirs: &[CruftDirectory],
+    metadata: &Metadata,
+    pattern: &IgnorePattern,
+) -> usize {
+    dirs.iter()
+        .filter(|dir| pattern.matches(dir) && !metadata.is_ignored(dir))
+        .count()
+}
+
+/// Internals for working on decruft itself, shown with F12
+fn draw_debug_overlay(f: &mut ratatui::Frame, app_state: &AppState, scan_metrics: &ScanMetrics) {
+    let stats = &app_state.frame_stats;
+    let memory = crate::metrics::resident_memory().map_or("n/a".to_string(), format_size);
+    let lines = vec![
+        Line::from(format!(
+            "frames: {} ({}/s)",
+            stats.frames,
+            stats.frames_per_second()
+        )),
+        Line::from(format!("filter+sort: {:.2?}", stats.last_filter_time)),
+        Line::from(format!("last frame: {} bytes", stats.last_frame_bytes)),
+        Line::from(format!(
+            "average: {} bytes/frame",
+            stats.average_frame_bytes()
+        )),
+        Line::from(format!(
+            "queued: {} dirs, {} to size",
+            scan_metrics.dir_queue.load(Ordering::Relaxed),
+            scan_metrics.size_queue.load(Ordering::Relaxed)
+        )),
+        Line::from(format!("events: {} last frame", stats.last_frame_events)),
+        Line::from(format!(
+            "contention: {} seen-set waits",
+            scan_metrics.reported_contention.load(Ordering::Relaxed)
+        )),
+        Line::from(format!("memory: {}", memory)),
+    ];
+    let area = f.area();
+    let width = 36.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let overlay_area = ratatui::layout::Rect::new(area.width - width, 0, width, height);
+    let overlay =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("debug"));
+    f.render_widget(ratatui::widgets::Clear, overlay_area);
+    f.render_widget(overlay, overlay_area);
+}
+
+/// Renders the "why is this big" tree shown under an expanded entry
+fn breakdown_lines(dir: &CruftDirectory, size_mode: SizeMode) -> Vec<Line<'static>> {
+    if let Some(source) = dir.size_estimated_by {
+        return vec![Line::styled(
+            format!(
+                "{:>17}└─ (size from {}, so no breakdown; see --exact-sizes)",
+                "", source
+            ),
+            Style::default().fg(Color::DarkGray),
+        )];
+    }
+    if dir.breakdown.is_empty() {
+        return vec![Line::styled(
+            format!("{:>17}└─ (empty)", ""),
+            Style::default().fg(Color::DarkGray),
+        )];
+    }
+    let total = size_mode.size_of(dir);
+    let mut breakdown: Vec<(&str, u64)> = dir
+        .breakdown
+        .iter()
+        .map(|entry| {
+            (
+                entry.name.as_str(),
+                size_mode.pick(entry.size, entry.disk_usage),
+            )
+        })
+        .collect();
+    breakdown.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    let last = breakdown.len() - 1;
+    breakdown
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, size))| {
+            let percent = if total > 0 {
+                size as f64 * 100.0 / total as f64
+            } else {
+                0.0
+            };
+            let branch = if i == last { "└─" } else { "├─" };
+            let label = if name == OWN_FILES_LABEL {
+                name.to_string()
+            } else {
+                format!("{}/", name)
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>17}{} ", "", branch),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("{:>12} ", format_size(size)),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(
+                    format!("{:>5.1}% ", percent),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(label),
+            ])
+        })
+        .collect()
+}
+
+fn save_metadata(app_state: &mut AppState, options: &UiOptions) {
+    let result = match options.metadata_path {
+        Some(ref path) => app_state.metadata.save(path),
+        None => Err(anyhow::anyhow!("no data directory to save it in")),
+    };
+    if let Err(e) = result {
+        app_state.status_message = Some(format!("Could not save metadata: {:#}", e));
+    }
+}
+
+fn do_unlink_now(
+    results: &mut ScanResults,
+    selected_path: &String,
+    audit_log: Option<&AuditLog>,
+) -> Result<()> {
+    if let Some(cd) = results.dirs.iter().find(|dir| dir.id() == *selected_path) {
         let path = cd.path.clone();
-        std::fs::remove_dir_all(&path).unwrap();
-        dirs.retain(|dir| dir.path != path);
+        delete::remove_link(cd, audit_log)?;
+        // The target is still there, but no longer reachable from where we scanned
+        results.remove(&path);
+    }
+    Ok(())
+}
+
+/// Why some deletions are for good, unlike the rest: they can't be moved into the staging area
+const CANNOT_BE_UNDONE: &str =
+    "can't be undone, being on another filesystem than the staging area (or scattered about)!";
+
+/// What to warn about before deleting `dir`: processes using it, and files in it that
+/// git tracks
+fn deletion_warning(dir: &CruftDirectory, open_files: &OpenFiles) -> Option<String> {
+    let mut warnings = Vec::new();
+    let users = open_files.users_of(&dir.path);
+    if !users.is_empty() {
+        warnings.push(format!("In use by {}!", open_files::describe(&users)));
+    }
+    if let Some(tracked) = TrackedFiles::in_dir(&dir.path) {
+        warnings.push(tracked.describe());
+    }
+    (!warnings.is_empty()).then(|| warnings.join(" "))
+}
+
+/// A confirmation prompt, after the warning that goes with it and how to get back what it
+/// deletes (if there are either)
+fn full_prompt(warning: &Option<String>, hint: Option<&str>, prompt: String) -> String {
+    let mut full = String::new();
+    if let Some(warning) = warning {
+        full.push_str(warning);
+        full.push(' ');
+    }
+    if let Some(hint) = hint {
+        full.push_str(&format!("To get it back: {}. ", hint));
+    }
+    full.push_str(&prompt);
+    full
+}
+
+/// Takes a key pressed at a confirmation of several deletions: y or n, or under a typed
+/// confirmation, their number (`expected`) and Enter. Whether it was confirmed, once it's
+/// been answered either way.
+fn confirm_count(app_state: &mut AppState, code: KeyCode, expected: &str) -> Option<bool> {
+    match code {
+        KeyCode::Enter if app_state.confirm_typed => {
+            if app_state.confirm_input == expected {
+                Some(true)
+            } else {
+                app_state.confirm_input.clear();
+                None
+            }
+        }
+        KeyCode::Backspace if app_state.confirm_typed => {
+            app_state.confirm_input.pop();
+            None
+        }
+        KeyCode::Char(c) if app_state.confirm_typed => {
+            app_state.confirm_input.push(c);
+            None
+        }
+        KeyCode::Char('y') => Some(true),
+        KeyCode::Esc | KeyCode::Char('n') => Some(false),
+        _ => None,
+    }
+}
+
+/// The text that must be typed to confirm deleting a path, when a typed confirmation is required
+fn expected_confirmation(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// How far the deletion under way has got, against the size found for the entry beforehand
+fn deletion_gauge(dir: &CruftDirectory, progress: Progress, queued: usize) -> Gauge<'static> {
+    let ratio = if dir.size > 0 && !dir.pending {
+        (progress.bytes as f64 / dir.size as f64).min(1.0)
+    } else {
+        0.0
+    };
+    let mut label = format!(
+        "Deleting {}: {} of {} freed, {} files",
+        dir.path.display(),
+        format_size(progress.bytes),
+        format_size(dir.size),
+        progress.files
+    );
+    if progress.bytes > 0 && progress.bytes < dir.size {
+        let remaining = progress
+            .elapsed
+            .mul_f64((dir.size - progress.bytes) as f64 / progress.bytes as f64);
+        label.push_str(&format!(", about {} left", format_eta(remaining)));
+    }
+    if queued > 0 {
+        label.push_str(&format!(" ({} more queued)", queued));
+    }
+    Gauge::default()
+        .gauge_style(Style::default().fg(Color::Red).bg(Color::Black))
+        .ratio(ratio)
+        .label(label)
+}
+
+fn format_eta(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    if secs < 60 {
+        format!("{}s", secs.max(1))
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+/// Hands the entry to the deletion worker, to be moved into the staging area where there is
+/// one, or deleted for good otherwise (or if it can't be staged); it stays listed, showing
+/// progress, until it's gone
+fn start_delete(
+    results: &ScanResults,
+    deletions: &mut DeleteWorker,
+    staging: Option<&mut Staging>,
+    selected_path: &String,
+    app_state: &mut AppState,
+) {
+    let Some(cd) = results.dirs.iter().find(|dir| dir.id() == *selected_path) else {
+        return;
+    };
+    // Aggregates and what's on another filesystem are deleted for good, as their
+    // confirmation said
+    let Some(staging) = staging.filter(|staging| staging.can_hold(cd)) else {
+        deletions.delete(cd);
+        return;
+    };
+    match staging.next_slot() {
+        Ok(at) => deletions.stage(cd, at),
+        Err(e) => app_state.status_message = Some(format!("Not deleted: {:#}", e)),
     }
 }
@@ -0,0 +1,32 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// `[ui] si_units`: sizes are shown in powers of 1000 rather than of 1024
+static SI_UNITS: AtomicBool = AtomicBool::new(false);
+
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+/// Shows sizes in powers of 1000 (as drive makers and macOS count) from here on
+pub fn use_si_units() {
+    SI_UNITS.store(true, Ordering::Relaxed);
+}
+
+/// A size in whichever unit keeps it short, like `512 B`, `1.91 MB` or `35.00 GB`. Units are
+/// powers of 1024, as everywhere else in decruft, unless `[ui] si_units` is set.
+pub fn format_size(bytes: u64) -> String {
+    let base = if SI_UNITS.load(Ordering::Relaxed) {
+        1000.0
+    } else {
+        1024.0
+    };
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= base && unit < UNITS.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
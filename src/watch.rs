@@ -0,0 +1,68 @@
+use crate::metrics::ScanMetrics;
+use crate::scanner::{self, ScanEvent, ScanOptions};
+use anyhow::{Context, Result};
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long things must be quiet before changes are looked at, so that a build's
+/// thousands of writes make for one update rather than thousands
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+/// ...unless they never quiet down, as during a long build
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Keeps the results fresh after the scan, for `--watch`: directories that change are
+/// looked at again as the filesystem reports them, and the outcome sent as scan events.
+pub struct Watch {
+    // Watching stops when this is dropped, and the thread with it
+    _watcher: notify::RecommendedWatcher,
+    events: Receiver<ScanEvent>,
+}
+
+impl Watch {
+    pub fn start(
+        start_dir: PathBuf,
+        options: Arc<ScanOptions>,
+        metrics: Arc<ScanMetrics>,
+    ) -> Result<Self> {
+        let (changes_sender, changes) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    let _ = changes_sender.send(event.paths);
+                }
+            })
+            .context("Cannot watch for changes")?;
+        watcher
+            .watch(&start_dir, RecursiveMode::Recursive)
+            .with_context(|| format!("Cannot watch {} for changes", start_dir.display()))?;
+
+        let (sender, events) = mpsc::channel();
+        std::thread::spawn(move || {
+            while let Ok(paths) = changes.recv() {
+                let mut changed: HashSet<PathBuf> = paths.into_iter().collect();
+                let deadline = Instant::now() + MAX_DELAY;
+                while Instant::now() < deadline {
+                    match changes.recv_timeout(SETTLE_TIME) {
+                        Ok(paths) => changed.extend(paths),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                let changed: Vec<PathBuf> = changed.into_iter().collect();
+                scanner::reevaluate(&start_dir, &options, &changed, &sender, &metrics);
+            }
+        });
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    pub fn events(&self) -> &Receiver<ScanEvent> {
+        &self.events
+    }
+}
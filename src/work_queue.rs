@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+
+/// A work queue for a pool of workers that may add more work while processing,
+/// such as directories found while reading a directory.
+///
+/// `pop` blocks while the queue is empty but other workers are still busy (and may
+/// push more work), and returns `None` once everything has been processed.
+pub struct WorkQueue<T> {
+    state: Mutex<QueueState<T>>,
+    changed: Condvar,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    busy: usize,
+}
+
+impl<T> WorkQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                busy: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn push(&self, item: T) {
+        self.state.lock().unwrap().items.push_back(item);
+        self.changed.notify_one();
+    }
+
+    /// Registers work going on outside the queue that may still push items, so that `pop`
+    /// keeps waiting for it; must be followed by a call to `done` (or a `done_on_drop`
+    /// guard).
+    pub fn hold(&self) {
+        self.state.lock().unwrap().busy += 1;
+    }
+
+    /// The number of items waiting to be taken
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    /// Takes the next item; every item taken must be followed by a call to `done`
+    /// (or a `done_on_drop` guard).
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            // Taking the newest item keeps the walk depth-first-ish, and the queue small
+            if let Some(item) = state.items.pop_back() {
+                state.busy += 1;
+                return Some(item);
+            }
+            if state.busy == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    pub fn done(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.busy -= 1;
+        if state.busy == 0 && state.items.is_empty() {
+            self.changed.notify_all();
+        }
+    }
+
+    /// Calls `done` once the returned guard is dropped, so that a worker that panics
+    /// doesn't leave the others waiting for it forever
+    pub fn done_on_drop(&self) -> Done<'_, T> {
+        Done(self)
+    }
+}
+
+pub struct Done<'a, T>(&'a WorkQueue<T>);
+
+impl<T> Drop for Done<'_, T> {
+    fn drop(&mut self) {
+        self.0.done();
+    }
+}